@@ -4,6 +4,9 @@
 
 use std::fmt::Debug;
 
+use crate::nbt;
+use crate::region::section;
+
 pub enum Value<T> {
     None,
     Default(T),
@@ -21,19 +24,122 @@ pub enum Scope {
     Region,
 }
 
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+}
+
 pub enum Method {
     Name(String),
+    Predicate(nbt::query::predicate::Predicate),
+}
+
+/// A set of chunks, in chunk coordinates.
+pub enum Area {
+    /// Every chunk between two corners, inclusive.
+    Rect{ from: (i32, i32), to: (i32, i32) },
+    /// Chunks no further than `radius` chunks from `center`.
+    Circle{ center: (i32, i32), radius: i32 },
+    /// Chunks whose center lies inside the polygon.
+    Polygon(Vec<(i32, i32)>),
+}
+
+/// Options of the `render` command.
+pub struct Render {
+    /// Every chunk of the dimension when there is none.
+    pub area:       Option<Area>,
+    pub output:     String,
+    pub hillshade:  bool,
+    pub biome_tint: bool,
+}
+
+/// Options of the `maps` command.
+pub struct Maps {
+    /// Every map of the save when empty.
+    pub ids:    Vec<i32>,
+    /// A folder to write one `map_<id>.png` per map to.
+    pub export: Option<String>,
+    /// A PNG file to draw all the maps into, where they lie in the world.
+    pub stitch: Option<String>,
+}
+
+/// Options of the `export-structure` command.
+pub struct ExportStructure {
+    pub area:   BlockBox,
+    /// A `.schem` file gets a Sponge schematic, a `.nbt` file a structure block file.
+    pub output: String,
+}
+
+/// Options of the `paste` command.
+pub struct Paste {
+    /// A `.nbt` file is read as a structure block file, anything else as a Sponge schematic.
+    pub file: String,
+    /// Where the structure's smallest corner goes.
+    pub at:   (i32, i32, i32),
+}
+
+/// Options of the `replace` command.
+pub struct Replace {
+    /// Matches blocks of its name that have every property it gives.
+    pub from: nbt::TAGCompound,
+    pub to:   nbt::TAGCompound,
+    /// Every chunk of the dimension when there is none.
+    pub area: Option<Area>,
+}
+
+/// What `heatmap` shows for each chunk.
+#[derive(Clone, Copy)]
+pub enum Metric {
+    InhabitedTime,
+    LastModified,
+    EntityCount,
+    BlockEntityCount,
+}
+
+/// Options of the `heatmap` command.
+pub struct Heatmap {
+    pub metric: Metric,
+    /// Every chunk of the dimension when there is none.
+    pub area:   Option<Area>,
+    /// A `.csv` file gets the values, anything else a PNG image.
+    pub output: String,
 }
 
 pub enum Command {
     List(Scope),
     Search(Method),
+    Get(nbt::path::Path),
+    Diff(String, String),
+    DiffWorld(String, String),
+    Fsck,
+    Repair,
+    Trim,
+    DeleteChunks(Area),
+    /// Source save, destination save, the chunks to copy and how many chunks to move them by.
+    CopyChunks(String, String, Area, (i32, i32)),
+    Render(Render),
+    Heatmap(Heatmap),
+    Maps(Maps),
+    ExportStructure(ExportStructure),
+    Paste(Paste),
+    Replace(Replace),
 }
 
 pub struct Configuration {
     pub command:   Value<crate::Command>,
     pub save_root: Value<String>,
     pub index:     Value<Index>,
+    pub dimension: Value<Dimension>,
+    pub chunk:     Value<(i32, i32)>,
+    pub file:      Value<String>,
+    pub jobs:      Value<usize>,
+    pub strict:    Value<bool>,
+    pub quarantine: Value<bool>,
+    pub max_inhabited: Value<i64>,
+    pub older_than:    Value<i64>,
+    pub keep_radius:   Value<i64>,
+    pub dry_run:       Value<bool>,
 }
 
 impl<T> Value<T> {
@@ -46,6 +152,171 @@ impl<T> Value<T> {
     }
 }
 
+impl Dimension {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "overworld" | "o"             => Some(Dimension::Overworld),
+            "nether" | "the_nether" | "n" => Some(Dimension::Nether),
+            "end" | "the_end" | "e"       => Some(Dimension::End),
+            _                             => None,
+        }
+    }
+}
+
+impl Metric {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "inhabited_time"     => Some(Metric::InhabitedTime),
+            "last_modified"      => Some(Metric::LastModified),
+            "entity_count"       => Some(Metric::EntityCount),
+            "block_entity_count" => Some(Metric::BlockEntityCount),
+            _                    => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::InhabitedTime    => "inhabited_time",
+            Metric::LastModified     => "last_modified",
+            Metric::EntityCount      => "entity_count",
+            Metric::BlockEntityCount => "block_entity_count",
+        }
+    }
+}
+
+/// Parses a `x,z` coordinate pair.
+pub fn coordinates(arg: &str) -> Option<(i32, i32)> {
+    let (x, z) = arg.split_once(',')?;
+    Some((x.trim().parse().ok()?, z.trim().parse().ok()?))
+}
+
+/// Parses block coordinates like `10,64,-3`.
+pub fn position(arg: &str) -> Option<(i32, i32, i32)> {
+    let mut parts = arg.split(',').map(|part| part.trim().parse().ok());
+    let position = (parts.next()??, parts.next()??, parts.next()??);
+    match parts.next() {
+        Some(_) => None,
+        None    => Some(position),
+    }
+}
+
+/// Smallest and largest corner of a box of blocks, inclusive.
+pub type BlockBox = ((i32, i32, i32), (i32, i32, i32));
+
+/// Parses a box of blocks like `0,60,0:15,80,15` into its smallest and largest corner.
+pub fn block_box(arg: &str) -> Option<BlockBox> {
+    let (a, b) = arg.split_once(':')?;
+    let (a, b) = (position(a)?, position(b)?);
+    Some(((a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)), (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))))
+}
+
+/// Parses a duration like `90d` into seconds. Units are `s`, `m`, `h`, `d` and `w`.
+pub fn duration(arg: &str) -> Option<i64> {
+    let (at, unit) = arg.char_indices().last()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _   => return None,
+    };
+    let count = &arg[..at];
+    count.parse::<i64>().ok().filter(|count| *count >= 0)?.checked_mul(seconds)
+}
+
+impl Area {
+    /// Parses the arguments of `delete-chunks`: `--from x,z --to x,z`, `--center x,z --radius r`
+    /// or `--polygon x,z x,z x,z ...`.
+    pub fn parse(args: &[String]) -> Option<Self> {
+        let (mut from, mut to, mut center, mut radius, mut polygon) = (None, None, None, None, None);
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from"   => from = Some(coordinates(args.next()?)?),
+                "--to"     => to = Some(coordinates(args.next()?)?),
+                "--center" => center = Some(coordinates(args.next()?)?),
+                "--radius" => radius = Some(args.next()?.parse().ok().filter(|radius: &i32| *radius >= 0)?),
+                "--polygon" => {
+                    let vertices = args.by_ref().map(|arg| coordinates(arg)).collect::<Option<Vec<_>>>()?;
+                    if vertices.len() < 3 {
+                        return None;
+                    }
+                    polygon = Some(vertices);
+                },
+                _ => return None,
+            }
+        }
+        match (from, to, center, radius, polygon) {
+            (Some(from), Some(to), None, None, None) => Some(Area::Rect{ from, to }),
+            (None, None, Some(center), Some(radius), None) => Some(Area::Circle{ center, radius }),
+            (None, None, None, None, Some(polygon)) => Some(Area::Polygon(polygon)),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, x: i32, z: i32) -> bool {
+        match self {
+            Area::Rect{ from, to } => {
+                (from.0.min(to.0)..=from.0.max(to.0)).contains(&x) && (from.1.min(to.1)..=from.1.max(to.1)).contains(&z)
+            },
+            Area::Circle{ center, radius } => {
+                /* far apart coordinates square past i64, so the distance is taken in i128 */
+                let (dx, dz) = (x as i128 - center.0 as i128, z as i128 - center.1 as i128);
+                dx*dx + dz*dz <= *radius as i128 * *radius as i128
+            },
+            Area::Polygon(vertices) => {
+                /* even-odd rule: count the edges a ray from the chunk's center towards +x crosses */
+                let (px, pz) = (x as f64 + 0.5, z as f64 + 0.5);
+                let mut inside = false;
+                for (i, a) in vertices.iter().enumerate() {
+                    let b = vertices[(i + 1) % vertices.len()];
+                    let (ax, az, bx, bz) = (a.0 as f64, a.1 as f64, b.0 as f64, b.1 as f64);
+                    if (az > pz) != (bz > pz) && px < ax + (pz - az) * (bx - ax) / (bz - az) {
+                        inside = !inside;
+                    }
+                }
+                inside
+            },
+        }
+    }
+
+    /// The smallest rectangle holding every chunk of the area, as `(min, max)` corners.
+    pub fn bounds(&self) -> ((i32, i32), (i32, i32)) {
+        match self {
+            Area::Rect{ from, to } => ((from.0.min(to.0), from.1.min(to.1)), (from.0.max(to.0), from.1.max(to.1))),
+            Area::Circle{ center, radius } => {
+                ((center.0.saturating_sub(*radius), center.1.saturating_sub(*radius)), (center.0.saturating_add(*radius), center.1.saturating_add(*radius)))
+            },
+            Area::Polygon(vertices) => vertices.iter().fold(((i32::MAX, i32::MAX), (i32::MIN, i32::MIN)), |(min, max), v| {
+                ((min.0.min(v.0), min.1.min(v.1)), (max.0.max(v.0), max.1.max(v.1)))
+            }),
+        }
+    }
+
+    /// Whether the region at region coordinates `rx`, `rz` holds any chunk of the area's bounds.
+    pub fn covers_region(&self, rx: i32, rz: i32) -> bool {
+        let (min, max) = self.bounds();
+        (min.0 >> 5..=max.0 >> 5).contains(&rx) && (min.1 >> 5..=max.1 >> 5).contains(&rz)
+    }
+}
+
+impl Debug for Area {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Area::Rect{ from, to } => f.write_fmt(format_args!("{},{} to {},{}", from.0, from.1, to.0, to.1)),
+            Area::Circle{ center, radius } => f.write_fmt(format_args!("{} around {},{}", radius, center.0, center.1)),
+            Area::Polygon(vertices) => {
+                f.write_str("polygon")?;
+                for vertex in vertices {
+                    f.write_fmt(format_args!(" {},{}", vertex.0, vertex.1))?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
 impl Debug for Command {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -60,8 +331,40 @@ impl Debug for Command {
                 f.write_str("search: ")?;
                 match method {
                     Method::Name(name) => f.write_fmt(format_args!("[name]: \"{}\"", name)),
+                    Method::Predicate(predicate) => f.write_fmt(format_args!("[where]: {}", predicate)),
                 }
             },
+            Command::Get(path) => f.write_fmt(format_args!("get: {}", path)),
+            Command::Diff(old, new) => f.write_fmt(format_args!("diff: {} -> {}", old, new)),
+            Command::DiffWorld(old, new) => f.write_fmt(format_args!("diff-world: {} -> {}", old, new)),
+            Command::Fsck => f.write_str("fsck"),
+            Command::Repair => f.write_str("repair"),
+            Command::Trim => f.write_str("trim"),
+            Command::DeleteChunks(area) => f.write_fmt(format_args!("delete-chunks: {:?}", area)),
+            Command::Render(render) => {
+                f.write_str("render: ")?;
+                match &render.area {
+                    Some(area) => f.write_fmt(format_args!("{:?}", area))?,
+                    None       => f.write_str("all")?,
+                }
+                f.write_fmt(format_args!(" -> {}", render.output))
+            },
+            Command::Heatmap(heatmap) => f.write_fmt(format_args!("heatmap: {} -> {}", heatmap.metric.name(), heatmap.output)),
+            Command::ExportStructure(export) => {
+                let (from, to) = export.area;
+                f.write_fmt(format_args!("export-structure: {},{},{}:{},{},{} -> {}", from.0, from.1, from.2, to.0, to.1, to.2, export.output))
+            },
+            Command::Paste(paste) => f.write_fmt(format_args!("paste: {} at {},{},{}", paste.file, paste.at.0, paste.at.1, paste.at.2)),
+            Command::Replace(replace) => {
+                f.write_fmt(format_args!("replace: {} -> {}", section::state_string(&replace.from), section::state_string(&replace.to)))
+            },
+            Command::Maps(maps) => match maps.ids.is_empty() {
+                true  => f.write_str("maps: all"),
+                false => f.write_fmt(format_args!("maps: {:?}", maps.ids)),
+            },
+            Command::CopyChunks(from, to, area, offset) => {
+                f.write_fmt(format_args!("copy-chunks: {:?} from {} to {} moved by {},{}", area, from, to, offset.0, offset.1))
+            },
         }
     }
 }
@@ -76,6 +379,16 @@ impl Debug for Index {
     }
 }
 
+impl Debug for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dimension::Overworld => f.write_str("overworld"),
+            Dimension::Nether => f.write_str("nether"),
+            Dimension::End => f.write_str("end"),
+        }
+    }
+}
+
 impl<T> Debug for Value<T> 
 where
     T: Debug
@@ -95,6 +408,77 @@ impl Debug for Configuration {
             .field("command", &self.command.value().unwrap())
             .field("root", &self.save_root)
             .field("index", &self.index)
+            .field("dimension", &self.dimension)
+            .field("chunk", &self.chunk)
+            .field("file", &self.file)
+            .field("jobs", &self.jobs)
+            .field("strict", &self.strict)
+            .field("quarantine", &self.quarantine)
+            .field("max_inhabited", &self.max_inhabited)
+            .field("older_than", &self.older_than)
+            .field("keep_radius", &self.keep_radius)
+            .field("dry_run", &self.dry_run)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{duration, Area};
+
+    #[test]
+    fn durations() {
+        assert!(duration("90d") == Some(90 * 24 * 60 * 60));
+        assert!(duration("2w") == Some(14 * 24 * 60 * 60));
+        assert!(duration("0s") == Some(0));
+        assert!(duration("15m") == Some(900));
+        assert!(duration("3h") == Some(10800));
+    }
+
+    #[test]
+    fn bad_durations() {
+        for arg in ["", "d", "90", "-1d", "1y", "1.5h", "9223372036854775807w"] {
+            assert!(duration(arg).is_none(), "'{}' parsed", arg);
+        }
+        /* a multibyte last character is an unknown unit, not a panic */
+        assert!(duration("9é").is_none() && duration("秒").is_none() && duration("1日").is_none());
+    }
+
+    #[test]
+    fn rectangles_include_both_corners() {
+        let area = Area::Rect{ from: (3, -2), to: (-1, 4) };
+        assert!(area.contains(-1, -2) && area.contains(3, 4) && area.contains(0, 0));
+        assert!(!area.contains(4, 0) && !area.contains(0, 5) && !area.contains(-2, -2));
+        assert!(area.bounds() == ((-1, -2), (3, 4)));
+    }
+
+    #[test]
+    fn regions_are_covered_by_the_bounds() {
+        let area = Area::Rect{ from: (-1, 31), to: (32, 32) };
+        assert!(area.covers_region(-1, 0) && area.covers_region(1, 1) && area.covers_region(0, 0));
+        assert!(!area.covers_region(-2, 0) && !area.covers_region(2, 1) && !area.covers_region(0, -1) && !area.covers_region(0, 2));
+        /* a circle covers the corners of its bounds even though it holds no chunk there */
+        let circle = Area::Circle{ center: (0, 0), radius: 40 };
+        assert!(circle.covers_region(-2, -2) && circle.covers_region(1, 1) && !circle.covers_region(2, 0));
+    }
+
+    #[test]
+    fn circles() {
+        let area = Area::Circle{ center: (10, 10), radius: 5 };
+        assert!(area.contains(10, 10) && area.contains(15, 10) && area.contains(13, 14));
+        assert!(!area.contains(14, 14) && !area.contains(16, 10));
+        /* distances that overflow i32 before they are squared */
+        let far = Area::Circle{ center: (i32::MIN, 0), radius: 1 };
+        assert!(far.contains(i32::MIN, 0) && !far.contains(i32::MAX, 0));
+        let wide = Area::Circle{ center: (i32::MAX, i32::MAX), radius: i32::MAX };
+        assert!(wide.contains(0, i32::MAX) && !wide.contains(-1, i32::MAX) && !wide.contains(0, 0));
+    }
+
+    #[test]
+    fn polygons_use_the_chunk_center() {
+        let triangle = Area::Polygon(vec![(0, 0), (10, 0), (0, 10)]);
+        assert!(triangle.contains(0, 0) && triangle.contains(4, 4) && triangle.contains(7, 1));
+        assert!(!triangle.contains(5, 5) && !triangle.contains(-1, 0) && !triangle.contains(10, 0));
+        assert!(triangle.bounds() == ((0, 0), (10, 10)));
+    }
+}
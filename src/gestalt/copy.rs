@@ -0,0 +1,144 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::{nbt, config, region, pool};
+use colored::*;
+
+use super::{Gestalt, Error, Corrupt, directory, back_up, sibling_region_file};
+
+/// A chunk read for `copy-chunks`, already moved to where it goes.
+struct Moved {
+    /// `region`, `entities` or `poi`.
+    folder:    &'static str,
+    x:         i32,
+    z:         i32,
+    root:      nbt::NBT,
+    timestamp: i32,
+}
+
+impl Gestalt {
+    /// Reads the chunks of `area` from a region file and from the same region's `entities` and `poi`
+    /// files, moving each by `offset` chunks.
+    fn copy_from_region_file(path: &std::path::Path, area: &config::Area, offset: (i32, i32)) -> Result<(Vec<Moved>, Vec<Corrupt>), Error> {
+        let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+        let (mut moved, mut corrupt) = (Vec::new(), Vec::new());
+        for folder in ["region", "entities", "poi"] {
+            let file = match folder {
+                "region" => path.to_path_buf(),
+                _ => match sibling_region_file(path, folder) {
+                    Some(file) => file,
+                    None => continue,
+                },
+            };
+            let buffer = match region::open(&file) {
+                Ok(b) => b,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound && folder != "region" => continue,
+                Err(e) => return Err(Error::ReadFile(e)),
+            };
+            let mut parser = region::Parser::new(&buffer).beside(&file);
+            for entry in parser.entries().map_err(Error::Region)? {
+                let (x, z) = (rx * 32 + (entry.index % 32) as i32, rz * 32 + (entry.index / 32) as i32);
+                if !area.contains(x, z) {
+                    continue;
+                }
+                let mut root = match parser.chunk_at(entry.index) {
+                    Ok(Some(chunk)) => chunk.nbt_owned(),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        corrupt.push(Corrupt{ path: file.clone(), index: Some(entry.index), error: Error::Region(e) });
+                        continue;
+                    },
+                };
+                if offset != (0, 0) {
+                    match folder {
+                        "region"   => region::relocate::terrain(&mut root, offset.0, offset.1),
+                        "entities" => region::relocate::entities(&mut root, offset.0, offset.1),
+                        _          => region::relocate::poi(&mut root, offset.0, offset.1),
+                    }
+                }
+                if folder != "poi" {
+                    region::relocate::uuids(&mut root);
+                }
+                moved.push(Moved{ folder, x: x + offset.0, z: z + offset.1, root, timestamp: entry.timestamp });
+            }
+        }
+        Ok((moved, corrupt))
+    }
+
+    /// Writes copied chunks into one region file of the destination, keeping the chunks it had and
+    /// the file as it was as `<file>.bak`.
+    fn copy_into_region_file(path: &std::path::Path, (rx, rz): (i32, i32), moved: &[Moved]) -> Result<(), Error> {
+        let mut writer = region::Writer::new(rx, rz, region::Compression::Zlib);
+        {
+            let buffer = match region::open(path) {
+                Ok(b) => b,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => region::Mapped::default(),
+                Err(e) => return Err(Error::ReadFile(e)),
+            };
+            let mut parser = region::Parser::new(&buffer);
+            for entry in parser.entries().map_err(Error::Region)? {
+                if let Some(stored) = parser.raw(&entry) {
+                    writer.stored(entry.index, entry.timestamp, stored).map_err(Error::Write)?;
+                }
+            }
+        }
+        for chunk in moved {
+            writer.chunk(chunk.x, chunk.z, &chunk.root, chunk.timestamp).map_err(Error::Write)?;
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::ReadFile)?;
+        }
+        back_up(path)?;
+        writer.finish().save(path).map_err(Error::ReadFile)
+    }
+
+    pub(super) fn copy_chunks(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::CopyChunks(from, to, area, offset) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::copy_chunks() should not be called on anything but a config::Command::CopyChunks")));
+        };
+        let Some(dimension) = config.dimension.value() else {
+            return Err(Error::Command(String::from("copy-chunks needs --dimension to tell where the area is")));
+        };
+        let files: Vec<_> = directory::files_in(directory::region_dirs(from).dimension(dimension)).map_err(Error::ReadFile)?
+            .into_iter()
+            .filter(|path| region::coordinates(path).is_some_and(|(rx, rz)| area.covers_region(rx, rz)))
+            .collect();
+
+        /* everything is read before anything is written, so copying within one save can't read its own output */
+        let mut targets: std::collections::BTreeMap<(&str, (i32, i32)), Vec<Moved>> = std::collections::BTreeMap::new();
+        let work = |path: std::path::PathBuf| Self::copy_from_region_file(&path, area, *offset);
+        pool::ordered(*config.jobs.value().unwrap(), files, work, |result| {
+            let (moved, corrupt) = result?;
+            for chunk in moved {
+                targets.entry((chunk.folder, (chunk.x >> 5, chunk.z >> 5))).or_default().push(chunk);
+            }
+            self.skip(&config, corrupt)
+        })?;
+
+        let Some(destination) = directory::region_dirs(to).dimension(dimension).first().map(std::path::PathBuf::from) else {
+            return Ok(());
+        };
+        let dry_run = *config.dry_run.value().unwrap();
+        let mut copied = 0;
+        for ((folder, (rx, rz)), moved) in targets {
+            let Some(dir) = destination.parent() else {
+                continue;
+            };
+            let path = dir.join(folder).join(format!("r.{}.{}.mca", rx, rz));
+            if !dry_run {
+                Self::copy_into_region_file(&path, (rx, rz), &moved)?;
+            }
+            println!("{}: {} chunks", path.display(), moved.len());
+            if folder == "region" {
+                copied += moved.len();
+            }
+        }
+        if dry_run {
+            println!("{}", format!("dry run, nothing was changed: {} chunks would be copied", copied).yellow());
+        } else {
+            println!("chunks copied: {}", copied);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::{nbt, config, region};
+use colored::*;
+
+use super::{Gestalt, Error, Corrupt, directory, dimensions, read_nbt_file, chunk_in_save, scan_region_file, plural};
+
+/// `items` by the header slot `index` gives for each, for lookups by slot.
+fn by_slot<T>(items: &[T], index: impl Fn(&T) -> usize) -> Vec<Option<&T>> {
+    let mut slots = vec![None; 1024];
+    for item in items {
+        slots[index(item)] = Some(item);
+    }
+    slots
+}
+
+impl Gestalt {
+    fn print_differences(old: &nbt::NBT, new: &nbt::NBT, location: Option<(i32, i32)>) -> usize {
+        let differences = nbt::diff::diff(old, new);
+        for difference in differences.iter() {
+            match location {
+                Some((x, z)) => println!("[{}, {}] {}", x, z, difference),
+                None         => println!("{}", difference),
+            }
+        }
+        differences.len()
+    }
+
+    /// Pairs up the chunks of two versions of the same region file by header index and diffs each pair.
+    /// Chunks that fail to decode on either side are returned instead of compared.
+    fn diff_region_files(old: &std::path::Path, new: &std::path::Path) -> (usize, Vec<Corrupt>) {
+        let (rx, rz) = region::coordinates(new).or(region::coordinates(old)).unwrap_or((0, 0));
+        let mut corrupt = Vec::new();
+        let mut scan = |file: &std::path::Path| match scan_region_file(file) {
+            Ok((chunks, mut skipped)) => {
+                corrupt.append(&mut skipped);
+                Some(chunks)
+            },
+            Err(error) => {
+                corrupt.push(Corrupt{ path: file.to_path_buf(), index: None, error });
+                None
+            },
+        };
+        let (Some(old_chunks), Some(new_chunks)) = (scan(old), scan(new)) else {
+            return (0, corrupt);
+        };
+        let mut unreadable = vec![false; 1024];
+        for index in corrupt.iter().filter_map(|corrupt| corrupt.index) {
+            unreadable[index] = true;
+        }
+        let (old_chunks, new_chunks) = (by_slot(&old_chunks, |chunk| chunk.index()), by_slot(&new_chunks, |chunk| chunk.index()));
+        let mut changes = 0;
+
+        for index in (0..1024).filter(|index| !unreadable[*index]) {
+            let x = rx * 32 + (index % 32) as i32;
+            let z = rz * 32 + (index / 32) as i32;
+            match (old_chunks[index], new_chunks[index]) {
+                (Some(was), Some(now)) => changes += Self::print_differences(was.nbt(), now.nbt(), Some((x, z))),
+                (Some(_), None) => {
+                    println!("{}", format!("- [{}, {}] chunk", x, z).red());
+                    changes += 1;
+                },
+                (None, Some(_)) => {
+                    println!("{}", format!("+ [{}, {}] chunk", x, z).green());
+                    changes += 1;
+                },
+                (None, None) => (),
+            }
+        }
+        (changes, corrupt)
+    }
+
+    pub(super) fn diff(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::Diff(old, new) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::diff() should not be called on anything but a config::Command::Diff")));
+        };
+
+        let changes = if directory::is_region_name(std::path::Path::new(old)) && directory::is_region_name(std::path::Path::new(new)) {
+            let (changes, corrupt) = Self::diff_region_files(std::path::Path::new(old), std::path::Path::new(new));
+            self.skip(&config, corrupt)?;
+            changes
+        } else if std::path::Path::new(old).is_file() && std::path::Path::new(new).is_file() {
+            Self::print_differences(&read_nbt_file(old)?, &read_nbt_file(new)?, None)
+        } else if let Some((x, z)) = config.chunk.value() {
+            let dimension = config.dimension.value().unwrap_or(&config::Dimension::Overworld);
+            let was = chunk_in_save(old, dimension, *x, *z)?;
+            let now = chunk_in_save(new, dimension, *x, *z)?;
+            match (was, now) {
+                (Some(was), Some(now)) => Self::print_differences(was.nbt(), now.nbt(), Some((*x, *z))),
+                (None, None) => {
+                    println!("chunk [{}, {}] exists in neither save.", x, z);
+                    return Ok(());
+                },
+                (was, _) => {
+                    let side = if was.is_none() { old } else { new };
+                    println!("chunk [{}, {}] has not been generated in {}.", x, z, side);
+                    return Ok(());
+                },
+            }
+        } else {
+            let mut changes = 0;
+            for dimension in dimensions(&config) {
+                println!("{:?}:", dimension);
+                for (was, now) in directory::region_file_pairs(old, new, dimension).map_err(Error::ReadFile)? {
+                    let (region, corrupt) = Self::diff_region_files(&was, &now);
+                    changes += region;
+                    self.skip(&config, corrupt)?;
+                }
+            }
+            changes
+        };
+
+        println!("{} difference{}", changes, plural(changes));
+        Ok(())
+    }
+
+    /// Compares two versions of a region file header first and only decodes chunks whose stored
+    /// bytes differ. Returns the added, deleted and modified chunk counts, and the chunks that
+    /// couldn't be compared.
+    fn diff_region_headers(old: &std::path::Path, new: &std::path::Path) -> Result<([usize; 3], Vec<Corrupt>), Error> {
+        let read = |file: &std::path::Path| match region::open(file) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(region::Mapped::default()),
+            result => result,
+        };
+        let (old_buffer, new_buffer) = (read(old).map_err(Error::ReadFile)?, read(new).map_err(Error::ReadFile)?);
+        if *old_buffer == *new_buffer {
+            return Ok(([0; 3], Vec::new()));
+        }
+
+        let (rx, rz) = region::coordinates(new).or(region::coordinates(old)).unwrap_or((0, 0));
+        let (mut was_region, mut now_region) = (region::Parser::new(&old_buffer).beside(old), region::Parser::new(&new_buffer).beside(new));
+        let mut corrupt = Vec::new();
+        let (was_entries, now_entries) = match (was_region.entries(), now_region.entries()) {
+            (Ok(was), Ok(now)) => (was, now),
+            (Err(e), _) => return Ok(([0; 3], vec![Corrupt{ path: old.to_path_buf(), index: None, error: Error::Region(e) }])),
+            (_, Err(e)) => return Ok(([0; 3], vec![Corrupt{ path: new.to_path_buf(), index: None, error: Error::Region(e) }])),
+        };
+        let (was_entries, now_entries) = (by_slot(&was_entries, |entry| entry.index), by_slot(&now_entries, |entry| entry.index));
+        let mut counts = [0; 3];
+
+        for index in 0..1024 {
+            let x = rx * 32 + (index % 32) as i32;
+            let z = rz * 32 + (index / 32) as i32;
+            let (was, now) = match (was_entries[index], now_entries[index]) {
+                (None, None) => continue,
+                (None, Some(_)) => {
+                    println!("{}", format!("+ [{}, {}]", x, z).green());
+                    counts[0] += 1;
+                    continue;
+                },
+                (Some(_), None) => {
+                    println!("{}", format!("- [{}, {}]", x, z).red());
+                    counts[1] += 1;
+                    continue;
+                },
+                (Some(was), Some(now)) => (was, now),
+            };
+
+            /* the game rewrites a chunk's timestamp on every save, so an unchanged header means an unchanged chunk */
+            if was.timestamp == now.timestamp && was.offset == now.offset && was.sectors == now.sectors {
+                continue;
+            }
+            let checksum = |stored: &[u8]| {
+                let mut crc = flate2::Crc::new();
+                crc.update(stored);
+                crc.sum()
+            };
+            /* stored bytes that can't be read are left to decoding, which reports them */
+            if was_region.raw(was).zip(now_region.raw(now)).is_some_and(|(was, now)| checksum(was) == checksum(now)) {
+                continue;
+            }
+
+            let was_chunk = match was_region.chunk_at(index) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    corrupt.push(Corrupt{ path: old.to_path_buf(), index: Some(index), error: Error::Region(e) });
+                    continue;
+                },
+            };
+            let now_chunk = match now_region.chunk_at(index) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    corrupt.push(Corrupt{ path: new.to_path_buf(), index: Some(index), error: Error::Region(e) });
+                    continue;
+                },
+            };
+            let (Some(was_chunk), Some(now_chunk)) = (was_chunk, now_chunk) else {
+                continue;
+            };
+            let differences = nbt::diff::diff(was_chunk.nbt(), now_chunk.nbt()).len();
+            if differences != 0 {
+                println!("{}", format!("~ [{}, {}] changed tags: {}", x, z, differences).yellow());
+                counts[2] += 1;
+            }
+        }
+        Ok((counts, corrupt))
+    }
+
+    pub(super) fn diff_world(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::DiffWorld(old, new) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::diff_world() should not be called on anything but a config::Command::DiffWorld")));
+        };
+
+        for dimension in dimensions(&config) {
+            println!("{:?}:", dimension);
+            let mut counts = [0; 3];
+            for (was, now) in directory::region_file_pairs(old, new, dimension).map_err(Error::ReadFile)? {
+                let (region, corrupt) = match Self::diff_region_headers(&was, &now) {
+                    Ok(diffed) => diffed,
+                    Err(error) => ([0; 3], vec![Corrupt{ path: now, index: None, error }]),
+                };
+                self.skip(&config, corrupt)?;
+                for (count, region) in counts.iter_mut().zip(region) {
+                    *count += region;
+                }
+            }
+            println!("\t{} added, {} deleted, {} modified", counts[0], counts[1], counts[2]);
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::Gestalt;
+    use crate::region::{Compression, Writer};
+
+    fn chunk(x: i32, z: i32, status: &str) -> crate::nbt::NBT {
+        crate::nbt!{ "xPos": (x), "zPos": (z), "Status": (status) }
+    }
+
+    /// A fresh folder under the system's temporary directory.
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rave-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(std::fs::create_dir_all(&dir).is_ok());
+        dir
+    }
+
+    #[test]
+    fn header_diff_counts_chunk_changes() {
+        let dir = scratch("diff-world");
+        let (old, new) = (dir.join("old").join("r.0.0.mca"), dir.join("new").join("r.0.0.mca"));
+        assert!(std::fs::create_dir_all(old.parent().unwrap()).is_ok() && std::fs::create_dir_all(new.parent().unwrap()).is_ok());
+
+        /* 0 is deleted, 1 is only saved again, 2 is modified, 3 is untouched and 4 is added */
+        let mut writer = Writer::new(0, 0, Compression::Zlib);
+        for (x, status) in [(0, "full"), (1, "full"), (2, "full"), (3, "full")] {
+            assert!(writer.chunk(x, 0, &chunk(x, 0, status), 10).is_ok());
+        }
+        assert!(writer.finish().save(&old).is_ok());
+        let mut writer = Writer::new(0, 0, Compression::Zlib);
+        for (x, status, timestamp) in [(1, "full", 20), (2, "features", 20), (3, "full", 10), (4, "full", 20)] {
+            assert!(writer.chunk(x, 0, &chunk(x, 0, status), timestamp).is_ok());
+        }
+        assert!(writer.finish().save(&new).is_ok());
+
+        let Ok((counts, corrupt)) = Gestalt::diff_region_headers(&old, &new) else {
+            panic!("the regions didn't compare");
+        };
+        assert!(counts == [1, 1, 1]);
+        assert!(corrupt.is_empty());
+        assert!(Gestalt::diff_region_headers(&old, &dir.join("missing").join("r.0.0.mca")).is_ok_and(|(counts, _)| counts == [0, 4, 0]));
+
+        /* chunk 3 pointing past the end of both files is reported, not taken as unchanged */
+        for (path, timestamp) in [(&old, 10u32), (&new, 20)] {
+            let mut bytes = std::fs::read(path).unwrap_or_default();
+            bytes[12..16].copy_from_slice(&(200u32 << 8 | 1).to_be_bytes());
+            bytes[4096+12..4096+16].copy_from_slice(&timestamp.to_be_bytes());
+            assert!(std::fs::write(path, bytes).is_ok());
+        }
+        let Ok((counts, corrupt)) = Gestalt::diff_region_headers(&old, &new) else {
+            panic!("the damaged regions didn't compare");
+        };
+        assert!(counts == [1, 1, 1]);
+        assert!(corrupt.len() == 1 && corrupt[0].index == Some(3));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
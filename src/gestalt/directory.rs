@@ -0,0 +1,88 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use std::path::{Path, PathBuf};
+use crate::config;
+
+pub(crate) type List = Vec<String>;
+
+pub(crate) struct WorldFile {
+    pub overworld: List,
+    pub nether:    List,
+    pub the_end:   List,
+}
+
+pub(crate) fn region_files(config: &config::Configuration) -> WorldFile {
+    region_dirs(config.save_root.value().unwrap())
+}
+
+pub(crate) fn region_dirs(base: &str) -> WorldFile {
+    let mut bundle = WorldFile{
+        overworld: Vec::new(), 
+        nether: Vec::new(),
+        the_end: Vec::new(),
+    };
+    bundle.overworld.push(format!("{}/region", base));
+    bundle.nether.push(format!("{}/DIM-1/region", base));
+    bundle.the_end.push(format!("{}/DIM1/region", base));
+    bundle
+}
+
+impl WorldFile {
+    pub(crate) fn dimension(&self, dimension: &config::Dimension) -> &List {
+        match dimension {
+            config::Dimension::Overworld => &self.overworld,
+            config::Dimension::Nether    => &self.nether,
+            config::Dimension::End       => &self.the_end,
+        }
+    }
+}
+
+/// Matching region files of one dimension in two saves, by file name. Either side of a pair may not exist.
+/// Like `files_in`, only `r.<x>.<z>.mca` files are paired.
+pub(crate) fn region_file_pairs(old: &str, new: &str, dimension: &config::Dimension) -> Result<Vec<(PathBuf, PathBuf)>, std::io::Error> {
+    let (old_dirs, new_dirs) = (region_dirs(old), region_dirs(new));
+    let mut pairs = Vec::new();
+    for (old_dir, new_dir) in old_dirs.dimension(dimension).iter().zip(new_dirs.dimension(dimension)) {
+        let mut names = Vec::new();
+        for dir in [old_dir, new_dir] {
+            let Ok(files) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for file in files {
+                let name = file?.file_name();
+                if is_region_name(Path::new(&name)) {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+        for name in names {
+            pairs.push((Path::new(old_dir).join(&name), Path::new(new_dir).join(&name)));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Every region file in `dirs`, sorted so work spread over threads is still reported in a stable order.
+/// Backups and quarantine files left next to them by `repair` are skipped.
+pub(crate) fn files_in(dirs: &List) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        for file in std::fs::read_dir(dir)? {
+            let path = file?.path();
+            if is_region_name(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Whether `path` names a region file, leaving out `.mcc` chunks and what `repair` leaves behind.
+pub(crate) fn is_region_name(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "mca") && crate::region::coordinates(path).is_some()
+}
@@ -0,0 +1,355 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::{nbt, config, region, pool, structure};
+use colored::*;
+
+use super::{Gestalt, Error, Corrupt, directory, dimensions, back_up, unix_now, sibling_region_file, read_nbt_file};
+
+/// What `paste` did to one region file and its entities.
+#[derive(Default)]
+struct Pasted {
+    chunks:   usize,
+    blocks:   usize,
+    skipped:  usize,
+    block_entities: usize,
+    entities: usize,
+    /// Chunks the structure covers that were never generated.
+    missing:  usize,
+}
+
+/// What `replace` did to one region file.
+struct Swapped {
+    chunks:   usize,
+    replaced: region::section::Replaced,
+    corrupt:  Vec<Corrupt>,
+}
+
+impl Gestalt {
+    pub(super) fn export_structure(&mut self, config: config::Configuration) -> Result<(), Error> {
+        use nbt::access::Lookup;
+
+        let config::Command::ExportStructure(options) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::export_structure() should not be called on anything but a config::Command::ExportStructure")));
+        };
+        let (min, max) = options.area;
+        let size = (max.0 as i64 - min.0 as i64 + 1, max.1 as i64 - min.1 as i64 + 1, max.2 as i64 - min.2 as i64 + 1);
+        /* sponge schematics store each side as an unsigned short */
+        if size.0 > u16::MAX as i64 || size.1 > u16::MAX as i64 || size.2 > u16::MAX as i64 || size.0 * size.1 * size.2 > structure::MAX_BLOCKS as i64 {
+            return Err(Error::Command(format!("export-structure area of {}x{}x{} blocks is too large, give a smaller area", size.0, size.1, size.2)));
+        }
+        let dimension = config.dimension.value().unwrap_or(&config::Dimension::Overworld);
+        let mut extract = structure::Extract::new(min, max);
+        let (mut missing, mut unreadable, mut corrupt) = (0, 0, Vec::new());
+        for dir in directory::region_files(&config).dimension(dimension) {
+            for rz in min.2 >> 9..=max.2 >> 9 {
+                for rx in min.0 >> 9..=max.0 >> 9 {
+                    let path = std::path::Path::new(dir).join(format!("r.{}.{}.mca", rx, rz));
+                    let chunks: Vec<(i32, i32)> = (min.2 >> 4..=max.2 >> 4).filter(|cz| cz >> 5 == rz)
+                        .flat_map(|cz| (min.0 >> 4..=max.0 >> 4).filter(move |cx| cx >> 5 == rx).map(move |cx| (cx, cz)))
+                        .collect();
+                    let buffer = match region::open(&path) {
+                        Ok(b) => b,
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                            missing += chunks.len();
+                            continue;
+                        },
+                        Err(e) => return Err(Error::ReadFile(e)),
+                    };
+                    let mut parser = region::Parser::new(&buffer).beside(&path);
+                    for (cx, cz) in &chunks {
+                        let index = (cx & 31) as usize + (cz & 31) as usize * 32;
+                        match parser.chunk_at(index) {
+                            Ok(Some(chunk)) => unreadable += extract.terrain(*cx, *cz, chunk.nbt()),
+                            Ok(None) => missing += 1,
+                            Err(e)   => corrupt.push(Corrupt{ path: path.clone(), index: Some(index), error: Error::Region(e) }),
+                        }
+                    }
+
+                    /* since 1.17 entities are kept in their own folder */
+                    let Some(entities) = sibling_region_file(&path, "entities") else {
+                        continue;
+                    };
+                    let buffer = match region::open(&entities) {
+                        Ok(b) => b,
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                        Err(e) => return Err(Error::ReadFile(e)),
+                    };
+                    let mut parser = region::Parser::new(&buffer).beside(&entities);
+                    for (cx, cz) in &chunks {
+                        let index = (cx & 31) as usize + (cz & 31) as usize * 32;
+                        match parser.chunk_at(index) {
+                            Ok(Some(chunk)) => extract.entities(chunk.nbt()),
+                            Ok(None) => (),
+                            Err(e)   => corrupt.push(Corrupt{ path: entities.clone(), index: Some(index), error: Error::Region(e) }),
+                        }
+                    }
+                }
+            }
+        }
+        self.skip(&config, corrupt)?;
+
+        let level = read_nbt_file(&format!("{}/level.dat", config.save_root.value().unwrap())).ok();
+        let data_version = level.as_ref()
+            .and_then(|level| level.payload.as_compound().and_then(|level| level.get_compound("Data")).ok())
+            .and_then(|data| data.get_int("DataVersion").ok())
+            .unwrap_or(0);
+        let structure = extract.finish(data_version);
+        let root = match options.output.ends_with(".schem") {
+            true  => structure::sponge::write(&structure, unix_now().saturating_mul(1000)),
+            false => structure::vanilla::write(&structure),
+        };
+        let mut writer = nbt::Writer::new();
+        writer.write(&root).map_err(Error::Parse)?;
+        /* both formats are gzipped like level.dat */
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, &writer.bytes()).map_err(Error::ReadFile)?;
+        std::fs::write(&options.output, encoder.finish().map_err(Error::ReadFile)?).map_err(Error::ReadFile)?;
+
+        println!("{} blocks in {} states, {} block entities and {} entities written to {}",
+            structure.volume(), structure.palette.len(), structure.block_entities.len(), structure.entities.len(), options.output);
+        if missing > 0 || unreadable > 0 {
+            println!("{}", format!("{} chunks were never generated and {} sections couldn't be read, they were saved as air", missing, unreadable).yellow());
+        }
+        Ok(())
+    }
+
+    /// Pastes the part of `structure` that lands in one region file, then its entities into the
+    /// region's `entities` file for chunks saved since entities moved there. Both files are kept as
+    /// they were as `<file>.bak`.
+    fn paste_into_region_file(path: &std::path::Path, (rx, rz): (i32, i32), structure: &structure::Structure, at: (i32, i32, i32), dry_run: bool) -> Result<(Pasted, Vec<Corrupt>), Error> {
+        let ((min_x, min_z), (max_x, max_z)) = structure.chunks(at);
+        let covered = |cx: i32, cz: i32| (min_x..=max_x).contains(&cx) && (min_z..=max_z).contains(&cz);
+        let wanted = (min_z.max(rz * 32)..=max_z.min(rz * 32 + 31)).count() * (min_x.max(rx * 32)..=max_x.min(rx * 32 + 31)).count();
+        let (mut pasted, mut corrupt) = (Pasted::default(), Vec::new());
+        let buffer = match region::open(path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                pasted.missing = wanted;
+                return Ok((pasted, corrupt));
+            },
+            Err(e) => return Err(Error::ReadFile(e)),
+        };
+        let mut parser = region::Parser::new(&buffer).beside(path);
+        let mut writer = region::Writer::new(rx, rz, region::Compression::Zlib);
+        let now = unix_now() as i32;
+        /* chunks pasted into, with the data version they were saved with */
+        let mut present = Vec::new();
+        for entry in parser.entries().map_err(Error::Region)? {
+            /* copied first so a rewritten chunk replaces the stub and its .mcc file goes stale */
+            if let Some(stored) = parser.raw(&entry) {
+                writer.stored(entry.index, entry.timestamp, stored).map_err(Error::Write)?;
+            }
+            let (cx, cz) = (rx * 32 + (entry.index % 32) as i32, rz * 32 + (entry.index / 32) as i32);
+            if covered(cx, cz) {
+                match parser.chunk_at(entry.index) {
+                    Ok(Some(chunk)) => {
+                        let mut root = chunk.nbt_owned();
+                        let placed = structure.paste(at, cx, cz, &mut root);
+                        pasted.chunks += 1;
+                        pasted.blocks += placed.blocks;
+                        pasted.skipped += placed.skipped;
+                        pasted.block_entities += placed.block_entities;
+                        pasted.entities += placed.entities;
+                        let data_version = root.payload.as_compound().ok().and_then(|root| nbt::access::Lookup::get_int(root, "DataVersion").ok()).unwrap_or(0);
+                        present.push(((cx, cz), data_version));
+                        writer.chunk(cx, cz, &root, now).map_err(Error::Write)?;
+                    },
+                    Ok(None) => (),
+                    Err(e) => corrupt.push(Corrupt{ path: path.to_path_buf(), index: Some(entry.index), error: Error::Region(e) }),
+                }
+            }
+        }
+        pasted.missing = wanted - present.len() - corrupt.len();
+        if present.is_empty() {
+            return Ok((pasted, corrupt));
+        }
+        if !dry_run {
+            back_up(path)?;
+            writer.finish().save(path).map_err(Error::ReadFile)?;
+        }
+
+        let mut entity_chunks: Vec<((i32, i32), i32)> = present.into_iter()
+            .filter(|((cx, cz), data_version)| *data_version >= structure::paste::ENTITY_FOLDER && structure.has_entities(at, *cx, *cz))
+            .collect();
+        let Some(file) = sibling_region_file(path, "entities").filter(|_| !entity_chunks.is_empty()) else {
+            return Ok((pasted, corrupt));
+        };
+        let buffer = match region::open(&file) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => region::Mapped::default(),
+            Err(e) => return Err(Error::ReadFile(e)),
+        };
+        let mut parser = region::Parser::new(&buffer).beside(&file);
+        let mut writer = region::Writer::new(rx, rz, region::Compression::Zlib);
+        for entry in parser.entries().map_err(Error::Region)? {
+            /* copied first so a rewritten chunk replaces the stub and its .mcc file goes stale */
+            if let Some(stored) = parser.raw(&entry) {
+                writer.stored(entry.index, entry.timestamp, stored).map_err(Error::Write)?;
+            }
+            let (cx, cz) = (rx * 32 + (entry.index % 32) as i32, rz * 32 + (entry.index / 32) as i32);
+            if let Some(at_chunk) = entity_chunks.iter().position(|(chunk, _)| *chunk == (cx, cz)) {
+                match parser.chunk_at(entry.index) {
+                    Ok(Some(chunk)) => {
+                        let (_, data_version) = entity_chunks.remove(at_chunk);
+                        let (root, placed) = structure.paste_entities(at, cx, cz, Some(chunk.nbt_owned()), data_version);
+                        pasted.entities += placed;
+                        writer.chunk(cx, cz, &root, now).map_err(Error::Write)?;
+                    },
+                    Ok(None) => (),
+                    /* keep what's there rather than replace entities that can't be read */
+                    Err(e) => {
+                        entity_chunks.remove(at_chunk);
+                        corrupt.push(Corrupt{ path: file.clone(), index: Some(entry.index), error: Error::Region(e) });
+                    },
+                }
+            }
+        }
+        for ((cx, cz), data_version) in entity_chunks {
+            let (root, placed) = structure.paste_entities(at, cx, cz, None, data_version);
+            pasted.entities += placed;
+            writer.chunk(cx, cz, &root, now).map_err(Error::Write)?;
+        }
+        if !dry_run {
+            if let Some(dir) = file.parent() {
+                std::fs::create_dir_all(dir).map_err(Error::ReadFile)?;
+            }
+            back_up(&file)?;
+            writer.finish().save(&file).map_err(Error::ReadFile)?;
+        }
+        Ok((pasted, corrupt))
+    }
+
+    pub(super) fn paste(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::Paste(options) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::paste() should not be called on anything but a config::Command::Paste")));
+        };
+        let root = read_nbt_file(&options.file)?;
+        let structure = match options.file.ends_with(".nbt") {
+            true  => structure::vanilla::read(&root),
+            false => structure::sponge::read(&root),
+        }.map_err(Error::Structure)?;
+        let at = options.at;
+        if structure.volume() == 0 {
+            return Err(Error::Command(format!("{} holds no blocks", options.file)));
+        }
+        let far = [(at.0, structure.size.0), (at.1, structure.size.1), (at.2, structure.size.2)];
+        if far.iter().any(|(corner, side)| *corner as i64 + *side as i64 - 1 > i32::MAX as i64) {
+            return Err(Error::Command(String::from("the structure would reach past the edge of the world")));
+        }
+
+        let dimension = config.dimension.value().unwrap_or(&config::Dimension::Overworld);
+        let dry_run = *config.dry_run.value().unwrap();
+        let ((min_x, min_z), (max_x, max_z)) = structure.chunks(at);
+        let mut total = Pasted::default();
+        for dir in directory::region_files(&config).dimension(dimension) {
+            for rz in min_z >> 5..=max_z >> 5 {
+                for rx in min_x >> 5..=max_x >> 5 {
+                    let path = std::path::Path::new(dir).join(format!("r.{}.{}.mca", rx, rz));
+                    let (pasted, corrupt) = Self::paste_into_region_file(&path, (rx, rz), &structure, at, dry_run)?;
+                    total.chunks += pasted.chunks;
+                    total.blocks += pasted.blocks;
+                    total.skipped += pasted.skipped;
+                    total.block_entities += pasted.block_entities;
+                    total.entities += pasted.entities;
+                    total.missing += pasted.missing;
+                    self.skip(&config, corrupt)?;
+                }
+            }
+        }
+
+        let verb = if dry_run { "would paste" } else { "pasted" };
+        let summary = format!("{} {} blocks, {} block entities and {} entities into {} chunks",
+            verb, total.blocks, total.block_entities, total.entities, total.chunks);
+        match dry_run {
+            true  => println!("{}", summary.yellow()),
+            false => println!("{}", summary),
+        }
+        if total.missing > 0 || total.skipped > 0 {
+            println!("{}", format!("{} chunks were never generated and {} blocks fell outside the sections chunks have, they were left out", total.missing, total.skipped).yellow());
+        }
+        Ok(())
+    }
+
+    /// Replaces blocks in the chunks of `area` in one region file, rewriting it when any changed and
+    /// keeping the original as `<file>.bak`.
+    fn replace_in_region_file(path: &std::path::Path, options: &config::Replace, dry_run: bool) -> Result<Swapped, Error> {
+        let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+        let mut swapped = Swapped{ chunks: 0, replaced: region::section::Replaced::default(), corrupt: Vec::new() };
+        let buffer = region::open(path).map_err(Error::ReadFile)?;
+        let mut parser = region::Parser::new(&buffer).beside(path);
+        let mut writer = region::Writer::new(rx, rz, region::Compression::Zlib);
+        let now = unix_now() as i32;
+        for entry in parser.entries().map_err(Error::Region)? {
+            /* copied first so a rewritten chunk replaces the stub and its .mcc file goes stale */
+            if let Some(stored) = parser.raw(&entry) {
+                writer.stored(entry.index, entry.timestamp, stored).map_err(Error::Write)?;
+            }
+            let (cx, cz) = (rx * 32 + (entry.index % 32) as i32, rz * 32 + (entry.index / 32) as i32);
+            if options.area.as_ref().is_none_or(|area| area.contains(cx, cz)) {
+                match parser.chunk_at(entry.index) {
+                    Ok(Some(chunk)) => {
+                        let mut root = chunk.nbt_owned();
+                        let replaced = region::section::replace(&mut root, &options.from, &options.to);
+                        swapped.replaced.unreadable += replaced.unreadable;
+                        if replaced.renamed + replaced.repacked > 0 {
+                            swapped.chunks += 1;
+                            swapped.replaced.renamed += replaced.renamed;
+                            swapped.replaced.repacked += replaced.repacked;
+                            swapped.replaced.block_entities += replaced.block_entities;
+                            writer.chunk(cx, cz, &root, now).map_err(Error::Write)?;
+                        }
+                    },
+                    Ok(None) => (),
+                    Err(e) => swapped.corrupt.push(Corrupt{ path: path.to_path_buf(), index: Some(entry.index), error: Error::Region(e) }),
+                }
+            }
+        }
+        if swapped.chunks > 0 && !dry_run {
+            back_up(path)?;
+            writer.finish().save(path).map_err(Error::ReadFile)?;
+        }
+        Ok(swapped)
+    }
+
+    pub(super) fn replace(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::Replace(options) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::replace() should not be called on anything but a config::Command::Replace")));
+        };
+        let dry_run = *config.dry_run.value().unwrap();
+        let save_dir = directory::region_files(&config);
+        let mut files = Vec::new();
+        for dimension in dimensions(&config) {
+            files.append(&mut directory::files_in(save_dir.dimension(dimension)).map_err(Error::ReadFile)?);
+        }
+        if let Some(area) = &options.area {
+            files.retain(|path| region::coordinates(path).is_some_and(|(rx, rz)| area.covers_region(rx, rz)));
+        }
+
+        let (mut chunks, mut total) = (0, region::section::Replaced::default());
+        let work = |path: std::path::PathBuf| Self::replace_in_region_file(&path, options, dry_run);
+        pool::ordered(*config.jobs.value().unwrap(), files, work, |result| {
+            let swapped = result?;
+            chunks += swapped.chunks;
+            total.renamed += swapped.replaced.renamed;
+            total.repacked += swapped.replaced.repacked;
+            total.unreadable += swapped.replaced.unreadable;
+            total.block_entities += swapped.replaced.block_entities;
+            self.skip(&config, swapped.corrupt)
+        })?;
+
+        let verb = if dry_run { "would replace" } else { "replaced" };
+        let summary = format!("{} {} with {} in {} sections of {} chunks ({} by renaming palette entries, {} repacked), {} block entities removed",
+            verb, region::section::state_string(&options.from), region::section::state_string(&options.to),
+            total.renamed + total.repacked, chunks, total.renamed, total.repacked, total.block_entities);
+        match dry_run {
+            true  => println!("{}", summary.yellow()),
+            false => println!("{}", summary),
+        }
+        if total.unreadable > 0 {
+            println!("{}", format!("{} sections from before 1.16 needed a repack this can't do and were left alone", total.unreadable).yellow());
+        }
+        Ok(())
+    }
+}
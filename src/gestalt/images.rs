@@ -0,0 +1,262 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::{nbt, config, region, pool, render};
+use colored::*;
+
+use super::{Gestalt, Error, Corrupt, directory, inhabited_time, sibling_region_file, read_nbt_file, scan_region_file};
+
+/* a larger map is almost certainly a mistake, its pixels alone would take 4 GiB */
+const MAX_PIXELS: usize = 1 << 30;
+
+/// One metric of each chunk of a region file, by chunk coordinates.
+type Measured = (Vec<((i32, i32), i64)>, Vec<Corrupt>);
+
+/// The surfaces of one region file's chunks a render draws, by chunk coordinates.
+type Surfaces = (Vec<(i32, i32, render::Surface)>, Vec<Corrupt>);
+
+/// The id in a `map_<id>.dat` file name.
+fn map_id(path: &std::path::Path) -> Option<i32> {
+    path.file_name()?.to_str()?.strip_prefix("map_")?.strip_suffix(".dat")?.parse().ok()
+}
+
+/// Length of the first of the lists `names` a chunk has, at its root or inside `Level` before 1.18.
+fn list_length(bytes: &[u8], names: &[&str]) -> Result<usize, Error> {
+    use nbt::borrowed::{NBTRef, PayloadRef};
+
+    let root = match NBTRef::parse(bytes) {
+        Ok(root) => root,
+        Err(e)   => return Err(Error::Parse(e)),
+    };
+    let PayloadRef::Compound(root) = root.payload else {
+        return Ok(0);
+    };
+    let level = match root.get("Level") {
+        Some(PayloadRef::Compound(level)) => level,
+        _ => root,
+    };
+    for name in names {
+        if let Some(PayloadRef::List(list)) = level.get(name) {
+            return Ok(list.len());
+        }
+    }
+    Ok(0)
+}
+
+impl Gestalt {
+    fn surfaces_in_region_file(path: &std::path::Path, options: &config::Render) -> Result<Surfaces, Error> {
+        let (chunks, corrupt) = scan_region_file(path)?;
+        let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+        let mut surfaces = Vec::new();
+        for chunk in chunks {
+            let (x, z) = (rx * 32 + (chunk.index() % 32) as i32, rz * 32 + (chunk.index() / 32) as i32);
+            if options.area.as_ref().is_some_and(|area| !area.contains(x, z)) {
+                continue;
+            }
+            if let Some(surface) = render::surface(chunk.nbt(), options.biome_tint) {
+                surfaces.push((x, z, surface));
+            }
+        }
+        Ok((surfaces, corrupt))
+    }
+
+    pub(super) fn render(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::Render(options) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::render() should not be called on anything but a config::Command::Render")));
+        };
+        let dimension = config.dimension.value().unwrap_or(&config::Dimension::Overworld);
+        /* regions whose chunk coordinates don't fit an i32 can't hold any chunk the game reaches */
+        let files: Vec<_> = directory::files_in(directory::region_files(&config).dimension(dimension)).map_err(Error::ReadFile)?
+            .into_iter()
+            .filter(|path| region::coordinates(path).is_some_and(|(rx, rz)| {
+                rx.checked_mul(32).is_some() && rz.checked_mul(32).is_some() && options.area.as_ref().is_none_or(|area| area.covers_region(rx, rz))
+            }))
+            .collect();
+        let regions: Vec<_> = files.iter().filter_map(|path| region::coordinates(path)).collect();
+        let (min, max) = match &options.area {
+            Some(area) => area.bounds(),
+            /* a region's first chunk is a multiple of 32, so its last one fits whenever the first does */
+            None => regions.iter().fold(((i32::MAX, i32::MAX), (i32::MIN, i32::MIN)), |(min, max), (rx, rz)| {
+                ((min.0.min(rx * 32), min.1.min(rz * 32)), (max.0.max(rx * 32 + 31), max.1.max(rz * 32 + 31)))
+            }),
+        };
+        if min.0 > max.0 || min.1 > max.1 {
+            return Err(Error::Command(String::from("render found no region files to draw")));
+        }
+        let pixels = (max.0 as i64 - min.0 as i64 + 1).saturating_mul(max.1 as i64 - min.1 as i64 + 1).saturating_mul(256);
+        if pixels > MAX_PIXELS as i64 {
+            return Err(Error::Command(format!("render area of {} pixels is too large, give a smaller area", pixels)));
+        }
+
+        let mut canvas = render::Canvas::new(min, max);
+        let mut drawn = 0;
+        let work = |path: std::path::PathBuf| Self::surfaces_in_region_file(&path, options);
+        pool::ordered(*config.jobs.value().unwrap(), files, work, |result| {
+            let (surfaces, corrupt) = result?;
+            for (x, z, surface) in surfaces {
+                canvas.draw(x, z, &surface);
+                drawn += 1;
+            }
+            self.skip(&config, corrupt)
+        })?;
+        if options.hillshade {
+            canvas.shade();
+        }
+
+        std::fs::write(&options.output, canvas.png()).map_err(Error::ReadFile)?;
+        println!("rendered {} chunks of the {:?} to {} ({} x {})", drawn, dimension, options.output, canvas.width(), canvas.height());
+        Ok(())
+    }
+
+    /// `metric` for every chunk of `area` in one region file. Entities are counted in the region's
+    /// `entities` file, or in the chunks themselves for saves from before 1.17.
+    fn measure_region_file(path: &std::path::Path, metric: config::Metric, area: Option<&config::Area>) -> Result<Measured, Error> {
+        let entities = match metric {
+            config::Metric::EntityCount => sibling_region_file(path, "entities").filter(|file| file.exists()),
+            _ => None,
+        };
+        let (buffer, entity_buffer) = match &entities {
+            Some(file) => (region::open(path), region::open(file)),
+            None => (region::open(path), Ok(region::Mapped::default())),
+        };
+        let (buffer, entity_buffer) = (buffer.map_err(Error::ReadFile)?, entity_buffer.map_err(Error::ReadFile)?);
+        let mut parser = region::Parser::new(&buffer).beside(path);
+        let mut entity_parser = match &entities {
+            Some(file) => region::Parser::new(&entity_buffer).beside(file),
+            None => region::Parser::new(&entity_buffer),
+        };
+        let entity_entries = entity_parser.entries().map_err(Error::Region)?;
+
+        let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+        let (mut values, mut corrupt) = (Vec::new(), Vec::new());
+        for entry in parser.entries().map_err(Error::Region)? {
+            let (x, z) = (rx * 32 + (entry.index % 32) as i32, rz * 32 + (entry.index / 32) as i32);
+            if area.is_some_and(|area| !area.contains(x, z)) {
+                continue;
+            }
+            let (file, value) = match metric {
+                config::Metric::LastModified => (path, Ok(entry.timestamp as i64)),
+                config::Metric::InhabitedTime => {
+                    (path, parser.inflate(&entry).map_err(Error::Region).and_then(|bytes| inhabited_time(&bytes)).map(|ticks| ticks.unwrap_or(0)))
+                },
+                config::Metric::BlockEntityCount => {
+                    (path, parser.inflate(&entry).map_err(Error::Region).and_then(|bytes| list_length(&bytes, &["block_entities", "TileEntities"])).map(|count| count as i64))
+                },
+                config::Metric::EntityCount => match (&entities, entity_entries.iter().find(|other| other.index == entry.index)) {
+                    (Some(file), Some(other)) => {
+                        (file.as_path(), entity_parser.inflate(other).map_err(Error::Region).and_then(|bytes| list_length(&bytes, &["Entities"])).map(|count| count as i64))
+                    },
+                    /* chunks without entities have no entry in the entities file */
+                    (Some(file), None) => (file.as_path(), Ok(0)),
+                    (None, _) => (path, parser.inflate(&entry).map_err(Error::Region).and_then(|bytes| list_length(&bytes, &["Entities"])).map(|count| count as i64)),
+                },
+            };
+            match value {
+                Ok(value)  => values.push(((x, z), value)),
+                Err(error) => corrupt.push(Corrupt{ path: file.to_path_buf(), index: Some(entry.index), error }),
+            }
+        }
+        Ok((values, corrupt))
+    }
+
+    pub(super) fn heatmap(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::Heatmap(options) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::heatmap() should not be called on anything but a config::Command::Heatmap")));
+        };
+        let dimension = config.dimension.value().unwrap_or(&config::Dimension::Overworld);
+        let mut files = directory::files_in(directory::region_files(&config).dimension(dimension)).map_err(Error::ReadFile)?;
+        if let Some(area) = &options.area {
+            files.retain(|path| region::coordinates(path).is_some_and(|(rx, rz)| area.covers_region(rx, rz)));
+        }
+
+        let mut values = Vec::new();
+        let work = |path: std::path::PathBuf| Self::measure_region_file(&path, options.metric, options.area.as_ref());
+        pool::ordered(*config.jobs.value().unwrap(), files, work, |result| {
+            let (measured, corrupt) = result?;
+            values.extend(measured);
+            self.skip(&config, corrupt)
+        })?;
+
+        let Some(bounds) = render::heatmap::bounds(&values) else {
+            return Err(Error::Command(String::from("heatmap found no chunks to measure")));
+        };
+        if options.output.ends_with(".csv") {
+            std::fs::write(&options.output, render::heatmap::csv(&values, options.metric.name())).map_err(Error::ReadFile)?;
+        } else {
+            let ((min_x, min_z), (max_x, max_z)) = bounds;
+            let pixels = (max_x as i64 - min_x as i64 + 1) * (max_z as i64 - min_z as i64 + 1);
+            if pixels > MAX_PIXELS as i64 {
+                return Err(Error::Command(format!("heatmap of {} pixels is too large, give a smaller area", pixels)));
+            }
+            /* timestamps are spread evenly, while counts and time spent pile up near zero */
+            let log = !matches!(options.metric, config::Metric::LastModified);
+            std::fs::write(&options.output, render::heatmap::png(&values, bounds, log)).map_err(Error::ReadFile)?;
+        }
+        let (low, high) = values.iter().fold((i64::MAX, i64::MIN), |(low, high), (_, value)| (low.min(*value), high.max(*value)));
+        println!("{} of {} chunks ({} to {}) written to {}", options.metric.name(), values.len(), low, high, options.output);
+        Ok(())
+    }
+
+    pub(super) fn maps(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::Maps(options) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::maps() should not be called on anything but a config::Command::Maps")));
+        };
+        let mut files: Vec<(i32, std::path::PathBuf)> = match config.file.value() {
+            Some(file) => vec![(map_id(std::path::Path::new(file)).unwrap_or(0), std::path::PathBuf::from(file))],
+            None => {
+                let folder = std::path::Path::new(config.save_root.value().unwrap()).join("data");
+                let entries = std::fs::read_dir(&folder).map_err(Error::ReadFile)?;
+                entries.filter_map(|entry| entry.ok())
+                    .filter_map(|entry| Some((map_id(&entry.path())?, entry.path())))
+                    .collect()
+            },
+        };
+        files.sort_by_key(|(id, _)| *id);
+        if let Some(missing) = options.ids.iter().find(|id| !files.iter().any(|(other, _)| other == *id)) {
+            return Err(Error::Command(format!("there is no map #{}", missing)));
+        }
+        if !options.ids.is_empty() {
+            files.retain(|(id, _)| options.ids.contains(id));
+        }
+
+        let mut maps = Vec::with_capacity(files.len());
+        for (id, path) in files {
+            let root = read_nbt_file(&path.to_string_lossy())?;
+            let map = render::map::Map::parse(id, &root).map_err(Error::Access)?;
+            let scale = format!("1:{}", 1 << map.scale);
+            println!("{} {} {} centered on {},{}{}", format!("map #{}", map.id).bold(), scale, map.dimension, map.center.0, map.center.1, if map.locked { ", locked" } else { "" });
+            for banner in &map.banners {
+                let name = banner.name.as_ref().map_or(String::new(), |name| format!(" {}", name));
+                println!("\t{} banner{} at {},{},{}", banner.color, name, banner.pos.0, banner.pos.1, banner.pos.2);
+            }
+            maps.push(map);
+        }
+        if maps.is_empty() {
+            return Err(Error::Command(String::from("the save has no maps")));
+        }
+
+        if let Some(folder) = &options.export {
+            std::fs::create_dir_all(folder).map_err(Error::ReadFile)?;
+            for map in &maps {
+                let path = std::path::Path::new(folder).join(format!("map_{}.png", map.id));
+                std::fs::write(&path, map.png()).map_err(Error::ReadFile)?;
+            }
+            println!("{} maps written to {}", maps.len(), folder);
+        }
+        if let Some(output) = &options.stitch {
+            /* pixels of maps at different scales or in different dimensions don't line up */
+            if let Some(map) = maps.iter().find(|map| map.scale != maps[0].scale || map.dimension != maps[0].dimension) {
+                return Err(Error::Command(format!("map #{} has a different scale or dimension than map #{}, stitch maps of one kind at a time", map.id, maps[0].id)));
+            }
+            let (_, (width, height)) = render::map::layout(&maps).unwrap();
+            if width as u64 * height as u64 > MAX_PIXELS as u64 {
+                return Err(Error::Command(format!("stitched image of {}x{} pixels is too large, give fewer maps", width, height)));
+            }
+            let png = render::map::stitch(&maps).unwrap();
+            std::fs::write(output, png).map_err(Error::ReadFile)?;
+            println!("{} maps stitched into a {}x{} image at {}", maps.len(), width, height, output);
+        }
+        Ok(())
+    }
+}
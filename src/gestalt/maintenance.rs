@@ -0,0 +1,406 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::{nbt, config, region, pool};
+use colored::*;
+
+use super::{Gestalt, Error, Corrupt, directory, dimensions, back_up, unix_now, inhabited_time, sibling_region_file, read_nbt_file};
+
+/// The region files a maintenance command works on: `--file`, or those of the selected dimensions.
+fn region_paths(config: &config::Configuration) -> Result<Vec<std::path::PathBuf>, Error> {
+    if let Some(file) = config.file.value() {
+        return Ok(vec![std::path::PathBuf::from(file)]);
+    }
+    let save_dir = directory::region_files(config);
+    let mut files = Vec::new();
+    for dimension in dimensions(config) {
+        match directory::files_in(save_dir.dimension(dimension)) {
+            Ok(mut f) => files.append(&mut f),
+            Err(e)    => return Err(Error::ReadFile(e)),
+        }
+    }
+    Ok(files)
+}
+
+/// Writes `bytes` beside `path` first and renames them over it, so a crash never leaves half a file.
+fn replace_file(path: &std::path::Path, bytes: &[u8]) -> Result<(), Error> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    match std::fs::write(&temporary, bytes).and_then(|_| std::fs::rename(&temporary, path)) {
+        Ok(())  => Ok(()),
+        Err(e)  => Err(Error::ReadFile(e)),
+    }
+}
+
+/// The world spawn in block coordinates, from the save's level.dat.
+fn spawn(root: &str) -> Result<(i64, i64), Error> {
+    use nbt::access::Lookup;
+
+    let level = read_nbt_file(&format!("{}/level.dat", root))?;
+    /* `Data` holds every setting of the world, so index it rather than scan it per lookup */
+    let data = match level.payload.as_compound().and_then(|level| level.get_compound("Data")) {
+        Ok(data) => data.index(),
+        Err(e)   => return Err(Error::Access(e)),
+    };
+    /* 1.21.9 moved the spawn point from SpawnX / SpawnZ into a compound */
+    let Ok(spawn) = data.get_compound("spawn") else {
+        return match (data.get_int("SpawnX"), data.get_int("SpawnZ")) {
+            (Ok(x), Ok(z)) => Ok((x as i64, z as i64)),
+            (Err(e), _) | (_, Err(e)) => Err(Error::Access(e)),
+        };
+    };
+    match spawn.get_int_array("pos") {
+        Ok([x, _, z]) => Ok((*x as i64, *z as i64)),
+        Ok(_)         => Err(Error::Command(String::from("level.dat spawn position doesn't hold 3 coordinates"))),
+        Err(e)        => Err(Error::Access(e)),
+    }
+}
+
+/// Which chunks `trim` deletes: those outside the kept radius that pass every limit given.
+struct Trim {
+    max_inhabited: Option<i64>,
+    /// Chunks saved after this time, in seconds since the epoch, are kept.
+    cutoff:        Option<i64>,
+    keep_radius:   Option<i64>,
+    dry_run:       bool,
+}
+
+impl Trim {
+    /// Whether the chunk at chunk coordinates `x`, `z`, last saved at `timestamp`, is deleted.
+    /// `ticks` reads its `InhabitedTime` and is only called when the other limits didn't keep it.
+    fn deletes(&self, (x, z): (i64, i64), center: (i64, i64), timestamp: i64, ticks: impl FnOnce() -> Result<Option<i64>, Error>) -> Result<bool, Error> {
+        let (dx, dz) = (x * 16 + 8 - center.0, z * 16 + 8 - center.1);
+        if self.keep_radius.is_some_and(|radius| (dx as f64).hypot(dz as f64) <= radius as f64) {
+            return Ok(false);
+        }
+        if self.cutoff.is_some_and(|cutoff| timestamp > cutoff) {
+            return Ok(false);
+        }
+        match self.max_inhabited {
+            Some(max_inhabited) => Ok(ticks()?.is_some_and(|ticks| ticks <= max_inhabited)),
+            None => Ok(true),
+        }
+    }
+}
+
+/// What trimming one region file deleted, or would have in a dry run.
+struct Trimmed {
+    chunks:    usize,
+    removed:   Vec<usize>,
+    /// Bytes freed across the region, entities and poi files.
+    reclaimed: usize,
+    corrupt:   Vec<Corrupt>,
+}
+
+impl Gestalt {
+    /// Problems in the layout of one region file.
+    fn check_region_file(path: &std::path::Path, now: i64) -> Result<Vec<region::fsck::Problem>, Error> {
+        match region::open(path) {
+            Ok(buffer) => Ok(region::fsck::check(&buffer, now)),
+            Err(e)     => Err(Error::ReadFile(e)),
+        }
+    }
+
+    pub(super) fn fsck(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let files = region_paths(&config)?;
+        let now = unix_now();
+
+        let (mut checked, mut damaged) = (0, 0);
+        let check = |path: std::path::PathBuf| (Self::check_region_file(&path, now), path);
+        pool::ordered(*config.jobs.value().unwrap(), files, check, |(problems, path)| {
+            checked += 1;
+            let problems = problems?;
+            if problems.is_empty() {
+                println!("{}: {}", path.display(), "ok".green());
+                return Ok(());
+            }
+            damaged += 1;
+            println!("{}: {}", path.display(), format!("problems: {}", problems.len()).red());
+            for problem in problems {
+                println!("\t{}", problem);
+            }
+            Ok(())
+        })?;
+
+        println!("region files checked: {}, damaged: {}", checked, damaged);
+        if damaged > 0 {
+            return Err(Error::Damaged(damaged));
+        }
+        Ok(())
+    }
+
+    /// Rebuilds one region file in place, leaving the original next to it as `<file>.bak`.
+    fn repair_region_file(path: &std::path::Path, quarantine: bool) -> Result<Option<(region::repair::Repaired, usize)>, Error> {
+        let buffer = region::open(path).map_err(Error::ReadFile)?;
+        if buffer.is_empty() {
+            return Ok(None);
+        }
+        let repaired = region::repair::repair(&buffer).map_err(Error::Region)?;
+        let size = buffer.len();
+        if repaired.region[..] == buffer[..] {
+            return Ok(None);
+        }
+        /* the mapping has to be gone before the file is replaced on some platforms */
+        drop(buffer);
+
+        let with = |suffix: &str| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(suffix);
+            std::path::PathBuf::from(name)
+        };
+        back_up(path)?;
+        if quarantine && !repaired.quarantine.is_empty() {
+            std::fs::write(with(".quarantine"), &repaired.quarantine).map_err(Error::ReadFile)?;
+        }
+        replace_file(path, &repaired.region)?;
+        Ok(Some((repaired, size)))
+    }
+
+    pub(super) fn repair(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let files = region_paths(&config)?;
+        let quarantine = *config.quarantine.value().unwrap();
+
+        let (mut rewritten, mut dropped) = (0, 0);
+        let work = |path: std::path::PathBuf| (Self::repair_region_file(&path, quarantine), path);
+        pool::ordered(*config.jobs.value().unwrap(), files, work, |(repaired, path)| {
+            let Some((repaired, size)) = repaired? else {
+                println!("{}: {}", path.display(), "ok".green());
+                return Ok(());
+            };
+            rewritten += 1;
+            dropped += repaired.dropped.len();
+            let reclaimed = size.saturating_sub(repaired.region.len()) / 1024;
+            let summary = format!("kept {}, dropped {}, {} KiB reclaimed", repaired.kept, repaired.dropped.len(), reclaimed);
+            if repaired.dropped.is_empty() {
+                println!("{}: {}", path.display(), summary.green());
+            } else {
+                println!("{}: {}", path.display(), summary.red());
+            }
+            let (rx, rz) = region::coordinates(&path).unwrap_or((0, 0));
+            for (index, e) in repaired.dropped {
+                println!("\tchunk [{}, {}] (index {}): {}", rx * 32 + (index % 32) as i32, rz * 32 + (index / 32) as i32, index, e);
+            }
+            Ok(())
+        })?;
+
+        println!("region files rewritten: {}, chunks dropped: {}", rewritten, dropped);
+        Ok(())
+    }
+
+    /// Deletes the chunks at `removed` (header indices) from a region file and from the same region's
+    /// `entities` and `poi` files, along with any `.mcc` files holding them. Files left without chunks
+    /// are deleted. Returns the bytes freed, which is all a dry run does.
+    fn remove_chunks(path: &std::path::Path, removed: &[usize], dry_run: bool) -> Result<usize, Error> {
+        let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+        let mut files = vec![path.to_path_buf()];
+        files.extend(["entities", "poi"].into_iter().filter_map(|folder| sibling_region_file(path, folder)));
+
+        let mut reclaimed = 0;
+        for file in files {
+            let (size, retained) = {
+                let buffer = match region::open(&file) {
+                    Ok(b) => b,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound && file != path => continue,
+                    Err(e) => return Err(Error::ReadFile(e)),
+                };
+                let retained = region::Parser::new(&buffer).retain(|entry| !removed.contains(&entry.index)).map_err(Error::Region)?;
+                (buffer.len(), retained)
+            };
+            /* a header without a single location means nothing is left in the file */
+            let empty = retained.iter().all(|byte| *byte == 0);
+            reclaimed += if empty { size } else { size.saturating_sub(retained.len()) };
+            if dry_run {
+                continue;
+            }
+            /* the region was unmapped at the end of its block, so the file can be replaced */
+            if empty {
+                std::fs::remove_file(&file).map_err(Error::ReadFile)?;
+            } else {
+                replace_file(&file, &retained)?;
+            }
+            let Some(dir) = file.parent() else {
+                continue;
+            };
+            for index in removed {
+                let (x, z) = (rx * 32 + (index % 32) as i32, rz * 32 + (index / 32) as i32);
+                match std::fs::remove_file(dir.join(format!("c.{}.{}.mcc", x, z))) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(Error::ReadFile(e)),
+                    _ => (),
+                }
+            }
+        }
+        Ok(reclaimed)
+    }
+
+    /// Picks the chunks of one region file `trim` deletes and, unless it's a dry run, deletes them.
+    /// `center` is what `--keep-radius` is measured from, in block coordinates. Chunks that can't be
+    /// read are kept.
+    fn trim_region_file(path: &std::path::Path, trim: &Trim, center: (i64, i64)) -> Result<Trimmed, Error> {
+        let mut trimmed = Trimmed{ chunks: 0, removed: Vec::new(), reclaimed: 0, corrupt: Vec::new() };
+        {
+            let buffer = region::open(path).map_err(Error::ReadFile)?;
+            let mut parser = region::Parser::new(&buffer).beside(path);
+            let entries = parser.entries().map_err(Error::Region)?;
+            let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+            trimmed.chunks = entries.len();
+            for entry in entries.iter() {
+                let x = (rx * 32 + (entry.index % 32) as i32) as i64;
+                let z = (rz * 32 + (entry.index / 32) as i32) as i64;
+                let ticks = || parser.inflate(entry).map_err(Error::Region).and_then(|bytes| inhabited_time(&bytes));
+                match trim.deletes((x, z), center, entry.timestamp as i64, ticks) {
+                    Ok(true)  => trimmed.removed.push(entry.index),
+                    Ok(false) => (),
+                    Err(error) => trimmed.corrupt.push(Corrupt{ path: path.to_path_buf(), index: Some(entry.index), error }),
+                }
+            }
+        }
+        if !trimmed.removed.is_empty() {
+            trimmed.reclaimed = Self::remove_chunks(path, &trimmed.removed, trim.dry_run)?;
+        }
+        Ok(trimmed)
+    }
+
+    pub(super) fn trim(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let (max_inhabited, older_than) = (config.max_inhabited.value().copied(), config.older_than.value().copied());
+        if max_inhabited.is_none() && older_than.is_none() {
+            return Err(Error::Command(String::from("trim needs --max-inhabited or --older-than to tell which chunks to delete")));
+        }
+        let trim = Trim {
+            max_inhabited,
+            cutoff:      older_than.map(|seconds| unix_now().saturating_sub(seconds)),
+            keep_radius: config.keep_radius.value().copied(),
+            dry_run:     *config.dry_run.value().unwrap(),
+        };
+        let spawn = match trim.keep_radius {
+            Some(_) => spawn(config.save_root.value().unwrap())?,
+            None    => (0, 0),
+        };
+
+        /* the radius is kept around spawn in the overworld, and around the origin elsewhere */
+        let mut files = Vec::new();
+        match config.file.value() {
+            Some(file) => files.push((std::path::PathBuf::from(file), spawn)),
+            None => {
+                let save_dir = directory::region_files(&config);
+                for dimension in dimensions(&config) {
+                    let center = match dimension {
+                        config::Dimension::Overworld => spawn,
+                        _ => (0, 0),
+                    };
+                    match directory::files_in(save_dir.dimension(dimension)) {
+                        Ok(f)  => files.extend(f.into_iter().map(|file| (file, center))),
+                        Err(e) => return Err(Error::ReadFile(e)),
+                    }
+                }
+            },
+        }
+
+        let (mut chunks, mut removed, mut reclaimed) = (0, 0, 0);
+        let work = |(path, center): (std::path::PathBuf, (i64, i64))| (Self::trim_region_file(&path, &trim, center), path);
+        pool::ordered(*config.jobs.value().unwrap(), files, work, |(trimmed, path)| {
+            let trimmed = trimmed?;
+            chunks += trimmed.chunks;
+            removed += trimmed.removed.len();
+            reclaimed += trimmed.reclaimed;
+            if !trimmed.removed.is_empty() {
+                println!("{}: {} of {} chunks, {} KiB", path.display(), trimmed.removed.len(), trimmed.chunks, trimmed.reclaimed / 1024);
+            }
+            self.skip(&config, trimmed.corrupt)
+        })?;
+
+        if trim.dry_run {
+            println!("{}", format!("dry run, nothing was changed: {} of {} chunks would be trimmed, {} KiB reclaimed", removed, chunks, reclaimed / 1024).yellow());
+        } else {
+            println!("chunks trimmed: {} of {}, {} KiB reclaimed", removed, chunks, reclaimed / 1024);
+        }
+        Ok(())
+    }
+
+    /// Deletes the chunks of `area` that one region file holds.
+    fn delete_in_region_file(path: &std::path::Path, area: &config::Area, dry_run: bool) -> Result<(Vec<usize>, usize), Error> {
+        let removed: Vec<usize> = {
+            let buffer = region::open(path).map_err(Error::ReadFile)?;
+            let entries = region::Parser::new(&buffer).entries().map_err(Error::Region)?;
+            let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+            entries.into_iter()
+                .map(|entry| entry.index)
+                .filter(|index| area.contains(rx * 32 + (index % 32) as i32, rz * 32 + (index / 32) as i32))
+                .collect()
+        };
+        if removed.is_empty() {
+            return Ok((removed, 0));
+        }
+        let reclaimed = Self::remove_chunks(path, &removed, dry_run)?;
+        Ok((removed, reclaimed))
+    }
+
+    pub(super) fn delete_chunks(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::DeleteChunks(area) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::delete_chunks() should not be called on anything but a config::Command::DeleteChunks")));
+        };
+        /* the same coordinates are different places in every dimension */
+        if config.file.value().is_none() && config.dimension.value().is_none() {
+            return Err(Error::Command(String::from("delete-chunks needs --dimension or --file to tell where the area is")));
+        }
+        let dry_run = *config.dry_run.value().unwrap();
+        let files: Vec<_> = region_paths(&config)?.into_iter()
+            .filter(|path| region::coordinates(path).is_some_and(|(rx, rz)| area.covers_region(rx, rz)))
+            .collect();
+
+        let (mut deleted, mut reclaimed) = (0, 0);
+        let work = |path: std::path::PathBuf| (Self::delete_in_region_file(&path, area, dry_run), path);
+        pool::ordered(*config.jobs.value().unwrap(), files, work, |(result, path)| {
+            let (removed, freed) = result?;
+            if !removed.is_empty() {
+                println!("{}: {} chunks, {} KiB", path.display(), removed.len(), freed / 1024);
+            }
+            deleted += removed.len();
+            reclaimed += freed;
+            Ok(())
+        })?;
+
+        if dry_run {
+            println!("{}", format!("dry run, nothing was changed: {} chunks would be deleted, {} KiB reclaimed", deleted, reclaimed / 1024).yellow());
+        } else {
+            println!("chunks deleted: {}, {} KiB reclaimed", deleted, reclaimed / 1024);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn trim_needs_every_limit_to_agree() {
+        use super::{Trim, Error};
+
+        let trim = |max_inhabited, cutoff, keep_radius| Trim{ max_inhabited, cutoff, keep_radius, dry_run: true };
+        let ticks = |ticks: Option<i64>| move || Ok::<_, Error>(ticks);
+
+        /* only the age limit: old chunks go without reading them */
+        let old = trim(None, Some(1000), None);
+        assert!(matches!(old.deletes((0, 0), (0, 0), 999, || panic!("read without --max-inhabited")), Ok(true)));
+        assert!(matches!(old.deletes((0, 0), (0, 0), 1001, ticks(Some(0))), Ok(false)));
+
+        /* only the inhabited limit: chunks without an InhabitedTime are kept */
+        let idle = trim(Some(100), None, None);
+        assert!(matches!(idle.deletes((0, 0), (0, 0), 0, ticks(Some(100))), Ok(true)));
+        assert!(matches!(idle.deletes((0, 0), (0, 0), 0, ticks(Some(101))), Ok(false)));
+        assert!(matches!(idle.deletes((0, 0), (0, 0), 0, ticks(None)), Ok(false)));
+        assert!(matches!(idle.deletes((0, 0), (0, 0), 0, || Err(Error::Command(String::new()))), Err(Error::Command(_))));
+
+        /* both: a chunk has to be old and idle */
+        let both = trim(Some(100), Some(1000), None);
+        assert!(matches!(both.deletes((0, 0), (0, 0), 999, ticks(Some(5))), Ok(true)));
+        assert!(matches!(both.deletes((0, 0), (0, 0), 1001, ticks(Some(5))), Ok(false)));
+        assert!(matches!(both.deletes((0, 0), (0, 0), 999, ticks(Some(500))), Ok(false)));
+
+        /* the radius is measured in blocks from the chunk's middle to the center, and always wins */
+        let near = trim(Some(100), Some(1000), Some(100));
+        assert!(matches!(near.deletes((5, 0), (0, 0), 0, ticks(Some(0))), Ok(false)));
+        assert!(matches!(near.deletes((6, 0), (0, 0), 0, ticks(Some(0))), Ok(true)));
+        assert!(matches!(near.deletes((-7, -7), (-100, -100), 0, ticks(Some(0))), Ok(false)));
+        assert!(matches!(near.deletes((0, 0), (-100, -100), 0, ticks(Some(0))), Ok(true)));
+    }
+}
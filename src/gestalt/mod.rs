@@ -0,0 +1,299 @@
+//
+// Created by Justin Tunheim on 7/23/24
+//
+
+mod directory;
+mod search;
+mod diff;
+mod maintenance;
+mod copy;
+mod images;
+mod edit;
+
+use std::io::Read;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use colored::*;
+
+use crate::{nbt, config, region, pool, structure};
+
+pub enum Error {
+    ReadFile(std::io::Error),
+    Region(region::Report),
+    Parse(nbt::Error),
+    Command(String),
+    Corrupt(usize),
+    Damaged(usize),
+    Access(nbt::access::Error),
+    Write(region::Error),
+    Structure(structure::Error),
+    Finding,
+}
+
+struct Gestalt {
+    corrupt: Vec<Corrupt>,
+}
+
+fn plural(i: usize) -> char {
+	if i == 1 {
+		' '
+	} else {
+		's'
+	}
+}
+
+pub fn run(config: config::Configuration) -> Result<(), Error> {
+    let mut gestalt = Gestalt::new(&config);
+
+    match config.command.value().unwrap() {
+        config::Command::List(_)   => gestalt.list(config)?,
+        config::Command::Search(_) => gestalt.search(config)?,
+        config::Command::Get(_)    => gestalt.get(config)?,
+        config::Command::Diff(_, _) => gestalt.diff(config)?,
+        config::Command::DiffWorld(_, _) => gestalt.diff_world(config)?,
+        config::Command::Fsck => gestalt.fsck(config)?,
+        config::Command::Repair => gestalt.repair(config)?,
+        config::Command::Trim => gestalt.trim(config)?,
+        config::Command::DeleteChunks(_) => gestalt.delete_chunks(config)?,
+        config::Command::CopyChunks(_, _, _, _) => gestalt.copy_chunks(config)?,
+        config::Command::Render(_) => gestalt.render(config)?,
+        config::Command::Heatmap(_) => gestalt.heatmap(config)?,
+        config::Command::Maps(_) => gestalt.maps(config)?,
+        config::Command::ExportStructure(_) => gestalt.export_structure(config)?,
+        config::Command::Paste(_) => gestalt.paste(config)?,
+        config::Command::Replace(_) => gestalt.replace(config)?,
+    };
+
+    gestalt.summarize()
+}
+
+/// The dimensions a command covers, every one of them unless `--dimension` was given.
+fn dimensions(config: &config::Configuration) -> Vec<&config::Dimension> {
+    match config.dimension.value() {
+        Some(dimension) => vec![dimension],
+        None => vec![&config::Dimension::Overworld, &config::Dimension::Nether, &config::Dimension::End],
+    }
+}
+
+/// Copies `path` to `<path>.bak` before it's rewritten. A file that doesn't exist yet has nothing to keep.
+fn back_up(path: &std::path::Path) -> Result<(), Error> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    match std::fs::copy(path, backup) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::ReadFile(e)),
+        _ => Ok(()),
+    }
+}
+
+/// Seconds since the epoch.
+fn unix_now() -> i64 {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(now) => now.as_secs() as i64,
+        Err(_)  => i64::MAX,
+    }
+}
+
+/// Ticks players have spent near a chunk, from its `InhabitedTime` (inside `Level` before 1.18).
+fn inhabited_time(bytes: &[u8]) -> Result<Option<i64>, Error> {
+    use nbt::access::Lookup;
+
+    let mut root = nbt::NBT::default();
+    nbt::Parser::new(bytes.to_vec()).parse(&mut root).map_err(Error::Parse)?;
+    Ok(region::section::level(&root).and_then(|level| level.get_long("InhabitedTime").ok()))
+}
+
+/// The file of the same region in a sibling of the `region` folder, like `entities` or `poi`.
+fn sibling_region_file(path: &std::path::Path, folder: &str) -> Option<std::path::PathBuf> {
+    Some(path.parent()?.parent()?.join(folder).join(path.file_name()?))
+}
+
+/// The region files of one dimension, read lazily so a whole save never has to fit in memory.
+pub(crate) struct World {
+    files: Vec<std::path::PathBuf>,
+    jobs:  usize,
+}
+
+/// Chunks of a `World` in file order. Region files are decoded on a pool that runs at most
+/// `jobs * 2` files past the one being handed out, with up to `jobs` more decoded files queued for
+/// the iterator, so a slow consumer holds back decoding instead of buffering the whole world.
+pub(crate) struct Chunks {
+    regions: std::sync::mpsc::Receiver<Scanned>,
+    current: std::vec::IntoIter<region::Chunk>,
+    skipped: std::vec::IntoIter<Corrupt>,
+}
+
+/// The chunks of one region file and those of them that couldn't be read, or the file's own failure.
+type Scanned = Result<(Vec<region::Chunk>, Vec<Corrupt>), Corrupt>;
+
+/// A chunk, or a whole region file when there is no index, that a scan couldn't read.
+pub(crate) struct Corrupt {
+    path:  std::path::PathBuf,
+    index: Option<usize>,
+    error: Error,
+}
+
+impl World {
+    pub(crate) fn new(dirs: &directory::List, jobs: usize) -> Result<Self, Error> {
+        match directory::files_in(dirs) {
+            Ok(files) => Ok(Self{ files, jobs }),
+            Err(e)    => Err(Error::ReadFile(e)),
+        }
+    }
+
+    /// Every readable chunk of a region file, followed by the ones that weren't.
+    pub(crate) fn chunks(self) -> Chunks {
+        let (send, regions) = std::sync::mpsc::sync_channel(self.jobs);
+        std::thread::spawn(move || {
+            let scan = |path: std::path::PathBuf| scan_region_file(&path).map_err(|error| Corrupt{ path, index: None, error });
+            /* sending only fails once the iterator was dropped, which ends the pool early */
+            pool::ordered(self.jobs, self.files, scan, |region| send.send(region))
+        });
+        Chunks{ regions, current: Vec::new().into_iter(), skipped: Vec::new().into_iter() }
+    }
+}
+
+impl Iterator for Chunks {
+    type Item = Result<region::Chunk, Corrupt>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chunk) = self.current.next() {
+                return Some(Ok(chunk));
+            }
+            if let Some(corrupt) = self.skipped.next() {
+                return Some(Err(corrupt));
+            }
+            match self.regions.recv().ok()? {
+                Ok((chunks, skipped)) => {
+                    self.current = chunks.into_iter();
+                    self.skipped = skipped.into_iter();
+                },
+                Err(corrupt) => return Some(Err(corrupt)),
+            }
+        }
+    }
+}
+
+/// Reads a standalone NBT file, inflating it first when it is gzip or zlib compressed (e.g level.dat).
+pub(crate) fn read_nbt_file(path: &str) -> Result<nbt::NBT, Error> {
+    let buffer = match std::fs::read(path) {
+        Ok(b) => b,
+        Err(e) => return Err(Error::ReadFile(e)),
+    };
+    let mut data = Vec::new();
+    let inflated = match buffer.get(0..2) {
+        Some([0x1f, 0x8b]) => GzDecoder::new(buffer.as_slice()).read_to_end(&mut data),
+        Some([0x78, _])    => ZlibDecoder::new(buffer.as_slice()).read_to_end(&mut data),
+        _ => {
+            data = buffer;
+            Ok(0)
+        },
+    };
+    if let Err(e) = inflated {
+        return Err(Error::ReadFile(e));
+    }
+    let mut root = nbt::NBT::default();
+    if let Err(e) = nbt::Parser::new(data).parse(&mut root) {
+        return Err(Error::Parse(e));
+    }
+    Ok(root)
+}
+
+/// Loads a single chunk of a save by its chunk coordinates, `None` when it was never generated.
+pub(crate) fn chunk_in_save(root: &str, dimension: &config::Dimension, x: i32, z: i32) -> Result<Option<region::Chunk>, Error> {
+    let mut chunk = None;
+    for dir in directory::region_dirs(root).dimension(dimension) {
+        let file = format!("{}/r.{}.{}.mca", dir, x >> 5, z >> 5);
+        let buffer = match region::open(std::path::Path::new(&file)) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::ReadFile(e)),
+        };
+        let index = (x & 31) as usize + (z & 31) as usize * 32;
+        match region::Parser::new(&buffer).beside(std::path::Path::new(&file)).chunk_at(index) {
+            Ok(found) => chunk = found,
+            Err(e)    => return Err(Error::Region(e)),
+        }
+    }
+    Ok(chunk)
+}
+
+/// Chunks of one region file, setting aside the ones that fail to decode instead of giving up on the file.
+fn scan_region_file(file: &std::path::Path) -> Result<(Vec<region::Chunk>, Vec<Corrupt>), Error> {
+    let buffer = match region::open(file) {
+        Ok(b) => b,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), Vec::new())),
+        Err(e) => return Err(Error::ReadFile(e)),
+    };
+    let (chunks, failures) = match region::Parser::new(&buffer).beside(file).parse_tolerant() {
+        Ok(r)  => r,
+        Err(e) => return Err(Error::Region(e)),
+    };
+    let corrupt = failures.into_iter().map(|failure| Corrupt{
+        path:  file.to_path_buf(),
+        index: Some(failure.index),
+        error: Error::Region(failure.report),
+    }).collect();
+    Ok((chunks, corrupt))
+}
+
+impl Gestalt {
+
+    /// Sets corrupt chunks aside for the summary, or fails on the first one with `--strict`.
+    fn skip(&mut self, config: &config::Configuration, corrupt: Vec<Corrupt>) -> Result<(), Error> {
+        if *config.strict.value().unwrap() {
+            if let Some(corrupt) = corrupt.into_iter().next() {
+                return Err(corrupt.error);
+            }
+            return Ok(());
+        }
+        self.corrupt.extend(corrupt);
+        Ok(())
+    }
+
+    /// Lists every chunk a scan skipped, failing the run when there were any.
+    fn summarize(&self) -> Result<(), Error> {
+        if self.corrupt.is_empty() {
+            return Ok(());
+        }
+        println!("{}", format!("corrupt chunks skipped: {}", self.corrupt.len()).red());
+        for corrupt in self.corrupt.iter() {
+            println!("\n{}", corrupt);
+        }
+        Err(Error::Corrupt(self.corrupt.len()))
+    }
+
+}
+
+impl Gestalt {
+    pub fn new(config: &config::Configuration) -> Self {
+        Self { corrupt: Vec::new() }
+    }
+}
+
+impl std::fmt::Display for Corrupt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(index) = self.index {
+            let (rx, rz) = region::coordinates(&self.path).unwrap_or((0, 0));
+            write!(f, " chunk [{}, {}] (index {})", rx * 32 + (index % 32) as i32, rz * 32 + (index / 32) as i32, index)?;
+        }
+        write!(f, ":\n{}", self.error)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReadFile(e) => f.write_fmt(format_args!("reading: {}", e)),
+            Self::Region(r) => f.write_fmt(format_args!("{}", r)),
+            Self::Parse(e) => f.write_fmt(format_args!("nbt: {}", e)),
+            Self::Command(cmd) => f.write_fmt(format_args!("command: {}", cmd)),
+            Self::Corrupt(count) => f.write_fmt(format_args!("corrupt chunks skipped: {}", count)),
+            Self::Damaged(count) => f.write_fmt(format_args!("damaged region files: {}", count)),
+            Self::Access(e) => f.write_fmt(format_args!("tag: {}", e)),
+            Self::Write(e) => f.write_fmt(format_args!("writing region: {}", e)),
+            Self::Structure(e) => f.write_fmt(format_args!("structure: {}", e)),
+            Self::Finding => f.write_fmt(format_args!("{}", "query gave no results")),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::{nbt, config, region, pool};
+
+use super::{Gestalt, Error, Corrupt, World, directory, dimensions, read_nbt_file, chunk_in_save, scan_region_file, plural};
+
+/// Output of searching one region file, printed in file order once every earlier file is done.
+struct Found {
+    lines:   Vec<String>,
+    misses:  usize,
+    corrupt: Vec<Corrupt>,
+}
+
+impl Gestalt {
+	pub(super) fn list(&mut self, config: config::Configuration) -> Result<(), Error> {
+		let save_dir = directory::region_files(&config);
+		let jobs = *config.jobs.value().unwrap();
+		for dimension in dimensions(&config) {
+			println!("starting {:?}...", dimension);
+			for chunk in World::new(save_dir.dimension(dimension), jobs)?.chunks() {
+				match chunk {
+					Ok(chunk)    => println!("{}", chunk.nbt()),
+					Err(corrupt) => self.skip(&config, vec![corrupt])?,
+				}
+			}
+		}
+		Ok(())
+	}
+
+    /// Name searches stream each chunk's NBT instead of parsing it, so no tree is built per chunk.
+    fn fast_find(name: &str, path: &std::path::Path, region: &mut region::Parser, found: &mut Found) -> Result<(), Error> {
+        let entries = match region.entries() {
+            Ok(entries) => entries,
+            Err(e)      => return Err(Error::Region(e)),
+        };
+        let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+        for entry in entries {
+            let tags = match region.inflate(&entry).map_err(Error::Region) {
+                Ok(bytes) => nbt::query::find_many_by_name(name.as_bytes(), &bytes).map_err(Error::Parse),
+                Err(e)    => Err(e),
+            };
+            let tags = match tags {
+                Ok(tags) => tags,
+                Err(error) => {
+                    found.corrupt.push(Corrupt{ path: path.to_path_buf(), index: Some(entry.index), error });
+                    continue;
+                },
+            };
+            if tags.is_empty() {
+                found.misses += 1;
+            }
+            let x = rx * 32 + (entry.index % 32) as i32;
+            let z = rz * 32 + (entry.index / 32) as i32;
+            for tag in tags {
+                found.lines.push(format!("[{}, {}] {}: {}", x, z, tag.path, nbt::type_name(tag.id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Searches one region file for the matches to print, skipping chunks that fail to decode.
+    fn find_in_region_file(method: &config::Method, path: &std::path::Path) -> Result<Found, Error> {
+        let mut found = Found{ lines: Vec::new(), misses: 0, corrupt: Vec::new() };
+        let predicate = match method {
+            config::Method::Predicate(predicate) => predicate,
+            config::Method::Name(name) => {
+                let buffer = match region::open(path) {
+                    Ok(b) => b,
+                    Err(e) => return Err(Error::ReadFile(e)),
+                };
+                Self::fast_find(name, path, &mut region::Parser::new(&buffer).beside(path), &mut found)?;
+                return Ok(found);
+            },
+        };
+        let (region, corrupt) = scan_region_file(path)?;
+        let (rx, rz) = region::coordinates(path).unwrap_or((0, 0));
+        for chunk in region {
+            let x = rx * 32 + (chunk.index() % 32) as i32;
+            let z = rz * 32 + (chunk.index() / 32) as i32;
+            let tags = nbt::query::find_many_by_predicate(predicate, chunk.nbt());
+            if tags.is_empty() {
+                found.misses += 1;
+            }
+            for tag in tags {
+                found.lines.push(format!("[{}, {}] {}: {}", x, z, tag.path, tag.payload));
+            }
+        }
+        found.corrupt = corrupt;
+        Ok(found)
+    }
+
+    fn find_nbt(&mut self, config: &config::Configuration, file_list: directory::List) -> Result<(), Error> {
+        let method = match config.command.value().unwrap() {
+            config::Command::Search(method) => method,
+            _ => return Err(Error::Command(format!("Gestalt::find() should not be called on anything but a config::Command::Search"))), 
+        };
+
+/*
+*       let Some(idx) = config.index.value() else {
+*           return self.fast_find(method);
+*       };
+*
+*       match idx {
+*           config::Index::First => return self.fast_find(method),
+*           _ => (),
+*       }
+*/
+
+        let files = match directory::files_in(&file_list) {
+            Ok(f) => f,
+            Err(e) => return Err(Error::ReadFile(e)),
+        };
+		let mut visited_nodes = 0;
+        let jobs = *config.jobs.value().unwrap();
+        let search = |path: std::path::PathBuf| Self::find_in_region_file(method, &path).map_err(|error| Corrupt{ path, index: None, error });
+        pool::ordered(jobs, files, search, |found| {
+            let found = match found {
+                Ok(found) => found,
+                Err(corrupt) => Found{ lines: Vec::new(), misses: 0, corrupt: vec![corrupt] },
+            };
+            for line in found.lines {
+                println!("{}", line);
+            }
+            visited_nodes += found.misses;
+            self.skip(config, found.corrupt)
+        })?;
+		println!("searched: {} node{}", visited_nodes, plural(visited_nodes));
+        Ok(())
+    }
+
+    pub(super) fn search(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let region_files = directory::region_files(&config);
+        println!("{}\n{:?}", "starting search...", config);
+        self.find_nbt(&config, region_files.overworld)?;
+        self.find_nbt(&config, region_files.nether)?;
+        self.find_nbt(&config, region_files.the_end)?;
+        println!("{}", "finished search.");
+        Ok(())
+    }
+
+    fn path_matches(path: &nbt::path::Path, root: &nbt::NBT, location: Option<(i32, i32)>) -> Vec<String> {
+        path.evaluate(&root.payload).into_iter().map(|found| match location {
+            Some((x, z)) => format!("[{}, {}] {}", x, z, found),
+            None         => format!("{}", found),
+        }).collect()
+    }
+
+    /// Evaluates a path against every chunk of one region file, skipping chunks that fail to decode.
+    /// Chunks are read as borrowed views so only the tags the path selects are copied out.
+    fn get_in_region(path: &nbt::path::Path, file: &std::path::Path) -> Result<Found, Error> {
+        use nbt::borrowed::NBTRef;
+
+        let mut found = Found{ lines: Vec::new(), misses: 0, corrupt: Vec::new() };
+        let buffer = match region::open(file) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(found),
+            Err(e) => return Err(Error::ReadFile(e)),
+        };
+        let mut region = region::Parser::new(&buffer).beside(file);
+        let entries = region.entries().map_err(Error::Region)?;
+        let (rx, rz) = region::coordinates(file).unwrap_or((0, 0));
+        for entry in entries {
+            let bytes = match region.inflate(&entry) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    found.corrupt.push(Corrupt{ path: file.to_path_buf(), index: Some(entry.index), error: Error::Region(e) });
+                    continue;
+                },
+            };
+            let root = match NBTRef::parse(&bytes) {
+                Ok(root) => root,
+                Err(e) => {
+                    found.corrupt.push(Corrupt{ path: file.to_path_buf(), index: Some(entry.index), error: Error::Parse(e) });
+                    continue;
+                },
+            };
+            let x = rx * 32 + (entry.index % 32) as i32;
+            let z = rz * 32 + (entry.index / 32) as i32;
+            for value in path.evaluate_ref(root.payload) {
+                found.lines.push(format!("[{}, {}] {}", x, z, value.to_payload()));
+            }
+        }
+        Ok(found)
+    }
+
+    pub(super) fn get(&mut self, config: config::Configuration) -> Result<(), Error> {
+        let config::Command::Get(path) = config.command.value().unwrap() else {
+            return Err(Error::Command(String::from("Gestalt::get() should not be called on anything but a config::Command::Get")));
+        };
+
+        if let Some(file) = config.file.value() {
+            if directory::is_region_name(std::path::Path::new(file)) {
+                let found = Self::get_in_region(path, std::path::Path::new(file))?;
+                for line in found.lines {
+                    println!("{}", line);
+                }
+                return self.skip(&config, found.corrupt);
+            }
+            let root = read_nbt_file(file)?;
+            for line in Self::path_matches(path, &root, None) {
+                println!("{}", line);
+            }
+            return Ok(());
+        }
+
+        if let Some((x, z)) = config.chunk.value() {
+            let dimension = config.dimension.value().unwrap_or(&config::Dimension::Overworld);
+            match chunk_in_save(config.save_root.value().unwrap(), dimension, *x, *z)? {
+                Some(chunk) => for line in Self::path_matches(path, chunk.nbt(), Some((*x, *z))) {
+                    println!("{}", line);
+                },
+                None => println!("chunk [{}, {}] has not been generated.", x, z),
+            }
+            return Ok(());
+        }
+
+        let save_dir = directory::region_files(&config);
+        let jobs = *config.jobs.value().unwrap();
+        for dimension in dimensions(&config) {
+            let files = directory::files_in(save_dir.dimension(dimension)).map_err(Error::ReadFile)?;
+            let get = |file: std::path::PathBuf| Self::get_in_region(path, &file).map_err(|error| Corrupt{ path: file, index: None, error });
+            pool::ordered(jobs, files, get, |found| {
+                let found = match found {
+                    Ok(found) => found,
+                    Err(corrupt) => Found{ lines: Vec::new(), misses: 0, corrupt: vec![corrupt] },
+                };
+                for line in found.lines {
+                    println!("{}", line);
+                }
+                self.skip(&config, found.corrupt)
+            })?;
+        }
+        Ok(())
+    }
+}
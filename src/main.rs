@@ -8,18 +8,65 @@ mod nbt;
 mod region;
 mod gestalt;
 mod config;
+mod pool;
+mod render;
+mod structure;
 
 use config::{Value, Scope, Command, Method};
 
 const CODENAME: &str = "RAVE";
 
 fn commands() -> String {
-    format!("{}{}",
-        "\n\t--root  | -r : Path to a Minecraft Java save",
-        "\n\t--index | -i : Can be combined with commands that iterate / list to select a specific element"
+    format!("{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}{}",
+        "\n\t--root      | -r : Path to a Minecraft Java save",
+        "\n\t--index     | -i : Can be combined with commands that iterate / list to select a specific element",
+        "\n\t--dimension | -d : Restrict to one dimension: overworld, nether or end",
+        "\n\t--chunk     | -c : Select a single chunk by its chunk coordinates e.g '--chunk -3,12'",
+        "\n\t--file      | -f : Operate on a standalone NBT (.dat) or region (.mca) file instead of a save",
+        "\n\t--jobs      | -j : Number of region files to decode at once, defaults to one per core",
+        "\n\t--strict         : Stop at the first corrupt chunk instead of skipping it and reporting it at the end",
+        "\n\t--quarantine     : With repair, keep chunks it drops in a '<file>.quarantine' region file",
+        "\n\t--max-inhabited  : With trim, only delete chunks players spent at most this many ticks in",
+        "\n\t--older-than     : With trim, only delete chunks last saved longer ago than this e.g '90d' (s, m, h, d, w)",
+        "\n\t--keep-radius    : With trim, never delete chunks within this many blocks of spawn (of the origin outside the overworld)",
+        "\n\t--dry-run        : With trim, delete-chunks, copy-chunks, paste or replace, report what would be changed without changing anything",
+        "\n\n\tget | g < path > : Print every tag selected by a Minecraft NBT path e.g 'Inventory[{id:\"minecraft:diamond\"}].Count'",
+        "\n\tsearch | s where < expr > : Search with comparisons e.g 'id matches minecraft:.*_shulker_box and Count ge 2 or type eq long_array'",
+        "\n\tdiff < old > < new > : Show changed tags between two NBT files, region files, chunks ('--chunk x,z' on two saves) or whole saves",
+        "\n\tdiff-world < old > < new > : List chunks added, deleted or modified between two copies of a save",
+        "\n\tfsck : Check the layout of every region file (or '--file') without decoding chunks",
+        "\n\trepair : Rewrite region files without unreadable chunks or overlapping and unused sectors, keeping a '.bak' copy",
+        "\n\ttrim : Delete barely visited chunks, with their entities and points of interest e.g 'rave trim --max-inhabited 200 --older-than 90d'",
+        "\n\tdelete-chunks < area > : Delete chunks so the game regenerates them, area in chunk coordinates e.g '--from -4,-4 --to 3,3', '--center 0,0 --radius 8' or '--polygon 0,0 10,0 0,10'",
+        "\n\tcopy-chunks < src > < dst > < area > [--offset dx,dz] : Copy chunks with their entities and points of interest into another save, moved by a number of chunks",
+        "\n\trender [area] [-o map.png] [--hillshade] [--biome-tint] : Draw a top down map of a dimension (the overworld by default) as a PNG image",
+        "\n\theatmap --metric < inhabited_time | last_modified | entity_count | block_entity_count > [area] [-o heatmap.png | -o heatmap.csv] : One pixel or CSV row per chunk",
+        "\n\tmaps [id ...] [--export folder] [--stitch art.png] : List in-game maps with their scale, dimension, center and banners, and draw them as PNG images",
+        "\n\texport-structure --area x1,y1,z1:x2,y2,z2 -o < build.schem | build.nbt > : Save blocks, block entities and entities as a Sponge schematic or structure block file",
+        "\n\tpaste < build.schem | build.nbt > --at x,y,z : Write a structure's blocks, block entities and entities into existing chunks, its smallest corner at x,y,z, keeping a '.bak' copy of each file it changes",
+        "\n\treplace < from > < to > [area] : Swap blocks in every dimension, or the one given with '--dimension', keeping a '.bak' copy of each file it changes e.g 'rave replace \"minecraft:oak_stairs[half=top]\" minecraft:stone'",
     )
 }
 
+/// Whether an argument is one of the global options, used to end free form arguments like search expressions.
+fn is_option(arg: &str) -> bool {
+    matches!(arg, "-r" | "--root" | "-i" | "--index" | "-d" | "--dimension" | "-c" | "--chunk" | "-f" | "--file" | "-j" | "--jobs" | "--strict" | "--quarantine"
+        | "--max-inhabited" | "--older-than" | "--keep-radius" | "--dry-run" | "--help")
+}
+
+/// Takes the arguments after a command up to the next global option, for commands with options of their own.
+/// `next` is the position of the first of them.
+fn command_args(args: &mut impl Iterator<Item = (usize, String)>, next: usize) -> Vec<String> {
+    let mut taken = Vec::new();
+    while let Some(peek) = std::env::args().nth(next + taken.len()) {
+        if is_option(&peek) {
+            break;
+        }
+        taken.push(args.next().unwrap().1);
+    }
+    taken
+}
+
 fn usage() -> String {
     format!("Usage: {} [OPTIONS] [COMMAND]\n\t{}", CODENAME.to_lowercase(), commands())
 }
@@ -37,6 +84,16 @@ fn main() {
         command:   Value::Default(Command::List(Scope::All)),
         save_root: Value::Default(working_path().expect("compatible operating system.")),
         index:     Value::None,
+        dimension: Value::None,
+        chunk:     Value::None,
+        file:      Value::None,
+        jobs:      Value::Default(pool::default_jobs()),
+        strict:    Value::Default(false),
+        quarantine: Value::Default(false),
+        max_inhabited: Value::None,
+        older_than:    Value::None,
+        keep_radius:   Value::None,
+        dry_run:       Value::Default(false),
     };
 
     let mut args = std::env::args().enumerate().skip(1);
@@ -59,6 +116,54 @@ fn main() {
                 };
                 config.save_root = Value::User(dir.1);
             },
+            "-d" | "--dimension" => {
+                let Some(dim) = args.next() else {
+                    return println!("--dimension or -d argument requires a dimension parameter e.g 'rave --dimension nether'");
+                };
+                let Some(dimension) = config::Dimension::parse(&dim.1) else {
+                    return println!("unrecognized dimension '{}'. e.g 'rave --dimension < overworld | nether | end >'", dim.1);
+                };
+                config.dimension = Value::User(dimension);
+            },
+            "-c" | "--chunk" => {
+                let Some(coords) = args.next().and_then(|arg| config::coordinates(&arg.1)) else {
+                    return println!("--chunk or -c argument requires chunk coordinates e.g 'rave --chunk -3,12'");
+                };
+                config.chunk = Value::User(coords);
+            },
+            "-f" | "--file" => {
+                let Some(file) = args.next() else {
+                    return println!("--file or -f argument requires a path parameter e.g 'rave --file ~/my/rave/save/level.dat'");
+                };
+                config.file = Value::User(file.1);
+            },
+            "-j" | "--jobs" => {
+                let Some(jobs) = args.next().and_then(|arg| arg.1.parse().ok()).filter(|jobs| *jobs > 0) else {
+                    return println!("--jobs or -j argument requires a positive number of threads e.g 'rave search Name --jobs 8'");
+                };
+                config.jobs = Value::User(jobs);
+            },
+            "--strict" => config.strict = Value::User(true),
+            "--quarantine" => config.quarantine = Value::User(true),
+            "--max-inhabited" => {
+                let Some(ticks) = args.next().and_then(|arg| arg.1.parse().ok()).filter(|ticks| *ticks >= 0) else {
+                    return println!("--max-inhabited argument requires a number of ticks e.g 'rave trim --max-inhabited 200'");
+                };
+                config.max_inhabited = Value::User(ticks);
+            },
+            "--older-than" => {
+                let Some(seconds) = args.next().and_then(|arg| config::duration(&arg.1)) else {
+                    return println!("--older-than argument requires a duration e.g 'rave trim --older-than 90d'");
+                };
+                config.older_than = Value::User(seconds);
+            },
+            "--keep-radius" => {
+                let Some(radius) = args.next().and_then(|arg| arg.1.parse().ok()).filter(|radius| *radius >= 0) else {
+                    return println!("--keep-radius argument requires a distance in blocks e.g 'rave trim --keep-radius 500'");
+                };
+                config.keep_radius = Value::User(radius);
+            },
+            "--dry-run" => config.dry_run = Value::User(true),
             "list" | "l" => {
                 let Some(peek) = std::env::args().nth(i+1) else {
                     continue;
@@ -71,12 +176,220 @@ fn main() {
                     _ => return println!("unrecognized parameter '{}' given to list command. e.g 'rave list < r | region >'", peek),
                 }
             },
+            "search" | "s" if std::env::args().nth(i+1).as_deref() == Some("where") => {
+                let _ = args.next();
+                let expr = command_args(&mut args, i+2);
+                match nbt::query::predicate::Predicate::parse(&expr.join(" ")) {
+                    Ok(predicate) => config.command = Value::User(Command::Search(Method::Predicate(predicate))),
+                    Err(e)        => return println!("invalid search expression '{}': {}", expr.join(" "), e),
+                }
+            },
             "search" | "s" => {
                 let Some(name) = args.next() else {
                     return println!("please provide the 'search' or 's' command with a name to search for. e.g 'rave search | s < name >'");
                 };
                 config.command = Value::User(Command::Search(Method::Name(name.1)));
             },
+            "get" | "g" => {
+                let Some(path) = args.next() else {
+                    return println!("please provide the 'get' or 'g' command with a NBT path. e.g 'rave get < path >'");
+                };
+                match nbt::path::Path::parse(&path.1) {
+                    Ok(path) => config.command = Value::User(Command::Get(path)),
+                    Err(e)   => return println!("invalid NBT path '{}': {}", path.1, e),
+                }
+            },
+            "diff" => {
+                let (Some(old), Some(new)) = (args.next(), args.next()) else {
+                    return println!("please provide the 'diff' command with two saves or files to compare. e.g 'rave diff < old > < new >'");
+                };
+                config.command = Value::User(Command::Diff(old.1, new.1));
+            },
+            "diff-world" => {
+                let (Some(old), Some(new)) = (args.next(), args.next()) else {
+                    return println!("please provide the 'diff-world' command with two saves to compare. e.g 'rave diff-world < old_save > < new_save >'");
+                };
+                config.command = Value::User(Command::DiffWorld(old.1, new.1));
+            },
+            "fsck" => config.command = Value::User(Command::Fsck),
+            "repair" => config.command = Value::User(Command::Repair),
+            "trim" => config.command = Value::User(Command::Trim),
+            "delete-chunks" => {
+                let area = command_args(&mut args, i+1);
+                let Some(area) = config::Area::parse(&area) else {
+                    return println!("please provide the 'delete-chunks' command with an area in chunk coordinates. e.g 'rave delete-chunks < --from x,z --to x,z | --center x,z --radius r | --polygon x,z x,z x,z ... >'");
+                };
+                config.command = Value::User(Command::DeleteChunks(area));
+            },
+            "copy-chunks" => {
+                let (Some(from), Some(to)) = (args.next(), args.next()) else {
+                    return println!("please provide the 'copy-chunks' command with the save to copy from and the one to copy into. e.g 'rave copy-chunks < src > < dst > --from x,z --to x,z'");
+                };
+                let mut area = command_args(&mut args, i+3);
+                let mut offset = (0, 0);
+                if let Some(at) = area.iter().position(|arg| arg == "--offset") {
+                    let Some(parsed) = area.get(at+1).and_then(|arg| config::coordinates(arg)) else {
+                        return println!("--offset requires a distance in chunks e.g 'rave copy-chunks < src > < dst > --from 0,0 --to 3,3 --offset 100,-20'");
+                    };
+                    offset = parsed;
+                    area.drain(at..at+2);
+                }
+                let Some(area) = config::Area::parse(&area) else {
+                    return println!("please provide the 'copy-chunks' command with an area in chunk coordinates. e.g 'rave copy-chunks < src > < dst > < --from x,z --to x,z | --center x,z --radius r | --polygon x,z x,z x,z ... >'");
+                };
+                config.command = Value::User(Command::CopyChunks(from.1, to.1, area, offset));
+            },
+            "render" => {
+                let rest = command_args(&mut args, i+1);
+                let mut render = config::Render{ area: None, output: String::from("map.png"), hillshade: false, biome_tint: false };
+                let mut area = Vec::new();
+                let mut rest = rest.into_iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "-o" | "--output" => {
+                            let Some(output) = rest.next() else {
+                                return println!("-o or --output requires a path e.g 'rave render -o map.png'");
+                            };
+                            render.output = output;
+                        },
+                        "--hillshade" => render.hillshade = true,
+                        "--biome-tint" => render.biome_tint = true,
+                        _ => area.push(arg),
+                    }
+                }
+                if !area.is_empty() {
+                    let Some(parsed) = config::Area::parse(&area) else {
+                        return println!("unrecognized area given to 'render'. e.g 'rave render < --from x,z --to x,z | --center x,z --radius r | --polygon x,z x,z x,z ... >'");
+                    };
+                    render.area = Some(parsed);
+                }
+                config.command = Value::User(Command::Render(render));
+            },
+            "heatmap" => {
+                let mut metric = None;
+                let mut output = String::from("heatmap.png");
+                let mut area = Vec::new();
+                let mut rest = command_args(&mut args, i+1).into_iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--metric" => {
+                            let Some(parsed) = rest.next().and_then(|name| config::Metric::parse(&name)) else {
+                                return println!("--metric requires one of inhabited_time, last_modified, entity_count or block_entity_count e.g 'rave heatmap --metric inhabited_time'");
+                            };
+                            metric = Some(parsed);
+                        },
+                        "-o" | "--output" => {
+                            let Some(path) = rest.next() else {
+                                return println!("-o or --output requires a path e.g 'rave heatmap --metric entity_count -o lag.csv'");
+                            };
+                            output = path;
+                        },
+                        _ => area.push(arg),
+                    }
+                }
+                let Some(metric) = metric else {
+                    return println!("please provide the 'heatmap' command with a metric. e.g 'rave heatmap --metric < inhabited_time | last_modified | entity_count | block_entity_count >'");
+                };
+                let area = match area.is_empty() {
+                    true  => None,
+                    false => match config::Area::parse(&area) {
+                        Some(area) => Some(area),
+                        None => return println!("unrecognized area given to 'heatmap'. e.g 'rave heatmap --metric entity_count < --from x,z --to x,z | --center x,z --radius r >'"),
+                    },
+                };
+                config.command = Value::User(Command::Heatmap(config::Heatmap{ metric, area, output }));
+            },
+            "maps" => {
+                let mut maps = config::Maps{ ids: Vec::new(), export: None, stitch: None };
+                let mut rest = command_args(&mut args, i+1).into_iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--export" => {
+                            let Some(folder) = rest.next() else {
+                                return println!("--export requires a folder e.g 'rave maps --export maps/'");
+                            };
+                            maps.export = Some(folder);
+                        },
+                        "--stitch" => {
+                            let Some(output) = rest.next() else {
+                                return println!("--stitch requires a path e.g 'rave maps 3 4 5 6 --stitch art.png'");
+                            };
+                            maps.stitch = Some(output);
+                        },
+                        _ => match arg.parse() {
+                            Ok(id) => maps.ids.push(id),
+                            Err(_) => return println!("unrecognized map id '{}'. e.g 'rave maps 0 1 2'", arg),
+                        },
+                    }
+                }
+                config.command = Value::User(Command::Maps(maps));
+            },
+            "export-structure" => {
+                let (mut area, mut output) = (None, None);
+                let mut rest = command_args(&mut args, i+1).into_iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--area" => {
+                            let Some(parsed) = rest.next().and_then(|arg| config::block_box(&arg)) else {
+                                return println!("--area requires two corners e.g 'rave export-structure --area 0,60,0:15,80,15 -o build.schem'");
+                            };
+                            area = Some(parsed);
+                        },
+                        "-o" | "--output" => output = rest.next(),
+                        _ => return println!("unrecognized argument '{}' given to 'export-structure'", arg),
+                    }
+                }
+                let (Some(area), Some(output)) = (area, output) else {
+                    return println!("please provide the 'export-structure' command with an area and a .schem or .nbt file. e.g 'rave export-structure --area 0,60,0:15,80,15 -o build.schem'");
+                };
+                if !output.ends_with(".schem") && !output.ends_with(".nbt") {
+                    return println!("export-structure writes .schem or .nbt files, not '{}'", output);
+                }
+                config.command = Value::User(Command::ExportStructure(config::ExportStructure{ area, output }));
+            },
+            "paste" => {
+                let (mut file, mut at) = (None, None);
+                let mut rest = command_args(&mut args, i+1).into_iter();
+                while let Some(arg) = rest.next() {
+                    match arg.as_str() {
+                        "--at" => {
+                            let Some(parsed) = rest.next().and_then(|arg| config::position(&arg)) else {
+                                return println!("--at requires block coordinates e.g 'rave paste build.schem --at 100,64,-20'");
+                            };
+                            at = Some(parsed);
+                        },
+                        _ if file.is_none() => file = Some(arg),
+                        _ => return println!("unrecognized argument '{}' given to 'paste'", arg),
+                    }
+                }
+                let (Some(file), Some(at)) = (file, at) else {
+                    return println!("please provide the 'paste' command with a structure file and where to put it. e.g 'rave paste build.schem --at 100,64,-20'");
+                };
+                config.command = Value::User(Command::Paste(config::Paste{ file, at }));
+            },
+            "replace" => {
+                let mut rest = command_args(&mut args, i+1).into_iter();
+                let (Some(from), Some(to)) = (rest.next(), rest.next()) else {
+                    return println!("please provide the 'replace' command with the block to replace and its replacement. e.g 'rave replace mymod:ore minecraft:stone'");
+                };
+                /* blocks without a namespace are vanilla ones, like in commands */
+                let state = |arg: &str| match arg.split('[').next().is_some_and(|name| name.contains(':')) {
+                    true  => region::section::state_compound(arg),
+                    false => region::section::state_compound(&format!("minecraft:{}", arg)),
+                };
+                let (Some(from), Some(to)) = (state(&from), state(&to)) else {
+                    return println!("unrecognized block state given to 'replace'. e.g 'rave replace \"minecraft:oak_log[axis=y]\" minecraft:birch_log'");
+                };
+                let area: Vec<String> = rest.collect();
+                let area = match area.is_empty() {
+                    true  => None,
+                    false => match config::Area::parse(&area) {
+                        Some(area) => Some(area),
+                        None => return println!("unrecognized area given to 'replace'. e.g 'rave replace mymod:ore minecraft:stone < --from x,z --to x,z | --center x,z --radius r | --polygon x,z x,z x,z ... >'"),
+                    },
+                };
+                config.command = Value::User(Command::Replace(config::Replace{ from, to, area }));
+            },
             "--help" => {
                 println!("{}", usage());
                 return;
@@ -91,5 +404,6 @@ fn main() {
 
     if let Err(e) = gestalt::run(config) {
         println!("Error operating on save:\n\n{}.", e); 
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,346 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use std::collections::HashMap;
+
+use crate::nbt::{
+    self, NBT, Payload, TAGByte, TAGShort, TAGInt, TAGLong, TAGFloat, TAGDouble,
+    TAGString, TAGList, TAGCompound, TAGByteArray, TAGIArray, TAGLArray,
+};
+
+pub enum Error {
+    Missing(String),
+    Type {
+        key:      Option<String>,
+        expected: &'static str,
+        found:    &'static str,
+    },
+    Utf8(Option<String>),
+}
+
+/// Tag types that can be borrowed straight out of a `Payload`, used for typed list access.
+pub trait FromPayload {
+    const ID: TAGByte;
+    fn from_payload(payload: &Payload) -> Option<&Self>;
+}
+
+macro_rules! from_payload {
+    ($tag:ty, $variant:ident, $id:expr) => {
+        impl FromPayload for $tag {
+            const ID: TAGByte = $id;
+            fn from_payload(payload: &Payload) -> Option<&Self> {
+                match payload {
+                    Payload::$variant(value) => Some(value),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+from_payload!(TAGByte, Byte, 1);
+from_payload!(TAGShort, Short, 2);
+from_payload!(TAGInt, Int, 3);
+from_payload!(TAGLong, Long, 4);
+from_payload!(TAGFloat, Float, 5);
+from_payload!(TAGDouble, Double, 6);
+from_payload!(TAGByteArray, BArray, 7);
+from_payload!(TAGString, String, 8);
+from_payload!(TAGList, List, 9);
+from_payload!(TAGCompound, Compound, 10);
+from_payload!(TAGIArray, IArray, 11);
+from_payload!(TAGLArray, LArray, 12);
+
+fn mismatch(expected: TAGByte, payload: &Payload) -> Error {
+    Error::Type{ key: None, expected: nbt::type_name(expected), found: nbt::type_name(payload.id()) }
+}
+
+impl Payload {
+    pub fn as_tag<T: FromPayload>(&self) -> Result<&T, Error> {
+        T::from_payload(self).ok_or_else(|| mismatch(T::ID, self))
+    }
+
+    pub fn as_str(&self) -> Result<&str, Error> {
+        match self.as_tag::<TAGString>()?.as_str() {
+            Some(str) => Ok(str),
+            None      => Err(Error::Utf8(None)),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&TAGList, Error> {
+        self.as_tag()
+    }
+
+    pub fn as_compound(&self) -> Result<&TAGCompound, Error> {
+        self.as_tag()
+    }
+
+    pub fn as_list_mut(&mut self) -> Result<&mut TAGList, Error> {
+        match self {
+            Payload::List(list) => Ok(list),
+            _ => Err(mismatch(9, self)),
+        }
+    }
+
+    pub fn as_compound_mut(&mut self) -> Result<&mut TAGCompound, Error> {
+        match self {
+            Payload::Compound(compound) => Ok(compound),
+            _ => Err(mismatch(10, self)),
+        }
+    }
+
+    /// Any numeric tag widened to an `i64`, for fields whose type changed between game versions.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Payload::Byte(b)  => Some(*b as i8 as i64),
+            Payload::Short(s) => Some(*s as i64),
+            Payload::Int(i)   => Some(*i as i64),
+            Payload::Long(l)  => Some(*l),
+            _ => None,
+        }
+    }
+}
+
+impl TAGString {
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.str).ok()
+    }
+}
+
+impl From<&str> for TAGString {
+    fn from(value: &str) -> Self {
+        Self{ str: value.as_bytes().to_vec() }
+    }
+}
+
+impl TAGList {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Payload> {
+        self.tags.iter()
+    }
+
+    pub fn list_of<T: FromPayload>(&self) -> Result<Vec<&T>, Error> {
+        self.tags.iter().map(|tag| tag.as_tag()).collect()
+    }
+}
+
+impl TAGCompound {
+    /// Child tags, without the closing `End` tag.
+    pub fn iter(&self) -> impl Iterator<Item = &NBT> {
+        self.tags.iter().filter(|tag| tag.payload != Payload::End)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Payload> {
+        self.tags.iter_mut()
+            .find(|tag| tag.name.str == name.as_bytes() && tag.payload != Payload::End)
+            .map(|tag| &mut tag.payload)
+    }
+
+    /// Replaces the tag called `name`, or adds it ahead of the closing `End` tag.
+    pub fn insert(&mut self, name: &str, payload: Payload) -> Option<Payload> {
+        if let Some(existing) = self.get_mut(name) {
+            return Some(std::mem::replace(existing, payload));
+        }
+        let at = self.tags.iter().position(|tag| tag.payload == Payload::End).unwrap_or(self.tags.len());
+        self.tags.insert(at, NBT{ name: TAGString::from(name), payload });
+        if at == self.tags.len() - 1 {
+            self.tags.push(NBT::default());
+        }
+        None
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Payload> {
+        let at = self.tags.iter().position(|tag| tag.name.str == name.as_bytes() && tag.payload != Payload::End)?;
+        Some(self.tags.remove(at).payload)
+    }
+
+    /// Builds a hash index over the children so repeated lookups on large compounds are O(1).
+    pub fn index(&self) -> Indexed<'_> {
+        let mut tags = HashMap::with_capacity(self.tags.len());
+        for tag in self.iter() {
+            tags.entry(tag.name.str.as_slice()).or_insert(&tag.payload);
+        }
+        Indexed{ tags }
+    }
+}
+
+/// A borrowed, hashed view of a `TAGCompound`.
+pub struct Indexed<'a> {
+    tags: HashMap<&'a [u8], &'a Payload>,
+}
+
+/// Named, typed lookups shared by `TAGCompound` (linear scan) and its `Indexed` view.
+pub trait Lookup {
+    fn get(&self, name: &str) -> Option<&Payload>;
+
+    fn get_tag<T: FromPayload>(&self, name: &str) -> Result<&T, Error> {
+        let Some(payload) = self.get(name) else {
+            return Err(Error::Missing(String::from(name)));
+        };
+        payload.as_tag().map_err(|e| e.key(name))
+    }
+
+    fn get_byte(&self, name: &str) -> Result<TAGByte, Error> {
+        self.get_tag(name).copied()
+    }
+
+    fn get_short(&self, name: &str) -> Result<TAGShort, Error> {
+        self.get_tag(name).copied()
+    }
+
+    fn get_int(&self, name: &str) -> Result<TAGInt, Error> {
+        self.get_tag(name).copied()
+    }
+
+    fn get_long(&self, name: &str) -> Result<TAGLong, Error> {
+        self.get_tag(name).copied()
+    }
+
+    fn get_str(&self, name: &str) -> Result<&str, Error> {
+        match self.get_tag::<TAGString>(name)?.as_str() {
+            Some(str) => Ok(str),
+            None      => Err(Error::Utf8(Some(String::from(name)))),
+        }
+    }
+
+    fn get_byte_array(&self, name: &str) -> Result<&[TAGByte], Error> {
+        Ok(&self.get_tag::<TAGByteArray>(name)?.body)
+    }
+
+    fn get_int_array(&self, name: &str) -> Result<&[TAGInt], Error> {
+        Ok(&self.get_tag::<TAGIArray>(name)?.ints)
+    }
+
+    fn get_long_array(&self, name: &str) -> Result<&[TAGLong], Error> {
+        Ok(&self.get_tag::<TAGLArray>(name)?.longs)
+    }
+
+    fn get_list(&self, name: &str) -> Result<&TAGList, Error> {
+        self.get_tag(name)
+    }
+
+    fn get_compound(&self, name: &str) -> Result<&TAGCompound, Error> {
+        self.get_tag(name)
+    }
+
+    fn get_list_of<T: FromPayload>(&self, name: &str) -> Result<Vec<&T>, Error> {
+        self.get_list(name)?.list_of().map_err(|e| e.key(name))
+    }
+}
+
+impl Lookup for TAGCompound {
+    fn get(&self, name: &str) -> Option<&Payload> {
+        self.iter().find(|tag| tag.name.str == name.as_bytes()).map(|tag| &tag.payload)
+    }
+}
+
+impl Lookup for Indexed<'_> {
+    fn get(&self, name: &str) -> Option<&Payload> {
+        self.tags.get(name.as_bytes()).copied()
+    }
+}
+
+impl Error {
+    fn key(self, name: &str) -> Self {
+        match self {
+            Error::Type{ key: None, expected, found } => Error::Type{ key: Some(String::from(name)), expected, found },
+            Error::Utf8(None) => Error::Utf8(Some(String::from(name))),
+            e => e,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Missing(key) => write!(f, "missing tag '{}'", key),
+            Error::Type{ key: Some(key), expected, found } => write!(f, "tag '{}' is a {}, expected a {}", key, found, expected),
+            Error::Type{ key: None, expected, found } => write!(f, "tag is a {}, expected a {}", found, expected),
+            Error::Utf8(Some(key)) => write!(f, "tag '{}' is not valid UTF-8", key),
+            Error::Utf8(None) => write!(f, "string tag is not valid UTF-8"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Lookup};
+    use crate::nbt::{Payload, TAGCompound, TAGList, TAGString};
+    use crate::nbt::build::Builder;
+
+    fn chunk() -> TAGCompound {
+        Builder::new()
+            .tag("DataVersion", 3955)
+            .tag("Status", "minecraft:full")
+            .tag("InhabitedTime", 120i64)
+            .tag("yPos", -4i8)
+            .tag("Heights", crate::nbt::TAGLArray{ longs: vec![1, 2] })
+            .tag("block_entities", TAGList::of(vec![Payload::Compound(Builder::new().tag("id", "minecraft:chest").build())]).unwrap())
+            .tag("Empty", TAGList::of(Vec::new()).unwrap())
+            .tag("Bad", TAGString{ str: vec![0xff, 0xfe] })
+            .build()
+    }
+
+    #[test]
+    fn typed_lookups() {
+        let chunk = chunk();
+        assert!(chunk.get_int("DataVersion").is_ok_and(|version| version == 3955));
+        assert!(chunk.get_str("Status").is_ok_and(|status| status == "minecraft:full"));
+        assert!(chunk.get_long_array("Heights").is_ok_and(|heights| heights == [1, 2]));
+        assert!(chunk.get_long("InhabitedTime").is_ok_and(|ticks| ticks == 120));
+        assert!(matches!(chunk.get_long("DataVersion"), Err(Error::Type{ .. })));
+        assert!(chunk.get("InhabitedTime").and_then(Payload::as_integer) == Some(120));
+        assert!(chunk.get("yPos").and_then(Payload::as_integer) == Some(-4));
+        assert!(chunk.get("Missing").is_none());
+    }
+
+    #[test]
+    fn lists_of() {
+        let chunk = chunk();
+        let block_entities = chunk.get_list_of::<TAGCompound>("block_entities");
+        assert!(block_entities.is_ok_and(|list| list.len() == 1 && list[0].get_str("id").is_ok_and(|id| id == "minecraft:chest")));
+        assert!(chunk.get_list_of::<TAGCompound>("Empty").is_ok_and(|list| list.is_empty()));
+        assert!(matches!(chunk.get_list_of::<i32>("block_entities"), Err(Error::Type{ .. })));
+    }
+
+    #[test]
+    fn errors_name_the_tag() {
+        let chunk = chunk();
+        let message = |error: Result<i32, Error>| error.err().map(|e| e.to_string()).unwrap_or_default();
+        assert!(message(chunk.get_int("Status")) == "tag 'Status' is a string, expected a int");
+        assert!(message(chunk.get_int("Missing")) == "missing tag 'Missing'");
+        assert!(matches!(chunk.get_str("Bad"), Err(Error::Utf8(Some(key))) if key == "Bad"));
+        assert!(matches!(Payload::Int(1).as_str(), Err(Error::Type{ key: None, .. })));
+    }
+
+    #[test]
+    fn index_agrees_with_scan() {
+        let chunk = chunk();
+        let index = chunk.index();
+        for tag in chunk.iter() {
+            let name = tag.name.as_str().unwrap();
+            assert!(index.get(name) == chunk.get(name));
+        }
+        assert!(index.get_int("DataVersion").is_ok_and(|version| version == 3955));
+        assert!(index.get("").is_none());
+    }
+
+    #[test]
+    fn insert_and_remove_keep_the_end_tag() {
+        let mut chunk = chunk();
+        let len = chunk.tags.len();
+        assert!(chunk.insert("DataVersion", Payload::Int(1)) == Some(Payload::Int(3955)));
+        assert!(chunk.insert("New", Payload::Byte(1)).is_none());
+        assert!(chunk.tags.len() == len + 1 && chunk.tags.last().is_some_and(|tag| tag.payload == Payload::End));
+        assert!(chunk.remove("New") == Some(Payload::Byte(1)));
+        assert!(chunk.remove("New").is_none());
+
+        let mut empty = TAGCompound{ tags: Vec::new() };
+        empty.insert("a", Payload::Int(1));
+        assert!(empty.tags.len() == 2 && empty.get_int("a").is_ok());
+    }
+}
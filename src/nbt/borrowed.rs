@@ -0,0 +1,325 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::nbt::{
+    Error, NBT, Payload, TAGByte, TAGShort, TAGInt, TAGLong, TAGFloat, TAGDouble,
+    TAGString, TAGList, TAGCompound, TAGByteArray, TAGIArray, TAGLArray,
+};
+use crate::nbt::visit::{Reader, Ints, Longs};
+
+/* `NBTRef::parse` walks the whole tree once, so reading any view it hands out can't fail */
+fn valid<T>(result: Result<T, Error>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e)    => panic!("NBT validated by NBTRef::parse failed to read: {}", e),
+    }
+}
+
+/// A tree that borrows the bytes it was parsed from. Compounds and lists are only decoded as
+/// they're iterated and arrays only as their elements are read, so nothing is copied up front.
+#[derive(Clone, Copy)]
+pub struct NBTRef<'a> {
+    pub name:    &'a [u8],
+    pub payload: PayloadRef<'a>,
+}
+
+#[derive(Clone, Copy)]
+pub enum PayloadRef<'a> {
+    End,
+    Byte(TAGByte),
+    Short(TAGShort),
+    Int(TAGInt),
+    Long(TAGLong),
+    Float(TAGFloat),
+    Double(TAGDouble),
+    BArray(&'a [TAGByte]),
+    String(&'a [u8]),
+    List(ListRef<'a>),
+    Compound(CompoundRef<'a>),
+    IArray(IntArrayRef<'a>),
+    LArray(LongArrayRef<'a>),
+}
+
+/// The tags of a compound, up to and including its closing `End` tag.
+#[derive(Clone, Copy)]
+pub struct CompoundRef<'a> {
+    bytes: &'a [u8],
+}
+
+#[derive(Clone, Copy)]
+pub struct ListRef<'a> {
+    id:     TAGByte,
+    length: usize,
+    bytes:  &'a [u8],
+}
+
+#[derive(Clone, Copy)]
+pub struct IntArrayRef<'a> {
+    bytes: &'a [u8],
+}
+
+#[derive(Clone, Copy)]
+pub struct LongArrayRef<'a> {
+    bytes: &'a [u8],
+}
+
+pub struct Tags<'a> {
+    reader: Reader<'a>,
+}
+
+pub struct Elements<'a> {
+    reader: Reader<'a>,
+    id:     TAGByte,
+    left:   usize,
+}
+
+/// Reads the payload of type `id` at the reader's position, stepping over it.
+fn payload<'a>(reader: &mut Reader<'a>, id: TAGByte) -> Result<PayloadRef<'a>, Error> {
+    let start = reader.pos;
+    Ok(match id {
+        0  => PayloadRef::End,
+        1  => PayloadRef::Byte(reader.byte()?),
+        2  => PayloadRef::Short(reader.short()?),
+        3  => PayloadRef::Int(reader.int()?),
+        4  => PayloadRef::Long(reader.long()?),
+        5  => PayloadRef::Float(TAGFloat::from_bits(reader.int()? as u32)),
+        6  => PayloadRef::Double(TAGDouble::from_bits(reader.long()? as u64)),
+        7  => PayloadRef::BArray(reader.array(1)?),
+        8  => PayloadRef::String(reader.string()?),
+        9  => {
+            let id = reader.byte()?;
+            let length = reader.length()?;
+            reader.pos = start;
+            reader.skip(9)?;
+            PayloadRef::List(ListRef{ id, length, bytes: &reader.bytes[start+5..reader.pos] })
+        },
+        10 => {
+            reader.skip(10)?;
+            PayloadRef::Compound(CompoundRef{ bytes: &reader.bytes[start..reader.pos] })
+        },
+        11 => PayloadRef::IArray(IntArrayRef{ bytes: reader.array(4)? }),
+        12 => PayloadRef::LArray(LongArrayRef{ bytes: reader.array(8)? }),
+        _  => return Err(Error::InvalidByteSequence(start as u64, id)),
+    })
+}
+
+impl<'a> NBTRef<'a> {
+    /// Checks the structure of the whole tree without allocating, then hands out a view of its root.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(bytes);
+        let id = reader.byte()?;
+        if id == 0 {
+            return Ok(Self{ name: &[], payload: PayloadRef::End });
+        }
+        let name = reader.string()?;
+        Ok(Self{ name, payload: payload(&mut reader, id)? })
+    }
+
+    pub fn to_nbt(self) -> NBT {
+        NBT{ name: TAGString{ str: self.name.to_vec() }, payload: self.payload.to_payload() }
+    }
+}
+
+impl<'a> PayloadRef<'a> {
+    pub fn id(&self) -> TAGByte {
+        match self {
+            PayloadRef::End         => 0,
+            PayloadRef::Byte(_)     => 1,
+            PayloadRef::Short(_)    => 2,
+            PayloadRef::Int(_)      => 3,
+            PayloadRef::Long(_)     => 4,
+            PayloadRef::Float(_)    => 5,
+            PayloadRef::Double(_)   => 6,
+            PayloadRef::BArray(_)   => 7,
+            PayloadRef::String(_)   => 8,
+            PayloadRef::List(_)     => 9,
+            PayloadRef::Compound(_) => 10,
+            PayloadRef::IArray(_)   => 11,
+            PayloadRef::LArray(_)   => 12,
+        }
+    }
+
+    /// Copies the view into an owned `Payload`.
+    pub fn to_payload(self) -> Payload {
+        match self {
+            PayloadRef::End        => Payload::End,
+            PayloadRef::Byte(b)    => Payload::Byte(b),
+            PayloadRef::Short(s)   => Payload::Short(s),
+            PayloadRef::Int(i)     => Payload::Int(i),
+            PayloadRef::Long(l)    => Payload::Long(l),
+            PayloadRef::Float(f)   => Payload::Float(f),
+            PayloadRef::Double(d)  => Payload::Double(d),
+            PayloadRef::BArray(body) => Payload::BArray(TAGByteArray{ body: body.to_vec() }),
+            PayloadRef::String(str)  => Payload::String(TAGString{ str: str.to_vec() }),
+            PayloadRef::List(list) => Payload::List(TAGList{
+                id:   list.id,
+                tags: list.iter().map(|tag| tag.to_payload()).collect(),
+            }),
+            PayloadRef::Compound(compound) => {
+                let mut tags: Vec<NBT> = compound.iter().map(|tag| tag.to_nbt()).collect();
+                tags.push(NBT::default());
+                Payload::Compound(TAGCompound{ tags })
+            },
+            PayloadRef::IArray(array) => Payload::IArray(TAGIArray{ ints: array.iter().collect() }),
+            PayloadRef::LArray(array) => Payload::LArray(TAGLArray{ longs: array.iter().collect() }),
+        }
+    }
+}
+
+impl<'a> CompoundRef<'a> {
+    /// Child tags, without the closing `End` tag.
+    pub fn iter(&self) -> Tags<'a> {
+        Tags{ reader: Reader::new(self.bytes) }
+    }
+
+    pub fn get(&self, name: &str) -> Option<PayloadRef<'a>> {
+        self.iter().find(|tag| tag.name == name.as_bytes()).map(|tag| tag.payload)
+    }
+}
+
+impl<'a> ListRef<'a> {
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn iter(&self) -> Elements<'a> {
+        Elements{ reader: Reader::new(self.bytes), id: self.id, left: self.length }
+    }
+}
+
+impl<'a> IntArrayRef<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 4
+    }
+
+    pub fn get(&self, index: usize) -> Option<TAGInt> {
+        let int = self.bytes.get(index*4..index*4+4)?;
+        Some(TAGInt::from_be_bytes(int.try_into().unwrap()))
+    }
+
+    pub fn iter(&self) -> Ints<'a> {
+        Ints{ bytes: self.bytes.chunks_exact(4) }
+    }
+}
+
+impl<'a> LongArrayRef<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / 8
+    }
+
+    pub fn get(&self, index: usize) -> Option<TAGLong> {
+        let long = self.bytes.get(index*8..index*8+8)?;
+        Some(TAGLong::from_be_bytes(long.try_into().unwrap()))
+    }
+
+    pub fn iter(&self) -> Longs<'a> {
+        Longs{ bytes: self.bytes.chunks_exact(8) }
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = NBTRef<'a>;
+
+    fn next(&mut self) -> Option<NBTRef<'a>> {
+        let id = valid(self.reader.byte());
+        if id == 0 {
+            /* park on the End tag so the iterator stays fused */
+            self.reader.pos -= 1;
+            return None;
+        }
+        let name = valid(self.reader.string());
+        Some(NBTRef{ name, payload: valid(payload(&mut self.reader, id)) })
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = PayloadRef<'a>;
+
+    fn next(&mut self) -> Option<PayloadRef<'a>> {
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        Some(valid(payload(&mut self.reader, self.id)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NBTRef, PayloadRef};
+    use crate::nbt::{Parser, Writer, NBT};
+
+    fn tree() -> NBT {
+        crate::nbt!{
+            "DataVersion": 3955,
+            "Position": [I; 3, -7],
+            "Heightmaps": { "WORLD_SURFACE": [L; 1l, -2l, 3l] },
+            "Biomes": [B; 1b, 2b],
+            "Entities": [
+                { "id": "minecraft:pig", "Pos": [0.5d, 64.0d, -3.25d], "Rotation": [90.0f, 0.0f] },
+                { "id": "minecraft:cow", "Age": -24000s, "UUID": [I; 1, 2, 3, 4] },
+            ],
+            "Lists": [[1b, 2b], ["a"], []],
+            "Empty": {},
+            "名前": "値",
+        }
+    }
+
+    fn bytes(root: &NBT) -> Vec<u8> {
+        let mut writer = Writer::new();
+        assert!(writer.write(root).is_ok());
+        writer.bytes()
+    }
+
+    #[test]
+    fn round_trips_against_the_owned_parser() {
+        let bytes = bytes(&tree());
+        let mut owned = NBT::default();
+        assert!(Parser::new(bytes.clone()).parse(&mut owned).is_ok());
+        let Ok(borrowed) = NBTRef::parse(&bytes) else {
+            panic!("NBTRef::parse rejected the writer's output");
+        };
+        assert!(borrowed.to_nbt() == owned);
+        assert!(owned == tree());
+    }
+
+    #[test]
+    fn views_read_in_place() {
+        let bytes = bytes(&tree());
+        let Ok(NBTRef{ payload: PayloadRef::Compound(root), .. }) = NBTRef::parse(&bytes) else {
+            panic!("root is not a compound");
+        };
+        assert!(matches!(root.get("DataVersion"), Some(PayloadRef::Int(3955))));
+        let Some(PayloadRef::IArray(position)) = root.get("Position") else {
+            panic!("Position is not an int array");
+        };
+        assert!(position.len() == 2 && position.get(1) == Some(-7) && position.get(2).is_none());
+        let Some(PayloadRef::List(entities)) = root.get("Entities") else {
+            panic!("Entities is not a list");
+        };
+        assert!(entities.len() == 2 && entities.iter().count() == 2);
+        let ids: Vec<_> = entities.iter().filter_map(|entity| match entity {
+            PayloadRef::Compound(entity) => entity.get("id").map(|id| id.to_payload()),
+            _ => None,
+        }).collect();
+        assert!(ids == [crate::nbt::Payload::from("minecraft:pig"), crate::nbt::Payload::from("minecraft:cow")]);
+        assert!(root.get("Missing").is_none());
+    }
+
+    #[test]
+    fn truncated_input_fails_up_front() {
+        let bytes = bytes(&tree());
+        for end in [1, 3, bytes.len() / 2, bytes.len() - 1] {
+            assert!(NBTRef::parse(&bytes[..end]).is_err(), "{} of {} bytes parsed", end, bytes.len());
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::nbt::{
+    snbt, NBT, Payload, TAGByte, TAGShort, TAGInt, TAGLong, TAGFloat, TAGDouble,
+    TAGString, TAGList, TAGCompound, TAGByteArray, TAGIArray, TAGLArray,
+};
+
+/// Builds a compound one tag at a time and closes it with the `End` tag the parser expects.
+///
+/// ```ignore
+/// let item = Builder::new()
+///     .tag("Count", 1i8)
+///     .tag("id", "minecraft:stone")
+///     .tag("tag", Builder::new().tag("Damage", 0))
+///     .root("");
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    tags: Vec<NBT>,
+}
+
+impl Builder {
+    /// Adds `name`, replacing an earlier tag of the same name like the game does.
+    pub fn tag(mut self, name: &str, payload: impl Into<Payload>) -> Self {
+        let payload = payload.into();
+        match self.tags.iter_mut().find(|tag| tag.name.str == name.as_bytes()) {
+            Some(tag) => tag.payload = payload,
+            None      => self.tags.push(NBT{ name: TAGString::from(name), payload }),
+        }
+        self
+    }
+
+    pub fn build(mut self) -> TAGCompound {
+        self.tags.push(NBT::default());
+        TAGCompound{ tags: self.tags }
+    }
+
+    /// Finishes the compound as the root of a tree, which is usually unnamed.
+    pub fn root(self, name: &str) -> NBT {
+        NBT{ name: TAGString::from(name), payload: Payload::Compound(self.build()) }
+    }
+
+    pub fn new() -> Self {
+        Self { tags: Vec::new() }
+    }
+}
+
+impl TAGList {
+    /// A list of `tags`, or `None` when they don't all share one type.
+    pub fn of(tags: Vec<Payload>) -> Option<Self> {
+        let id = tags.first().map(Payload::id).unwrap_or(0);
+        if tags.iter().any(|tag| tag.id() != id) {
+            return None;
+        }
+        Some(Self{ id, tags })
+    }
+}
+
+macro_rules! into_payload {
+    ($from:ty, $variant:ident) => {
+        impl From<$from> for Payload {
+            fn from(value: $from) -> Self {
+                Payload::$variant(value)
+            }
+        }
+    };
+}
+
+into_payload!(TAGByte, Byte);
+into_payload!(TAGShort, Short);
+into_payload!(TAGInt, Int);
+into_payload!(TAGLong, Long);
+into_payload!(TAGFloat, Float);
+into_payload!(TAGDouble, Double);
+into_payload!(TAGByteArray, BArray);
+into_payload!(TAGString, String);
+into_payload!(TAGList, List);
+into_payload!(TAGCompound, Compound);
+into_payload!(TAGIArray, IArray);
+into_payload!(TAGLArray, LArray);
+
+impl From<i8> for Payload {
+    fn from(value: i8) -> Self {
+        Payload::Byte(value as TAGByte)
+    }
+}
+
+impl From<bool> for Payload {
+    fn from(value: bool) -> Self {
+        Payload::Byte(value as TAGByte)
+    }
+}
+
+impl From<&str> for Payload {
+    fn from(value: &str) -> Self {
+        Payload::String(TAGString::from(value))
+    }
+}
+
+impl From<String> for Payload {
+    fn from(value: String) -> Self {
+        Payload::String(TAGString::from(value))
+    }
+}
+
+impl From<Builder> for Payload {
+    fn from(value: Builder) -> Self {
+        Payload::Compound(value.build())
+    }
+}
+
+#[doc(hidden)]
+pub fn literal(src: &str) -> Payload {
+    match snbt::Parser::at(src, 0).value() {
+        Ok(payload) => payload,
+        Err(e)      => panic!("nbt!: '{}' is not a valid tag: {}", src, e),
+    }
+}
+
+#[doc(hidden)]
+pub fn list(tags: Vec<Payload>) -> Payload {
+    match TAGList::of(tags) {
+        Some(list) => Payload::List(list),
+        None       => panic!("nbt!: list elements must all be the same type"),
+    }
+}
+
+/// Builds an unnamed root compound from SNBT-like syntax, typed the way the game types SNBT:
+///
+/// ```ignore
+/// let item = nbt!{
+///     "Count": 1b,
+///     "id": "minecraft:stone",
+///     "tag": { "Damage": 0, "Lore": ["a", "b"], "Seeds": [L; 1l, -2l] },
+///     "Slot": (slot),
+/// };
+/// ```
+///
+/// Keys are string literals. Scalars take the SNBT suffixes (`b`, `s`, `l`, `f`, `d`), typed arrays
+/// are written `[B; ..]`, `[I; ..]` and `[L; ..]`, and a parenthesized expression is converted with
+/// `Payload::from`. Lists whose elements differ in type panic.
+#[macro_export]
+macro_rules! nbt {
+    (@compound $builder:expr ;) => { $builder };
+    (@compound $builder:expr ; $key:literal : - $value:literal $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@compound $builder.tag($key, $crate::nbt!(@value - $value)) ; $($($rest)*)?)
+    };
+    (@compound $builder:expr ; $key:literal : $value:tt $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@compound $builder.tag($key, $crate::nbt!(@value $value)) ; $($($rest)*)?)
+    };
+
+    (@elements [$($out:expr),*]) => { vec![$($out),*] };
+    (@elements [$($out:expr),*] - $value:literal $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@elements [$($out,)* $crate::nbt!(@value - $value)] $($($rest)*)?)
+    };
+    (@elements [$($out:expr),*] $value:tt $(, $($rest:tt)*)?) => {
+        $crate::nbt!(@elements [$($out,)* $crate::nbt!(@value $value)] $($($rest)*)?)
+    };
+
+    (@value { $($body:tt)* }) => {
+        $crate::nbt::Payload::Compound($crate::nbt!(@compound $crate::nbt::build::Builder::new() ; $($body)*).build())
+    };
+    (@value [B; $($body:tt)*]) => { $crate::nbt::build::literal(stringify!([B; $($body)*])) };
+    (@value [I; $($body:tt)*]) => { $crate::nbt::build::literal(stringify!([I; $($body)*])) };
+    (@value [L; $($body:tt)*]) => { $crate::nbt::build::literal(stringify!([L; $($body)*])) };
+    (@value [$($body:tt)*]) => { $crate::nbt::build::list($crate::nbt!(@elements [] $($body)*)) };
+    (@value ($value:expr)) => { $crate::nbt::Payload::from($value) };
+    (@value - $value:literal) => { $crate::nbt::build::literal(concat!("-", stringify!($value))) };
+    (@value $value:literal) => { $crate::nbt::build::literal(stringify!($value)) };
+
+    ($($body:tt)*) => {
+        $crate::nbt!(@compound $crate::nbt::build::Builder::new() ; $($body)*).root("")
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Builder;
+    use crate::nbt::{NBT, Payload, TAGCompound, TAGList, TAGString, TAGByteArray, TAGIArray, TAGLArray};
+
+    fn tag(name: &str, payload: Payload) -> NBT {
+        NBT{ name: TAGString::from(name), payload }
+    }
+
+    fn end() -> NBT {
+        NBT{ name: TAGString{ str: Vec::new() }, payload: Payload::End }
+    }
+
+    #[test]
+    fn macro_matches_hand_built_tree() {
+        let slot = 3i8;
+        let built = crate::nbt!{
+            "Count": 1b,
+            "Damage": -2s,
+            "id": "minecraft:stone",
+            "Slot": (slot),
+            "tag": {
+                "Lore": ["a", "b"],
+                "Seed": -5l,
+                "Speed": 0.5f,
+                "Pos": [1.0d, -2.5d],
+                "Empty": [],
+            },
+            "Bytes": [B; 1b, 2b],
+            "Ints": [I; 1, -2],
+            "Longs": [L; 3l],
+        };
+
+        let nested = TAGCompound{ tags: vec![
+            tag("Lore", Payload::List(TAGList::of(vec![Payload::from("a"), Payload::from("b")]).unwrap())),
+            tag("Seed", Payload::Long(-5)),
+            tag("Speed", Payload::Float(0.5)),
+            tag("Pos", Payload::List(TAGList::of(vec![Payload::Double(1.0), Payload::Double(-2.5)]).unwrap())),
+            tag("Empty", Payload::List(TAGList::of(Vec::new()).unwrap())),
+            end(),
+        ]};
+        let expected = NBT{ name: TAGString::from(""), payload: Payload::Compound(TAGCompound{ tags: vec![
+            tag("Count", Payload::Byte(1)),
+            tag("Damage", Payload::Short(-2)),
+            tag("id", Payload::String(TAGString::from("minecraft:stone"))),
+            tag("Slot", Payload::Byte(3)),
+            tag("tag", Payload::Compound(nested)),
+            tag("Bytes", Payload::BArray(TAGByteArray{ body: vec![1, 2] })),
+            tag("Ints", Payload::IArray(TAGIArray{ ints: vec![1, -2] })),
+            tag("Longs", Payload::LArray(TAGLArray{ longs: vec![3] })),
+            end(),
+        ]})};
+        assert!(built == expected);
+    }
+
+    #[test]
+    fn list_element_type() {
+        let built = crate::nbt!{ "Lore": ["a"], "Empty": [] };
+        let Payload::Compound(root) = built.payload else {
+            panic!("root is not a compound");
+        };
+        assert!(root.tags[0].payload.as_list().is_ok_and(|list| list.id == 8));
+        assert!(root.tags[1].payload.as_list().is_ok_and(|list| list.id == 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn mixed_lists_panic() {
+        crate::nbt!{ "Mixed": [1, "a"] };
+    }
+
+    #[test]
+    fn builder_replaces_repeated_names() {
+        let built = Builder::new().tag("a", 1).tag("b", 2).tag("a", 3).build();
+        assert!(built.tags.len() == 3 && built.tags[0].payload == Payload::Int(3));
+        assert!(built.tags[2].payload == Payload::End);
+    }
+}
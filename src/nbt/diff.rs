@@ -0,0 +1,129 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use colored::*;
+
+use crate::nbt::{path, NBT, Payload, TAGCompound};
+
+pub enum Change<'a> {
+    Added(&'a Payload),
+    Removed(&'a Payload),
+    Changed(&'a Payload, &'a Payload),
+}
+
+/// One tag that differs between two trees, addressed by its NBT path.
+pub struct Difference<'a> {
+    pub path:   String,
+    pub change: Change<'a>,
+}
+
+fn compound<'a>(parent: &str, old: &'a TAGCompound, new: &'a TAGCompound, out: &mut Vec<Difference<'a>>) {
+    for was in old.iter() {
+        let path = path::child(parent, &was.name.str);
+        match new.iter().find(|now| now.name == was.name) {
+            Some(now) => payload(path, &was.payload, &now.payload, out),
+            None      => out.push(Difference{ path, change: Change::Removed(&was.payload) }),
+        }
+    }
+    for now in new.iter() {
+        if !old.iter().any(|was| was.name == now.name) {
+            out.push(Difference{ path: path::child(parent, &now.name.str), change: Change::Added(&now.payload) });
+        }
+    }
+}
+
+fn payload<'a>(path: String, old: &'a Payload, new: &'a Payload, out: &mut Vec<Difference<'a>>) {
+    match (old, new) {
+        (Payload::Compound(old), Payload::Compound(new)) => compound(&path, old, new, out),
+        (Payload::List(was), Payload::List(now)) if was.id == now.id || was.tags.is_empty() || now.tags.is_empty() => {
+            for (i, (was, now)) in was.tags.iter().zip(now.tags.iter()).enumerate() {
+                payload(format!("{}[{}]", path, i), was, now, out);
+            }
+            for (i, was) in was.tags.iter().enumerate().skip(now.tags.len()) {
+                out.push(Difference{ path: format!("{}[{}]", path, i), change: Change::Removed(was) });
+            }
+            for (i, now) in now.tags.iter().enumerate().skip(was.tags.len()) {
+                out.push(Difference{ path: format!("{}[{}]", path, i), change: Change::Added(now) });
+            }
+        },
+        _ if old != new => out.push(Difference{ path, change: Change::Changed(old, new) }),
+        _ => (),
+    }
+}
+
+/// Compares two trees, treating compounds as unordered maps keyed by tag name and lists
+/// positionally. Scalars and typed arrays are reported as a single change when they differ.
+pub fn diff<'a>(old: &'a NBT, new: &'a NBT) -> Vec<Difference<'a>> {
+    let mut out = Vec::new();
+    payload(String::new(), &old.payload, &new.payload, &mut out);
+    out
+}
+
+impl std::fmt::Display for Difference<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.change {
+            Change::Added(now)        => write!(f, "{}", format!("+ {}: {}", self.path, now).green()),
+            Change::Removed(was)      => write!(f, "{}", format!("- {}: {}", self.path, was).red()),
+            Change::Changed(was, now) => write!(f, "{}", format!("~ {}: {} -> {}", self.path, was, now).yellow()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Change, Difference};
+
+    /// Each difference as `+`, `-` or `~` followed by its path.
+    fn paths(differences: &[Difference]) -> Vec<String> {
+        differences.iter().map(|difference| {
+            let sign = match difference.change {
+                Change::Added(_)      => '+',
+                Change::Removed(_)    => '-',
+                Change::Changed(_, _) => '~',
+            };
+            format!("{}{}", sign, difference.path)
+        }).collect()
+    }
+
+    #[test]
+    fn key_order_does_not_matter() {
+        let old = crate::nbt!{ "a": 1, "b": "two", "c": { "d": 3L, "e": 4b } };
+        let new = crate::nbt!{ "c": { "e": 4b, "d": 3L }, "b": "two", "a": 1 };
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn lists_compare_by_position() {
+        let old = crate::nbt!{ "list": [1, 2, 3] };
+        let grown = crate::nbt!{ "list": [1, 5, 3, 4] };
+        assert!(paths(&diff(&old, &grown)) == ["~list[1]", "+list[3]"]);
+        let shrunk = crate::nbt!{ "list": [1] };
+        assert!(paths(&diff(&old, &shrunk)) == ["-list[1]", "-list[2]"]);
+        /* an element moved to the front changes every position after it */
+        let shifted = crate::nbt!{ "list": [0, 1, 2, 3] };
+        assert!(paths(&diff(&old, &shifted)) == ["~list[0]", "~list[1]", "~list[2]", "+list[3]"]);
+    }
+
+    #[test]
+    fn type_changes_are_one_change() {
+        let old = crate::nbt!{ "value": 1, "list": [1, 2] };
+        let new = crate::nbt!{ "value": 1L, "list": ["1", "2"] };
+        let differences = diff(&old, &new);
+        assert!(paths(&differences) == ["~value", "~list"]);
+        assert!(matches!(differences[0].change, Change::Changed(crate::nbt::Payload::Int(1), crate::nbt::Payload::Long(1))));
+    }
+
+    #[test]
+    fn nested_changes_have_full_paths() {
+        let old = crate::nbt!{
+            "Level": { "Sections": [{ "Y": 1b, "Light": 1b }], "Status": "full" },
+            "gone": 1,
+        };
+        let new = crate::nbt!{
+            "Level": { "Sections": [{ "Y": 1b, "Light": 2b, "new": 1 }], "Status": "full" },
+            "here too": 2,
+        };
+        assert!(paths(&diff(&old, &new)) == ["~Level.Sections[0].Light", "+Level.Sections[0].new", "-gone", "+\"here too\""]);
+    }
+}
@@ -3,9 +3,20 @@
 //
 
 pub mod query;
+pub mod snbt;
+pub mod path;
+pub mod diff;
+pub mod access;
+pub mod build;
+pub mod visit;
+pub mod borrowed;
+/* nothing in the binary calls into serde yet, it's there for code built on these types */
+#[cfg(feature = "serde")]
+#[allow(dead_code)]
+pub mod serde;
 
 use std::io::Cursor;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
 const MAX_ELEMENTS:   usize = 2_147_483_647;
 const MAX_NEST_DEPTH: usize = 512;
@@ -17,32 +28,38 @@ pub type TAGLong = i64;
 pub type TAGFloat = f32;
 pub type TAGDouble = f64;
 
+#[derive(Clone, PartialEq)]
 pub struct TAGByteArray {
     pub body: Vec<TAGByte>,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct TAGString {
     pub str: Vec<TAGByte>,
 }
 
+#[derive(Clone, PartialEq)]
 pub struct TAGList {
     id:         TAGByte,
     pub tags:   Vec<Payload>,
 }
 
+#[derive(Clone, PartialEq)]
 pub struct TAGCompound {
     pub tags: Vec<NBT>,
 }
 
+#[derive(Clone, PartialEq)]
 pub struct TAGIArray {
     pub ints: Vec<TAGInt>,
 }
 
+#[derive(Clone, PartialEq)]
 pub struct TAGLArray {
     pub longs: Vec<TAGLong>,
 }
 
+#[derive(Clone, PartialEq)]
 pub enum Payload {
     End,
     Byte(TAGByte),
@@ -59,6 +76,7 @@ pub enum Payload {
     LArray(TAGLArray),
 }
 
+#[derive(Clone, PartialEq)]
 pub struct NBT {
     pub name:    TAGString,
     pub payload: Payload,
@@ -75,6 +93,13 @@ pub enum Error {
     TAGString(String),
     TAGShort(String),
     TAGByte,
+    StringLength(usize),
+}
+
+/// Encodes trees back into the binary format `Parser` reads.
+#[derive(Default)]
+pub struct Writer {
+    bytes: Vec<u8>,
 }
 
 pub struct Parser {
@@ -299,6 +324,129 @@ impl Parser {
     }
 }
 
+/// Lowercase tag type names as used by the game's NBT tooling, e.g `long_array`.
+pub fn type_name(id: TAGByte) -> &'static str {
+    match id {
+        0  => "end",
+        1  => "byte",
+        2  => "short",
+        3  => "int",
+        4  => "long",
+        5  => "float",
+        6  => "double",
+        7  => "byte_array",
+        8  => "string",
+        9  => "list",
+        10 => "compound",
+        11 => "int_array",
+        12 => "long_array",
+        _  => "unknown",
+    }
+}
+
+impl Payload {
+    pub fn id(&self) -> TAGByte {
+        match self {
+            Payload::End         => 0,
+            Payload::Byte(_)     => 1,
+            Payload::Short(_)    => 2,
+            Payload::Int(_)      => 3,
+            Payload::Long(_)     => 4,
+            Payload::Float(_)    => 5,
+            Payload::Double(_)   => 6,
+            Payload::BArray(_)   => 7,
+            Payload::String(_)   => 8,
+            Payload::List(_)     => 9,
+            Payload::Compound(_) => 10,
+            Payload::IArray(_)   => 11,
+            Payload::LArray(_)   => 12,
+        }
+    }
+}
+
+impl Writer {
+    fn string(&mut self, str: &TAGString) -> Result<(), Error> {
+        let Ok(length) = u16::try_from(str.str.len()) else {
+            return Err(Error::StringLength(str.str.len()));
+        };
+        self.bytes.write_u16::<BigEndian>(length).unwrap();
+        self.bytes.extend_from_slice(&str.str);
+        Ok(())
+    }
+
+    fn length(&mut self, length: usize) {
+        self.bytes.write_i32::<BigEndian>(length as i32).unwrap();
+    }
+
+    fn payload(&mut self, payload: &Payload) -> Result<(), Error> {
+        match payload {
+            Payload::End       => (),
+            Payload::Byte(b)   => self.bytes.push(*b),
+            Payload::Short(s)  => self.bytes.write_i16::<BigEndian>(*s).unwrap(),
+            Payload::Int(i)    => self.bytes.write_i32::<BigEndian>(*i).unwrap(),
+            Payload::Long(l)   => self.bytes.write_i64::<BigEndian>(*l).unwrap(),
+            Payload::Float(f)  => self.bytes.write_f32::<BigEndian>(*f).unwrap(),
+            Payload::Double(d) => self.bytes.write_f64::<BigEndian>(*d).unwrap(),
+            Payload::BArray(array) => {
+                self.length(array.body.len());
+                self.bytes.extend_from_slice(&array.body);
+            },
+            Payload::String(str) => self.string(str)?,
+            Payload::List(list) => {
+                self.bytes.push(list.id);
+                self.length(list.tags.len());
+                for tag in list.tags.iter() {
+                    self.payload(tag)?;
+                }
+            },
+            Payload::Compound(compound) => {
+                /* trees built by hand don't always carry the closing End tag, so it is always written here */
+                for tag in compound.tags.iter().filter(|tag| tag.payload != Payload::End) {
+                    self.tag(tag)?;
+                }
+                self.bytes.push(0);
+            },
+            Payload::IArray(array) => {
+                self.length(array.ints.len());
+                for int in array.ints.iter() {
+                    self.bytes.write_i32::<BigEndian>(*int).unwrap();
+                }
+            },
+            Payload::LArray(array) => {
+                self.length(array.longs.len());
+                for long in array.longs.iter() {
+                    self.bytes.write_i64::<BigEndian>(*long).unwrap();
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn tag(&mut self, nbt: &NBT) -> Result<(), Error> {
+        self.bytes.push(nbt.payload.id());
+        if nbt.payload != Payload::End {
+            self.string(&nbt.name)?;
+            self.payload(&nbt.payload)?;
+        }
+        Ok(())
+    }
+}
+
+impl Writer {
+    /// Appends `root`, failing on names and strings too long for their 16 bit length field.
+    pub fn write(&mut self, root: &NBT) -> Result<(), Error> {
+        self.tag(root)
+    }
+
+    pub fn bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn new() -> Self {
+        Self { bytes: Vec::new() }
+    }
+}
+
 impl Default for NBT {
     fn default() -> Self {
         Self {
@@ -397,6 +545,37 @@ impl std::fmt::Display for Error {
             Error::TAGString(msg) => write!(f, "{}", msg),
             Error::TAGShort(msg) => write!(f, "{}", msg),
             Error::TAGByte => write!(f, "{}", "Unable to read a NBT byte value (this should never happen...)."),
+            Error::StringLength(length) => write!(f, "String of {} bytes exceeds the maximum length of {}.", length, u16::MAX),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Parser, Writer, NBT, Payload, TAGString};
+
+    fn named(name: &str, value: &str) -> NBT {
+        crate::nbt::build::Builder::new().tag(name, value).root("")
+    }
+
+    #[test]
+    fn writer_round_trips_through_parser() {
+        let root = crate::nbt!{ "a": 1b, "b": [L; 2l], "c": [{ "d": "e" }], "f": 1.5d };
+        let mut writer = Writer::new();
+        assert!(writer.write(&root).is_ok());
+        let mut read = NBT::default();
+        assert!(Parser::new(writer.bytes()).parse(&mut read).is_ok());
+        assert!(read == root);
+    }
+
+    #[test]
+    fn strings_over_the_length_field_are_refused() {
+        let longest = "x".repeat(u16::MAX as usize);
+        assert!(Writer::new().write(&named("s", &longest)).is_ok());
+        let too_long = "x".repeat(u16::MAX as usize + 1);
+        assert!(matches!(Writer::new().write(&named("s", &too_long)), Err(Error::StringLength(65536))));
+        assert!(matches!(Writer::new().write(&named(&too_long, "s")), Err(Error::StringLength(65536))));
+        let root = NBT{ name: TAGString::from(""), payload: Payload::String(TAGString::from(too_long)) };
+        assert!(Writer::new().write(&root).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use std::borrow::Cow;
+
+use crate::nbt::{snbt, Payload};
+use crate::nbt::borrowed::PayloadRef;
+
+pub enum Error {
+    Empty,
+    Syntax(usize, String),
+    Filter(snbt::Error),
+}
+
+enum Node {
+    Root(Payload),
+    Named(Vec<u8>),
+    NamedFilter(Vec<u8>, Payload),
+    AllElements,
+    Index(i32),
+    ElementFilter(Payload),
+}
+
+/// A compiled `/data` style path, e.g. `Inventory[{id:"minecraft:diamond"}].Count`.
+pub struct Path {
+    source: String,
+    nodes:  Vec<Node>,
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn syntax(&self, msg: &str) -> Error {
+        Error::Syntax(self.pos, String::from(msg))
+    }
+
+    fn filter(&mut self) -> Result<Payload, Error> {
+        let mut parser = snbt::Parser::at(self.src, self.pos);
+        let compound = match parser.compound() {
+            Ok(compound) => compound,
+            Err(e)       => return Err(Error::Filter(e)),
+        };
+        self.pos = parser.position();
+        Ok(Payload::Compound(compound))
+    }
+
+    fn name(&mut self) -> Result<Vec<u8>, Error> {
+        if let Some(b'"') | Some(b'\'') = self.peek() {
+            let mut parser = snbt::Parser::at(self.src, self.pos);
+            let name = match parser.string() {
+                Ok(name) => name,
+                Err(e)   => return Err(Error::Filter(e)),
+            };
+            self.pos = parser.position();
+            return Ok(name);
+        }
+        let start = self.pos;
+        while let Some(byte) = self.peek() {
+            if byte.is_ascii_whitespace() || b".[]{}\"'".contains(&byte) {
+                break;
+            }
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.syntax("expected a tag name"));
+        }
+        Ok(self.src.as_bytes()[start..self.pos].to_vec())
+    }
+
+    fn named(&mut self) -> Result<Node, Error> {
+        let name = self.name()?;
+        if self.peek() == Some(b'{') {
+            return Ok(Node::NamedFilter(name, self.filter()?));
+        }
+        Ok(Node::Named(name))
+    }
+
+    fn element(&mut self) -> Result<Node, Error> {
+        self.pos += 1;
+        let node = match self.peek() {
+            Some(b']') => Node::AllElements,
+            Some(b'{') => Node::ElementFilter(self.filter()?),
+            _ => {
+                let start = self.pos;
+                while let Some(byte) = self.peek() {
+                    if byte == b']' {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                match self.src[start..self.pos].trim().parse() {
+                    Ok(index) => Node::Index(index),
+                    Err(_)    => return Err(Error::Syntax(start, String::from("expected a list index"))),
+                }
+            },
+        };
+        if self.peek() != Some(b']') {
+            return Err(self.syntax("expected ']'"));
+        }
+        self.pos += 1;
+        Ok(node)
+    }
+}
+
+impl Path {
+    pub fn parse(src: &str) -> Result<Self, Error> {
+        let src = src.trim();
+        if src.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        let mut lexer = Lexer{ src, pos: 0 };
+        let mut nodes = Vec::new();
+        match lexer.peek() {
+            Some(b'{') => nodes.push(Node::Root(lexer.filter()?)),
+            _          => nodes.push(lexer.named()?),
+        }
+        while let Some(byte) = lexer.peek() {
+            match byte {
+                b'.' => {
+                    lexer.pos += 1;
+                    nodes.push(lexer.named()?);
+                },
+                b'[' => nodes.push(lexer.element()?),
+                _    => return Err(lexer.syntax("expected '.' or '['")),
+            }
+        }
+
+        Ok(Self{ source: String::from(src), nodes })
+    }
+
+    /// Every value the path selects underneath `root`. Elements of typed arrays are not stored as
+    /// payloads so those come back owned.
+    pub fn evaluate<'a>(&self, root: &'a Payload) -> Vec<Cow<'a, Payload>> {
+        let mut current = vec![Cow::Borrowed(root)];
+        for node in self.nodes.iter() {
+            let mut next = Vec::new();
+            for value in current {
+                if let Cow::Borrowed(payload) = value {
+                    step(node, payload, &mut next);
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// `evaluate` over a borrowed tree, so only the tags the path walks through get decoded.
+    pub fn evaluate_ref<'a>(&self, root: PayloadRef<'a>) -> Vec<PayloadRef<'a>> {
+        let mut current = vec![root];
+        for node in self.nodes.iter() {
+            let mut next = Vec::new();
+            for payload in current {
+                step_ref(node, payload, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+/// `index` into `len` elements, counting from the back when negative.
+fn position(index: i32, len: usize) -> Option<usize> {
+    let i = if index < 0 { len as i64 + index as i64 } else { index as i64 };
+    if i < 0 || i >= len as i64 {
+        return None;
+    }
+    Some(i as usize)
+}
+
+fn step<'a>(node: &Node, payload: &'a Payload, out: &mut Vec<Cow<'a, Payload>>) {
+    match node {
+        Node::Root(filter) => {
+            if matches(filter, payload) {
+                out.push(Cow::Borrowed(payload));
+            }
+        },
+        Node::Named(name) | Node::NamedFilter(name, _) => {
+            let Payload::Compound(compound) = payload else {
+                return;
+            };
+            for tag in compound.iter().filter(|tag| tag.name.str == *name) {
+                if let Node::NamedFilter(_, filter) = node {
+                    if !matches(filter, &tag.payload) {
+                        continue;
+                    }
+                }
+                out.push(Cow::Borrowed(&tag.payload));
+            }
+        },
+        Node::AllElements => match payload {
+            Payload::List(list)    => out.extend(list.tags.iter().map(Cow::Borrowed)),
+            Payload::BArray(array) => out.extend(array.body.iter().map(|b| Cow::Owned(Payload::Byte(*b)))),
+            Payload::IArray(array) => out.extend(array.ints.iter().map(|i| Cow::Owned(Payload::Int(*i)))),
+            Payload::LArray(array) => out.extend(array.longs.iter().map(|l| Cow::Owned(Payload::Long(*l)))),
+            _ => (),
+        },
+        Node::Index(index) => {
+            let len = match payload {
+                Payload::List(list)    => list.tags.len(),
+                Payload::BArray(array) => array.body.len(),
+                Payload::IArray(array) => array.ints.len(),
+                Payload::LArray(array) => array.longs.len(),
+                _ => return,
+            };
+            let Some(i) = position(*index, len) else {
+                return;
+            };
+            match payload {
+                Payload::List(list)    => out.push(Cow::Borrowed(&list.tags[i])),
+                Payload::BArray(array) => out.push(Cow::Owned(Payload::Byte(array.body[i]))),
+                Payload::IArray(array) => out.push(Cow::Owned(Payload::Int(array.ints[i]))),
+                Payload::LArray(array) => out.push(Cow::Owned(Payload::Long(array.longs[i]))),
+                _ => (),
+            }
+        },
+        Node::ElementFilter(filter) => {
+            if let Payload::List(list) = payload {
+                out.extend(list.tags.iter().filter(|tag| matches(filter, tag)).map(Cow::Borrowed));
+            }
+        },
+    }
+}
+
+fn step_ref<'a>(node: &Node, payload: PayloadRef<'a>, out: &mut Vec<PayloadRef<'a>>) {
+    match node {
+        Node::Root(filter) => {
+            if matches_ref(filter, payload) {
+                out.push(payload);
+            }
+        },
+        Node::Named(name) | Node::NamedFilter(name, _) => {
+            let PayloadRef::Compound(compound) = payload else {
+                return;
+            };
+            for tag in compound.iter().filter(|tag| tag.name == name.as_slice()) {
+                if let Node::NamedFilter(_, filter) = node {
+                    if !matches_ref(filter, tag.payload) {
+                        continue;
+                    }
+                }
+                out.push(tag.payload);
+            }
+        },
+        Node::AllElements => match payload {
+            PayloadRef::List(list)    => out.extend(list.iter()),
+            PayloadRef::BArray(body)  => out.extend(body.iter().map(|b| PayloadRef::Byte(*b))),
+            PayloadRef::IArray(array) => out.extend(array.iter().map(PayloadRef::Int)),
+            PayloadRef::LArray(array) => out.extend(array.iter().map(PayloadRef::Long)),
+            _ => (),
+        },
+        Node::Index(index) => {
+            let len = match payload {
+                PayloadRef::List(list)    => list.len(),
+                PayloadRef::BArray(body)  => body.len(),
+                PayloadRef::IArray(array) => array.len(),
+                PayloadRef::LArray(array) => array.len(),
+                _ => return,
+            };
+            let Some(i) = position(*index, len) else {
+                return;
+            };
+            match payload {
+                PayloadRef::List(list)    => out.extend(list.iter().nth(i)),
+                PayloadRef::BArray(body)  => out.push(PayloadRef::Byte(body[i])),
+                PayloadRef::IArray(array) => out.extend(array.get(i).map(PayloadRef::Int)),
+                PayloadRef::LArray(array) => out.extend(array.get(i).map(PayloadRef::Long)),
+                _ => (),
+            }
+        },
+        Node::ElementFilter(filter) => {
+            if let PayloadRef::List(list) = payload {
+                out.extend(list.iter().filter(|tag| matches_ref(filter, *tag)));
+            }
+        },
+    }
+}
+
+/// Partial comparison the game uses for path filters: every tag in a filter compound has to be
+/// present in the target and every element of a filter list has to match some target element.
+pub fn matches(filter: &Payload, target: &Payload) -> bool {
+    match (filter, target) {
+        (Payload::Compound(filter), Payload::Compound(target)) => {
+            filter.iter()
+                .all(|want| target.tags.iter().any(|have| have.name == want.name && matches(&want.payload, &have.payload)))
+        },
+        (Payload::List(filter), Payload::List(target)) => {
+            if filter.tags.is_empty() {
+                return target.tags.is_empty();
+            }
+            filter.tags.iter().all(|want| target.tags.iter().any(|have| matches(want, have)))
+        },
+        _ => filter == target,
+    }
+}
+
+/// `matches` against a borrowed target, which is only copied out for the scalar comparisons.
+fn matches_ref(filter: &Payload, target: PayloadRef) -> bool {
+    match (filter, target) {
+        (Payload::Compound(filter), PayloadRef::Compound(target)) => {
+            filter.iter()
+                .all(|want| target.iter().any(|have| have.name == want.name.str.as_slice() && matches_ref(&want.payload, have.payload)))
+        },
+        (Payload::List(filter), PayloadRef::List(target)) => {
+            if filter.tags.is_empty() {
+                return target.is_empty();
+            }
+            filter.tags.iter().all(|want| target.iter().any(|have| matches_ref(want, have)))
+        },
+        _ => filter.id() == target.id() && *filter == target.to_payload(),
+    }
+}
+
+/// Extends a rendered path with a compound key, quoting names the path syntax can't spell bare.
+pub(crate) fn child(parent: &str, name: &[u8]) -> String {
+    let name = String::from_utf8_lossy(name);
+    let segment = if name.is_empty() || name.contains(|c: char| c.is_whitespace() || ".[]{}\"'".contains(c)) {
+        snbt::quote(&name)
+    } else {
+        name.into_owned()
+    };
+    if parent.is_empty() {
+        return segment;
+    }
+    format!("{}.{}", parent, segment)
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Empty => write!(f, "empty path"),
+            Error::Syntax(pos, msg) => write!(f, "{} at {}", msg, pos),
+            Error::Filter(e) => write!(f, "invalid filter: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{child, Path};
+    use crate::nbt::{snbt, Payload, Writer, NBT, TAGString};
+    use crate::nbt::borrowed::NBTRef;
+
+    /// Selects with both the owned and the borrowed evaluation, which have to agree.
+    fn select(path: &str, src: &str) -> Vec<Payload> {
+        let root = match snbt::Parser::at(src, 0).value() {
+            Ok(root) => root,
+            Err(e) => panic!("'{}' didn't parse: {}", src, e),
+        };
+        let path = match Path::parse(path) {
+            Ok(path) => path,
+            Err(e) => panic!("'{}' didn't compile: {}", path, e),
+        };
+        let owned: Vec<Payload> = path.evaluate(&root).into_iter().map(|found| found.into_owned()).collect();
+
+        let mut writer = Writer::new();
+        assert!(writer.write(&NBT{ name: TAGString::from(""), payload: root }).is_ok());
+        let bytes = writer.bytes();
+        let Ok(borrowed) = NBTRef::parse(&bytes) else {
+            panic!("'{}' didn't read back", src);
+        };
+        let borrowed: Vec<Payload> = path.evaluate_ref(borrowed.payload).into_iter().map(|found| found.to_payload()).collect();
+        assert!(owned == borrowed, "{} selects differently from a borrowed tree", path);
+        owned
+    }
+
+    const PLAYER: &str = r#"{Inventory: [{id: "minecraft:diamond", Count: 3b}, {id: "minecraft:stone", Count: 64b}], Pos: [1.0d, 2.0d, 3.0d], UUID: [I; 1, 2, 3, 4], "odd name": {a: 1}}"#;
+
+    #[test]
+    fn names_and_indices() {
+        assert!(select("Pos[0]", PLAYER) == vec![Payload::Double(1.0)]);
+        assert!(select("Pos[-1]", PLAYER) == vec![Payload::Double(3.0)]);
+        assert!(select("Pos[3]", PLAYER).is_empty());
+        assert!(select("UUID[2]", PLAYER) == vec![Payload::Int(3)]);
+        assert!(select("UUID[]", PLAYER).len() == 4);
+        assert!(select("\"odd name\".a", PLAYER) == vec![Payload::Int(1)]);
+        assert!(select("Missing.a", PLAYER).is_empty());
+    }
+
+    #[test]
+    fn filters() {
+        assert!(select("Inventory[{id:\"minecraft:diamond\"}].Count", PLAYER) == vec![Payload::Byte(3)]);
+        assert!(select("Inventory[].Count", PLAYER) == vec![Payload::Byte(3), Payload::Byte(64)]);
+        assert!(select("{Pos:[2.0d]}.UUID[0]", PLAYER) == vec![Payload::Int(1)]);
+        assert!(select("{Pos:[5.0d]}.UUID[0]", PLAYER).is_empty());
+        assert!(select("\"odd name\"{a:1}.a", PLAYER).len() == 1);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(Path::parse("").is_err());
+        assert!(Path::parse("Pos[x]").is_err());
+        assert!(Path::parse("Pos[0").is_err());
+        assert!(Path::parse("a..b").is_err());
+    }
+
+    #[test]
+    fn rendered_paths_parse_back() {
+        let rendered = child(&child("", b"odd \"name\""), "名前".as_bytes());
+        assert!(rendered == "\"odd \\\"name\\\"\".名前");
+        assert!(select(&rendered, r#"{"odd \"name\"": {"名前": 7}}"#) == vec![Payload::Int(7)]);
+    }
+}
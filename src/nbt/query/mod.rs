@@ -0,0 +1,124 @@
+//
+// Created by Justin Tunheim on 7/22/24
+//
+
+pub mod predicate;
+
+use crate::nbt::{ self, path, visit, NBT, Payload, TAGByte };
+use visit::{Flow, Visitor};
+use predicate::Predicate;
+
+/// Where a named tag sits, found without materializing the tree around it.
+pub(crate) struct Named {
+    pub path: String,
+    pub id:   TAGByte,
+}
+
+enum Frame {
+    Compound(String),
+    List(String, usize),
+}
+
+/// Tracks the path of every nested value so matches can be reported the way `find_many_by_predicate` does.
+struct NameSearch<'a> {
+    name:    &'a [u8],
+    stack:   Vec<Frame>,
+    pending: Option<String>,
+    found:   Vec<Named>,
+}
+
+impl NameSearch<'_> {
+    /// Path of the compound or list being opened: the tag just named, or the next element of a list.
+    fn open(&mut self) -> String {
+        if let Some(path) = self.pending.take() {
+            return path;
+        }
+        match self.stack.last_mut() {
+            Some(Frame::List(path, next)) => {
+                *next += 1;
+                format!("{}[{}]", path, *next - 1)
+            },
+            _ => String::new(),
+        }
+    }
+}
+
+impl Visitor for NameSearch<'_> {
+    fn visit_tag(&mut self, name: &[u8], id: TAGByte) -> Flow {
+        let path = match self.stack.last() {
+            Some(Frame::Compound(parent)) => path::child(parent, name),
+            _ => String::new(),
+        };
+        if name == self.name {
+            self.found.push(Named{ path: path.clone(), id });
+        }
+        /* only compounds and lists open a frame that consumes the pending path */
+        if id == 9 || id == 10 {
+            self.pending = Some(path);
+        }
+        Flow::Continue
+    }
+
+    fn visit_compound_start(&mut self) -> Flow {
+        let path = self.open();
+        self.stack.push(Frame::Compound(path));
+        Flow::Continue
+    }
+
+    fn visit_compound_end(&mut self) {
+        self.stack.pop();
+    }
+
+    fn visit_list_start(&mut self, id: TAGByte, _length: usize) -> Flow {
+        let path = self.open();
+        /* only compounds hold named tags, so lists of anything else are stepped over by their length */
+        if id != 9 && id != 10 {
+            return Flow::Skip;
+        }
+        self.stack.push(Frame::List(path, 0));
+        Flow::Continue
+    }
+
+    fn visit_list_end(&mut self) {
+        self.stack.pop();
+    }
+}
+
+/// Finds every tag called `name` by streaming the uncompressed NBT in `bytes`, never building a tree.
+pub(crate) fn find_many_by_name(name: &[u8], bytes: &[u8]) -> Result<Vec<Named>, nbt::Error> {
+    let mut search = NameSearch{ name, stack: Vec::new(), pending: None, found: Vec::new() };
+    visit::walk(bytes, &mut search)?;
+    Ok(search.found)
+}
+
+/// A tag selected by a search, along with the NBT path leading to it from the root.
+pub(crate) struct Found<'a> {
+    pub path:    String,
+    pub payload: &'a Payload,
+}
+
+fn visit<'a>(predicate: &Predicate, name: &[u8], path: String, payload: &'a Payload, found: &mut Vec<Found<'a>>) {
+    if predicate.test(name, payload) {
+        found.push(Found{ path: path.clone(), payload });
+    }
+    match payload {
+        Payload::Compound(compound) => {
+            for tag in compound.iter() {
+                visit(predicate, &tag.name.str, path::child(&path, &tag.name.str), &tag.payload, found);
+            }
+        },
+        Payload::List(list) => {
+            for (i, element) in list.tags.iter().enumerate() {
+                visit(predicate, &[], format!("{}[{}]", path, i), element, found);
+            }
+        },
+        _ => (),
+    }
+}
+
+/// Walks the whole tree once, testing every tag and list element against the compiled predicate.
+pub(crate) fn find_many_by_predicate<'a>(predicate: &Predicate, root: &'a NBT) -> Vec<Found<'a>> {
+    let mut found = Vec::new();
+    visit(predicate, &root.name.str, String::new(), &root.payload, &mut found);
+    found
+}
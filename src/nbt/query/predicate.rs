@@ -0,0 +1,447 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use regex::Regex;
+
+use crate::nbt::{self, path::Path, snbt, Payload};
+
+pub enum Error {
+    EndOfInput,
+    Unexpected(String),
+    Operator(String),
+    Value(String),
+    Path(String, nbt::path::Error),
+    Regex(regex::Error),
+}
+
+#[derive(PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Op(Op),
+    Word(String),
+    Quoted(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Match,
+    NoMatch,
+}
+
+enum Field {
+    Name,
+    Type,
+    Path(Path),
+}
+
+enum Operand {
+    Number(f64),
+    Text(String),
+    Type(nbt::TAGByte),
+    Regex(Regex),
+}
+
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, Operand),
+}
+
+/// A compiled search filter such as `id =~ minecraft:.*_shulker_box and Count ge 2`.
+///
+/// Every tag visited during a search is tested on its own: `name` and `type` refer to the tag
+/// itself and anything else is an NBT path into its payload, holding when any selected value
+/// satisfies the comparison. Operators have word forms (`eq ne gt ge lt le matches`) so the
+/// expression can be typed straight into a shell.
+pub struct Predicate {
+    source: String,
+    expr:   Expr,
+}
+
+fn is_op(c: char) -> bool {
+    matches!(c, '=' | '!' | '<' | '>' | '~')
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = src.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        match c {
+            '(' => { chars.next(); tokens.push(Token::Open) },
+            ')' => { chars.next(); tokens.push(Token::Close) },
+            '"' | '\'' => {
+                chars.next();
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => word.extend(chars.next()),
+                        Some(q) if q == c => break,
+                        Some(ch) => word.push(ch),
+                        None => return Err(Error::EndOfInput),
+                    }
+                }
+                tokens.push(Token::Quoted(word));
+            },
+            c if is_op(c) => {
+                let mut op = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !is_op(ch) {
+                        break;
+                    }
+                    op.push(ch);
+                    chars.next();
+                }
+                tokens.push(match op.as_str() {
+                    "!"         => Token::Not,
+                    "==" | "="  => Token::Op(Op::Eq),
+                    "!="        => Token::Op(Op::Ne),
+                    ">"         => Token::Op(Op::Gt),
+                    ">="        => Token::Op(Op::Ge),
+                    "<"         => Token::Op(Op::Lt),
+                    "<="        => Token::Op(Op::Le),
+                    "=~" | "~"  => Token::Op(Op::Match),
+                    "!~"        => Token::Op(Op::NoMatch),
+                    _           => return Err(Error::Operator(op)),
+                });
+            },
+            _ => {
+                let mut word = String::new();
+                let mut depth = 0;
+                let mut quote = None;
+                while let Some(&ch) = chars.peek() {
+                    match quote {
+                        Some(q) if ch == q => quote = None,
+                        Some(_) => (),
+                        None => match ch {
+                            '"' | '\'' => quote = Some(ch),
+                            '[' | '{' => depth += 1,
+                            ']' | '}' => depth -= 1,
+                            _ if depth == 0 && (ch.is_whitespace() || ch == '(' || ch == ')' || is_op(ch)) => break,
+                            _ => (),
+                        },
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(match word.to_lowercase().as_str() {
+                    "and" | "&&" => Token::And,
+                    "or" | "||"  => Token::Or,
+                    "not"        => Token::Not,
+                    "eq" | "is"  => Token::Op(Op::Eq),
+                    "ne"         => Token::Op(Op::Ne),
+                    "gt"         => Token::Op(Op::Gt),
+                    "ge"         => Token::Op(Op::Ge),
+                    "lt"         => Token::Op(Op::Lt),
+                    "le"         => Token::Op(Op::Le),
+                    "matches"    => Token::Op(Op::Match),
+                    _            => Token::Word(word),
+                });
+            },
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Compiler {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+}
+
+impl Compiler {
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        match self.peeked.take() {
+            Some(token) => Some(token),
+            None        => self.tokens.next(),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.not()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            lhs = Expr::And(Box::new(lhs), Box::new(self.not()?));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr, Error> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, Error> {
+        match self.next() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(_) => Err(Error::Unexpected(String::from("expected ')'"))),
+                    None    => Err(Error::EndOfInput),
+                }
+            },
+            Some(token @ (Token::Word(_) | Token::Quoted(_))) => {
+                /* a quoted field is always a tag name, which lets tags called `name` or `type` be reached */
+                let (field, source) = match token {
+                    Token::Quoted(name) => (path(&snbt::quote(&name))?, name),
+                    Token::Word(word)   => (field(&word)?, word),
+                    _ => unreachable!(),
+                };
+                let Some(Token::Op(op)) = self.next() else {
+                    return Err(Error::Unexpected(format!("expected an operator after '{}'", source)));
+                };
+                match self.next() {
+                    Some(Token::Word(value))   => compare(field, op, value, false),
+                    Some(Token::Quoted(value)) => compare(field, op, value, true),
+                    _ => Err(Error::Unexpected(format!("expected a value after '{}'", source))),
+                }
+            },
+            Some(_) => Err(Error::Unexpected(String::from("expected a comparison"))),
+            None    => Err(Error::EndOfInput),
+        }
+    }
+}
+
+fn path(field: &str) -> Result<Field, Error> {
+    match Path::parse(field) {
+        Ok(path) => Ok(Field::Path(path)),
+        Err(e)   => Err(Error::Path(String::from(field), e)),
+    }
+}
+
+fn field(word: &str) -> Result<Field, Error> {
+    match word {
+        "name" => Ok(Field::Name),
+        "type" => Ok(Field::Type),
+        _      => path(word),
+    }
+}
+
+/// A `quoted` value is always text, so `id == "10"` compares against the string rather than a number.
+fn compare(field: Field, op: Op, value: String, quoted: bool) -> Result<Expr, Error> {
+    let operand = match (&field, op) {
+        (_, Op::Match | Op::NoMatch) => match Regex::new(&format!("^(?:{})$", value)) {
+            Ok(regex) => Operand::Regex(regex),
+            Err(e)    => return Err(Error::Regex(e)),
+        },
+        (Field::Type, Op::Eq | Op::Ne) => {
+            match (0..=12).find(|id| nbt::type_name(*id) == value) {
+                Some(id) => Operand::Type(id),
+                None     => return Err(Error::Value(value)),
+            }
+        },
+        (Field::Type, _) => return Err(Error::Unexpected(String::from("types can only be compared with == or !="))),
+        (_, Op::Eq | Op::Ne) if quoted => Operand::Text(value),
+        (_, Op::Eq | Op::Ne) => match number(&value) {
+            Some(number) => Operand::Number(number),
+            None         => Operand::Text(value),
+        },
+        (_, _) if quoted => return Err(Error::Value(value)),
+        (_, _) => match number(&value) {
+            Some(number) => Operand::Number(number),
+            None         => return Err(Error::Value(value)),
+        },
+    };
+
+    Ok(Expr::Compare(field, op, operand))
+}
+
+/// Accepts the command style suffixes (`64b`, `3.5f`) so values can be pasted from SNBT.
+fn number(value: &str) -> Option<f64> {
+    let trimmed = value.trim_end_matches(['b', 'B', 's', 'S', 'l', 'L', 'f', 'F', 'd', 'D']);
+    trimmed.parse().ok()
+}
+
+fn numeric(payload: &Payload) -> Option<f64> {
+    match payload {
+        Payload::Byte(b)   => Some(*b as i8 as f64),
+        Payload::Short(s)  => Some(*s as f64),
+        Payload::Int(i)    => Some(*i as f64),
+        Payload::Long(l)   => Some(*l as f64),
+        Payload::Float(f)  => Some(*f as f64),
+        Payload::Double(d) => Some(*d),
+        _ => None,
+    }
+}
+
+fn text(payload: &Payload) -> Option<String> {
+    match payload {
+        Payload::String(str) => Some(String::from_utf8_lossy(&str.str).into_owned()),
+        _ => numeric(payload).map(|n| n.to_string()),
+    }
+}
+
+fn test(op: Op, operand: &Operand, value: &Payload) -> bool {
+    match operand {
+        Operand::Regex(regex) => {
+            let Some(text) = text(value) else { return false };
+            regex.is_match(&text) == (op == Op::Match)
+        },
+        Operand::Type(id) => (value.id() == *id) == (op == Op::Eq),
+        Operand::Text(want) => {
+            let Some(text) = text(value) else { return false };
+            (text == *want) == (op == Op::Eq)
+        },
+        Operand::Number(want) => {
+            let Some(have) = numeric(value) else { return false };
+            match op {
+                Op::Eq => have == *want,
+                Op::Ne => have != *want,
+                Op::Gt => have >  *want,
+                Op::Ge => have >= *want,
+                Op::Lt => have <  *want,
+                Op::Le => have <= *want,
+                Op::Match | Op::NoMatch => false,
+            }
+        },
+    }
+}
+
+impl Expr {
+    fn eval(&self, name: &[u8], payload: &Payload) -> bool {
+        match self {
+            Expr::And(lhs, rhs) => lhs.eval(name, payload) && rhs.eval(name, payload),
+            Expr::Or(lhs, rhs)  => lhs.eval(name, payload) || rhs.eval(name, payload),
+            Expr::Not(expr)     => !expr.eval(name, payload),
+            Expr::Compare(Field::Name, op, operand) => {
+                test(*op, operand, &Payload::String(nbt::TAGString{str: name.to_vec()}))
+            },
+            Expr::Compare(Field::Type, op, operand @ Operand::Type(_)) => test(*op, operand, payload),
+            Expr::Compare(Field::Type, op, operand) => {
+                let name = nbt::type_name(payload.id()).as_bytes().to_vec();
+                test(*op, operand, &Payload::String(nbt::TAGString{str: name}))
+            },
+            Expr::Compare(Field::Path(path), op, operand) => {
+                path.evaluate(payload).iter().any(|value| test(*op, operand, value))
+            },
+        }
+    }
+}
+
+impl Predicate {
+    pub fn parse(src: &str) -> Result<Self, Error> {
+        let mut compiler = Compiler{ tokens: tokenize(src)?.into_iter(), peeked: None };
+        let expr = compiler.or()?;
+        if compiler.next().is_some() {
+            return Err(Error::Unexpected(String::from("trailing input after expression")));
+        }
+        Ok(Self{ source: String::from(src), expr })
+    }
+
+    pub fn test(&self, name: &[u8], payload: &Payload) -> bool {
+        self.expr.eval(name, payload)
+    }
+}
+
+impl std::fmt::Display for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::EndOfInput => write!(f, "unexpected end of expression"),
+            Error::Unexpected(msg) => write!(f, "{}", msg),
+            Error::Operator(op) => write!(f, "unknown operator '{}'", op),
+            Error::Value(value) => write!(f, "'{}' is not a valid value for this comparison", value),
+            Error::Path(field, e) => write!(f, "invalid field '{}': {}", field, e),
+            Error::Regex(e) => write!(f, "invalid regular expression: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Predicate;
+    use crate::nbt::Payload;
+    use crate::nbt::build::Builder;
+
+    fn holds(src: &str, payload: &Payload) -> bool {
+        match Predicate::parse(src) {
+            Ok(predicate) => predicate.test(b"", payload),
+            Err(e) => panic!("'{}' didn't compile: {}", src, e),
+        }
+    }
+
+    fn item() -> Payload {
+        Payload::Compound(Builder::new()
+            .tag("Count", 3i8)
+            .tag("id", "minecraft:red_shulker_box")
+            .tag("Slot", "10")
+            .tag("Damage", 10)
+            .tag("名前 \"x\"", "named")
+            .build())
+    }
+
+    #[test]
+    fn ranges() {
+        assert!(holds("Count ge 2 and Count lt 5", &item()));
+        assert!(holds("Count > 2.5 && Count <= 3b", &item()));
+        assert!(!holds("Count gt 3 or Count lt 3", &item()));
+        assert!(holds("not (Count == 4)", &item()));
+    }
+
+    #[test]
+    fn regex_and_type() {
+        assert!(holds("id matches minecraft:.*_shulker_box", &item()));
+        assert!(holds("id !~ 'minecraft:stone'", &item()));
+        assert!(holds("type eq compound", &item()));
+        assert!(!holds("type eq int", &item()));
+        assert!(Predicate::parse("type gt int").is_err());
+    }
+
+    #[test]
+    fn quoted_values_are_text() {
+        assert!(holds("Slot == \"10\"", &item()));
+        assert!(holds("Slot eq '10'", &item()));
+        /* an unquoted 10 is a number, which a string tag never equals */
+        assert!(!holds("Slot == 10", &item()));
+        assert!(holds("Damage == 10", &item()));
+        assert!(Predicate::parse("Count gt \"2\"").is_err());
+    }
+
+    #[test]
+    fn quoted_fields() {
+        assert!(holds("'名前 \"x\"' == named", &item()));
+        assert!(holds("\"名前 \\\"x\\\"\" == named", &item()));
+        assert!(!holds("'名前' == named", &item()));
+    }
+}
@@ -0,0 +1,221 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use ::serde::de::{self, Visitor, IntoDeserializer};
+use ::serde::forward_to_deserialize_any;
+
+use crate::nbt::{NBT, Payload};
+use super::Error;
+
+/// Elements of typed arrays aren't stored as payloads, so they're handed out by value.
+#[derive(Clone, Copy)]
+enum Value<'de> {
+    Tag(&'de Payload),
+    Byte(u8),
+    Int(i32),
+    Long(i64),
+}
+
+/// Deserializes any `Deserialize` type from a borrowed `Payload` tree.
+pub struct Deserializer<'de> {
+    value: Value<'de>,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(payload: &'de Payload) -> Self {
+        Self{ value: Value::Tag(payload) }
+    }
+}
+
+struct Elements<'de> {
+    values: Box<dyn Iterator<Item = Value<'de>> + 'de>,
+    length: usize,
+}
+
+struct Tags<'de> {
+    tags:  std::slice::Iter<'de, NBT>,
+    value: Option<&'de Payload>,
+}
+
+struct Enum<'de> {
+    variant: &'de NBT,
+}
+
+fn elements<'de>(payload: &'de Payload) -> Option<Elements<'de>> {
+    let (values, length): (Box<dyn Iterator<Item = Value<'de>>>, usize) = match payload {
+        Payload::List(list)    => (Box::new(list.tags.iter().map(Value::Tag)), list.tags.len()),
+        Payload::BArray(array) => (Box::new(array.body.iter().map(|b| Value::Byte(*b))), array.body.len()),
+        Payload::IArray(array) => (Box::new(array.ints.iter().map(|i| Value::Int(*i))), array.ints.len()),
+        Payload::LArray(array) => (Box::new(array.longs.iter().map(|l| Value::Long(*l))), array.longs.len()),
+        _ => return None,
+    };
+    Some(Elements{ values, length })
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let payload = match self.value {
+            Value::Tag(payload) => payload,
+            Value::Byte(b) => return visitor.visit_i8(b as i8),
+            Value::Int(i)  => return visitor.visit_i32(i),
+            Value::Long(l) => return visitor.visit_i64(l),
+        };
+        match payload {
+            Payload::End       => visitor.visit_unit(),
+            Payload::Byte(b)   => visitor.visit_i8(*b as i8),
+            Payload::Short(s)  => visitor.visit_i16(*s),
+            Payload::Int(i)    => visitor.visit_i32(*i),
+            Payload::Long(l)   => visitor.visit_i64(*l),
+            Payload::Float(f)  => visitor.visit_f32(*f),
+            Payload::Double(d) => visitor.visit_f64(*d),
+            Payload::String(str) => match std::str::from_utf8(&str.str) {
+                Ok(str) => visitor.visit_borrowed_str(str),
+                Err(_)  => visitor.visit_borrowed_bytes(&str.str),
+            },
+            Payload::Compound(compound) => visitor.visit_map(Tags{ tags: compound.tags.iter(), value: None }),
+            Payload::List(_) | Payload::BArray(_) | Payload::IArray(_) | Payload::LArray(_) => {
+                visitor.visit_seq(elements(payload).unwrap())
+            },
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Tag(Payload::Byte(b)) => visitor.visit_bool(*b != 0),
+            Value::Byte(b) => visitor.visit_bool(b != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    /* bytes are stored unsigned, so unsigned targets get them without a sign round trip */
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Tag(Payload::Byte(b)) => visitor.visit_u8(*b),
+            Value::Byte(b) => visitor.visit_u8(b),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Tag(Payload::End) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Tag(Payload::BArray(array)) => visitor.visit_borrowed_bytes(&array.body),
+            Value::Tag(Payload::String(str)) => visitor.visit_borrowed_bytes(&str.str),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Tag(Payload::String(str)) => match std::str::from_utf8(&str.str) {
+                Ok(variant) => visitor.visit_enum(variant.into_deserializer()),
+                Err(_)      => Err(de::Error::custom("enum variant name is not valid UTF-8")),
+            },
+            Value::Tag(Payload::Compound(compound)) => {
+                let mut tags = compound.iter();
+                match (tags.next(), tags.next()) {
+                    (Some(variant), None) => visitor.visit_enum(Enum{ variant }),
+                    _ => Err(de::Error::custom("enum compounds must hold exactly one tag")),
+                }
+            },
+            _ => Err(de::Error::custom("expected an enum as a string or single tag compound")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Elements<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        match self.values.next() {
+            Some(value) => seed.deserialize(Deserializer{ value }).map(Some),
+            None        => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.length)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Tags<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        for tag in self.tags.by_ref() {
+            if tag.payload == Payload::End {
+                continue;
+            }
+            self.value = Some(&tag.payload);
+            let key = match std::str::from_utf8(&tag.name.str) {
+                Ok(key) => key,
+                Err(_)  => return Err(de::Error::custom("compound key is not valid UTF-8")),
+            };
+            return seed.deserialize(de::value::BorrowedStrDeserializer::new(key)).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some(payload) => seed.deserialize(Deserializer::new(payload)),
+            None          => Err(de::Error::custom("compound value requested before its key")),
+        }
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for Enum<'de> {
+    type Error = Error;
+    type Variant = Deserializer<'de>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Deserializer<'de>), Error> {
+        let name = match std::str::from_utf8(&self.variant.name.str) {
+            Ok(name) => name,
+            Err(_)   => return Err(de::Error::custom("enum variant name is not valid UTF-8")),
+        };
+        let variant = seed.deserialize(de::value::BorrowedStrDeserializer::new(name))?;
+        Ok((variant, Deserializer::new(&self.variant.payload)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
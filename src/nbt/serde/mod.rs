@@ -0,0 +1,205 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+//! Serde support for NBT, behind the `serde` feature.
+//!
+//! Rust types pick the tag type: `bool`, `i8` and `u8` become bytes, `i16` shorts, `i32` ints,
+//! `i64` longs, sequences lists and structs or maps compounds. `None` fields are left out of the
+//! compound. Typed arrays are opted into per field with `#[serde(with = "nbt::serde::long_array")]`
+//! and friends; deserializing accepts either arrays or lists for a `Vec`.
+
+pub mod ser;
+pub mod de;
+
+use ::serde::{Serialize, Deserialize};
+
+use crate::nbt::{self, NBT, Payload, TAGString};
+
+/* newtype names the serializer recognizes to emit typed arrays instead of lists */
+pub(crate) const BYTE_ARRAY: &str = "__rave_nbt_byte_array";
+pub(crate) const INT_ARRAY:  &str = "__rave_nbt_int_array";
+pub(crate) const LONG_ARRAY: &str = "__rave_nbt_long_array";
+
+pub enum Error {
+    Message(String),
+    Parse(nbt::Error),
+    Encode(nbt::Error),
+    MixedList(&'static str, &'static str),
+    KeyType,
+    Unsupported(&'static str),
+}
+
+pub fn to_payload<T: Serialize + ?Sized>(value: &T) -> Result<Payload, Error> {
+    value.serialize(ser::Serializer)
+}
+
+/// Serializes `value` as the root compound of a tree named `name`.
+pub fn to_nbt<T: Serialize + ?Sized>(value: &T, name: &str) -> Result<NBT, Error> {
+    Ok(NBT{ name: TAGString::from(name), payload: to_payload(value)? })
+}
+
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T, name: &str) -> Result<Vec<u8>, Error> {
+    let mut writer = nbt::Writer::new();
+    writer.write(&to_nbt(value, name)?).map_err(Error::Encode)?;
+    Ok(writer.bytes())
+}
+
+pub fn from_payload<'de, T: Deserialize<'de>>(payload: &'de Payload) -> Result<T, Error> {
+    T::deserialize(de::Deserializer::new(payload))
+}
+
+pub fn from_nbt<'de, T: Deserialize<'de>>(root: &'de NBT) -> Result<T, Error> {
+    from_payload(&root.payload)
+}
+
+/// Parses uncompressed NBT with `nbt::Parser` and deserializes the root compound into `T`.
+pub fn from_bytes<T: for<'de> Deserialize<'de>>(bytes: Vec<u8>) -> Result<T, Error> {
+    let mut root = NBT::default();
+    if let Err(e) = nbt::Parser::new(bytes).parse(&mut root) {
+        return Err(Error::Parse(e));
+    }
+    from_nbt(&root)
+}
+
+macro_rules! array {
+    ($module:ident, $marker:ident, $element:ty) => {
+        pub mod $module {
+            use ::serde::{Deserialize, Serializer, Deserializer};
+
+            pub fn serialize<T: AsRef<[$element]>, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_newtype_struct(super::$marker, value.as_ref())
+            }
+
+            pub fn deserialize<'de, T: From<Vec<$element>>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+                Vec::<$element>::deserialize(deserializer).map(T::from)
+            }
+        }
+    };
+}
+
+array!(byte_array, BYTE_ARRAY, i8);
+array!(int_array, INT_ARRAY, i32);
+array!(long_array, LONG_ARRAY, i64);
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl std::fmt::Debug for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Message(msg) => write!(f, "{}", msg),
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::Encode(e) => write!(f, "{}", e),
+            Error::MixedList(first, other) => write!(f, "list elements must share a type, found {} after {}", other, first),
+            Error::KeyType => write!(f, "compound keys must be strings"),
+            Error::Unsupported(what) => write!(f, "{} can't be represented in NBT", what),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::serde::{Serialize, Deserialize};
+
+    use crate::nbt::{Payload, TAGString};
+    use super::Error;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Mode {
+        Survival,
+        Spectating(i32),
+        Flying{ speed: f32 },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Player {
+        name:      String,
+        health:    f32,
+        level:     Option<i32>,
+        spawn:     Option<i64>,
+        on_ground: bool,
+        #[serde(with = "super::long_array")]
+        heightmap: Vec<i64>,
+        inventory: Vec<i16>,
+        mode:      Mode,
+        previous:  Mode,
+    }
+
+    fn player() -> Player {
+        Player{
+            name: String::from("Steve"),
+            health: 20.0,
+            level: Some(30),
+            spawn: None,
+            on_ground: true,
+            heightmap: vec![1, -2, i64::MAX],
+            inventory: vec![3, 4],
+            mode: Mode::Flying{ speed: 0.5 },
+            previous: Mode::Survival,
+        }
+    }
+
+    #[test]
+    fn structs_round_trip() {
+        let root = super::to_nbt(&player(), "Player").unwrap();
+        let Payload::Compound(compound) = &root.payload else { panic!("expected a compound") };
+        assert!(compound.iter().all(|tag| tag.name.to_string() != "spawn"));
+        assert_eq!(super::from_nbt::<Player>(&root).unwrap(), player());
+    }
+
+    #[test]
+    fn long_arrays_serialize_as_arrays() {
+        let Payload::Compound(compound) = super::to_payload(&player()).unwrap() else { panic!("expected a compound") };
+        let field = |name: &str| compound.iter().find(|tag| tag.name.to_string() == name).map(|tag| tag.payload.id());
+        assert_eq!(field("heightmap"), Some(12));
+        assert_eq!(field("inventory"), Some(9));
+        assert_eq!(field("on_ground"), Some(1));
+    }
+
+    #[test]
+    fn enums_round_trip() {
+        for mode in [Mode::Survival, Mode::Spectating(-1), Mode::Flying{ speed: 2.0 }] {
+            let payload = super::to_payload(&mode).unwrap();
+            assert_eq!(super::from_payload::<Mode>(&payload).unwrap(), mode);
+        }
+        assert!(super::to_payload(&Mode::Survival).unwrap() == Payload::String(TAGString::from("Survival")));
+    }
+
+    #[test]
+    fn from_bytes_reads_written_trees() {
+        let bytes = super::to_bytes(&player(), "Player").unwrap();
+        assert_eq!(super::from_bytes::<Player>(bytes.clone()).unwrap(), player());
+        assert!(matches!(super::from_bytes::<Player>(vec![99, 0, 0]), Err(Error::Parse(_))));
+    }
+
+    #[test]
+    fn out_of_range_unsigned_values_are_rejected() {
+        assert!(super::to_payload(&(i32::MAX as u32)).unwrap() == Payload::Int(i32::MAX));
+        assert!(matches!(super::to_payload(&u32::MAX), Err(Error::Unsupported(_))));
+        assert!(matches!(super::to_payload(&u64::MAX), Err(Error::Unsupported(_))));
+    }
+
+    #[test]
+    fn lists_must_share_a_type() {
+        assert!(matches!(super::to_payload(&vec![1, 2]).map(|list| list.id()), Ok(9)));
+        assert!(matches!(super::to_payload(&(1i8, 2i32)), Err(Error::MixedList(..))));
+    }
+}
@@ -0,0 +1,419 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use ::serde::ser::{self, Serialize};
+
+use crate::nbt::{
+    self, NBT, Payload, TAGByte, TAGString, TAGList, TAGCompound,
+    TAGByteArray, TAGIArray, TAGLArray,
+};
+use super::{Error, BYTE_ARRAY, INT_ARRAY, LONG_ARRAY};
+
+/* `None` has no NBT form; compounds catch this and leave the field out */
+const NONE: &str = "a bare None";
+
+/// Serializes any `Serialize` value into a `Payload` tree.
+pub struct Serializer;
+
+pub struct List {
+    tags: Vec<Payload>,
+}
+
+pub struct Compound {
+    tags: Vec<NBT>,
+    key:  Option<TAGString>,
+}
+
+pub struct Variant<T> {
+    name:  &'static str,
+    inner: T,
+}
+
+fn list(tags: Vec<Payload>) -> Result<Payload, Error> {
+    let id = tags.first().map(Payload::id).unwrap_or(0);
+    if let Some(other) = tags.iter().find(|tag| tag.id() != id) {
+        return Err(Error::MixedList(nbt::type_name(id), nbt::type_name(other.id())));
+    }
+    Ok(Payload::List(TAGList{id, tags}))
+}
+
+fn array(marker: &'static str, tags: Vec<Payload>) -> Result<Payload, Error> {
+    let mismatch = |tag: &Payload| Error::MixedList(marker, nbt::type_name(tag.id()));
+    match marker {
+        BYTE_ARRAY => {
+            let mut body = Vec::new();
+            for tag in tags {
+                let Payload::Byte(byte) = tag else { return Err(mismatch(&tag)) };
+                body.push(byte);
+            }
+            Ok(Payload::BArray(TAGByteArray{body}))
+        },
+        INT_ARRAY => {
+            let mut ints = Vec::new();
+            for tag in tags {
+                let Payload::Int(int) = tag else { return Err(mismatch(&tag)) };
+                ints.push(int);
+            }
+            Ok(Payload::IArray(TAGIArray{ints}))
+        },
+        _ => {
+            let mut longs = Vec::new();
+            for tag in tags {
+                let Payload::Long(long) = tag else { return Err(mismatch(&tag)) };
+                longs.push(long);
+            }
+            Ok(Payload::LArray(TAGLArray{longs}))
+        },
+    }
+}
+
+fn compound(tags: Vec<NBT>) -> Payload {
+    let mut tags = tags;
+    tags.push(NBT::default());
+    Payload::Compound(TAGCompound{tags})
+}
+
+fn wrap(name: &'static str, payload: Payload) -> Payload {
+    compound(vec![NBT{ name: TAGString::from(name), payload }])
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Payload;
+    type Error = Error;
+    type SerializeSeq = List;
+    type SerializeTuple = List;
+    type SerializeTupleStruct = List;
+    type SerializeTupleVariant = Variant<List>;
+    type SerializeMap = Compound;
+    type SerializeStruct = Compound;
+    type SerializeStructVariant = Variant<Compound>;
+
+    fn serialize_bool(self, v: bool) -> Result<Payload, Error> {
+        Ok(Payload::Byte(v as TAGByte))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Payload, Error> {
+        Ok(Payload::Byte(v as TAGByte))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Payload, Error> {
+        Ok(Payload::Short(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Payload, Error> {
+        Ok(Payload::Int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Payload, Error> {
+        Ok(Payload::Long(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Payload, Error> {
+        Ok(Payload::Byte(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Payload, Error> {
+        Ok(Payload::Short(v as i16))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Payload, Error> {
+        i32::try_from(v).map(Payload::Int).map_err(|_| Error::Unsupported("a u32 above i32::MAX"))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Payload, Error> {
+        i64::try_from(v).map(Payload::Long).map_err(|_| Error::Unsupported("a u64 above i64::MAX"))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Payload, Error> {
+        Ok(Payload::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Payload, Error> {
+        Ok(Payload::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Payload, Error> {
+        Ok(Payload::String(TAGString::from(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Payload, Error> {
+        Ok(Payload::String(TAGString::from(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Payload, Error> {
+        Ok(Payload::BArray(TAGByteArray{body: v.to_vec()}))
+    }
+
+    fn serialize_none(self) -> Result<Payload, Error> {
+        Err(Error::Unsupported(NONE))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Payload, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Payload, Error> {
+        Ok(compound(Vec::new()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Payload, Error> {
+        Ok(compound(Vec::new()))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Payload, Error> {
+        Ok(Payload::String(TAGString::from(variant)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<Payload, Error> {
+        match name {
+            BYTE_ARRAY | INT_ARRAY | LONG_ARRAY => {
+                let Payload::List(list) = value.serialize(self)? else {
+                    return Err(Error::Unsupported("a typed array that isn't a sequence"));
+                };
+                array(name, list.tags)
+            },
+            _ => value.serialize(self),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<Payload, Error> {
+        Ok(wrap(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<List, Error> {
+        Ok(List{ tags: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<List, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<List, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<Variant<List>, Error> {
+        Ok(Variant{ name: variant, inner: self.serialize_seq(Some(len))? })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Compound, Error> {
+        Ok(Compound{ tags: Vec::with_capacity(len.unwrap_or(0) + 1), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<Variant<Compound>, Error> {
+        Ok(Variant{ name: variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+impl List {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.tags.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Payload, Error> {
+        list(self.tags)
+    }
+}
+
+impl ser::SerializeSeq for List {
+    type Ok = Payload;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Payload, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for List {
+    type Ok = Payload;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Payload, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for List {
+    type Ok = Payload;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Payload, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Variant<List> {
+    type Ok = Payload;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> Result<Payload, Error> {
+        Ok(wrap(self.name, self.inner.finish()?))
+    }
+}
+
+/// Compound keys have to serialize to strings, anything else is rejected.
+struct Key;
+
+impl ser::Serializer for Key {
+    type Ok = TAGString;
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<TAGString, Error>;
+    type SerializeTuple = ser::Impossible<TAGString, Error>;
+    type SerializeTupleStruct = ser::Impossible<TAGString, Error>;
+    type SerializeTupleVariant = ser::Impossible<TAGString, Error>;
+    type SerializeMap = ser::Impossible<TAGString, Error>;
+    type SerializeStruct = ser::Impossible<TAGString, Error>;
+    type SerializeStructVariant = ser::Impossible<TAGString, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<TAGString, Error> {
+        Ok(TAGString::from(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<TAGString, Error> {
+        Ok(TAGString::from(v.to_string()))
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<TAGString, Error> {
+        Ok(TAGString::from(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<TAGString, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_i8(self, _v: i8) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_i16(self, _v: i16) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_i32(self, _v: i32) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_i64(self, _v: i64) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_u8(self, _v: u8) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_u16(self, _v: u16) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_u32(self, _v: u32) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_u64(self, _v: u64) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_f32(self, _v: f32) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_f64(self, _v: f64) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_none(self) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_unit(self) -> Result<TAGString, Error> { Err(Error::KeyType) }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<TAGString, Error> { Err(Error::KeyType) }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<TAGString, Error> {
+        Err(Error::KeyType)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<TAGString, Error> {
+        Err(Error::KeyType)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> { Err(Error::KeyType) }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> { Err(Error::KeyType) }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::KeyType)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::KeyType)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> { Err(Error::KeyType) }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Error> {
+        Err(Error::KeyType)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::KeyType)
+    }
+}
+
+/// `None` values are dropped from compounds rather than failing the whole structure.
+struct Field;
+
+impl Field {
+    fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Option<Payload>, Error> {
+        match value.serialize(Serializer) {
+            Ok(payload) => Ok(Some(payload)),
+            Err(Error::Unsupported(NONE)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Compound {
+    fn field<T: Serialize + ?Sized>(&mut self, name: TAGString, value: &T) -> Result<(), Error> {
+        if let Some(payload) = Field::serialize(value)? {
+            self.tags.push(NBT{ name, payload });
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Compound {
+    type Ok = Payload;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(Key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let Some(name) = self.key.take() else {
+            return Err(Error::Message(String::from("map value serialized before its key")));
+        };
+        self.field(name, value)
+    }
+
+    fn end(self) -> Result<Payload, Error> {
+        Ok(compound(self.tags))
+    }
+}
+
+impl ser::SerializeStruct for Compound {
+    type Ok = Payload;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.field(TAGString::from(key), value)
+    }
+
+    fn end(self) -> Result<Payload, Error> {
+        Ok(compound(self.tags))
+    }
+}
+
+impl ser::SerializeStructVariant for Variant<Compound> {
+    type Ok = Payload;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.inner.field(TAGString::from(key), value)
+    }
+
+    fn end(self) -> Result<Payload, Error> {
+        Ok(wrap(self.name, compound(self.inner.tags)))
+    }
+}
@@ -0,0 +1,322 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::nbt::{
+    NBT, Payload, TAGByte, TAGString, TAGList, TAGCompound,
+    TAGByteArray, TAGIArray, TAGLArray,
+};
+
+pub enum Error {
+    EndOfInput,
+    Unexpected(usize, char),
+    Number(String),
+    MixedList(usize),
+    MixedArray(usize),
+}
+
+/// Stringified NBT reader, i.e. the `{Count:1b,id:"minecraft:stone"}` notation used by commands.
+pub struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Result<u8, Error> {
+        let Some(byte) = self.peek() else {
+            return Err(Error::EndOfInput);
+        };
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if !byte.is_ascii_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, want: u8) -> Result<(), Error> {
+        self.whitespace();
+        let pos = self.pos;
+        match self.bump()? {
+            byte if byte == want => Ok(()),
+            byte => Err(Error::Unexpected(pos, byte as char)),
+        }
+    }
+
+    fn unexpected(&self) -> Error {
+        match self.peek() {
+            Some(byte) => Error::Unexpected(self.pos, byte as char),
+            None       => Error::EndOfInput,
+        }
+    }
+
+    fn quoted(&mut self) -> Result<Vec<u8>, Error> {
+        let quote = self.bump()?;
+        let mut str = Vec::new();
+        loop {
+            match self.bump()? {
+                b'\\' => str.push(self.bump()?),
+                byte if byte == quote => return Ok(str),
+                byte => str.push(byte),
+            }
+        }
+    }
+
+    fn unquoted(&mut self) -> Vec<u8> {
+        let start = self.pos;
+        while let Some(byte) = self.peek() {
+            if !is_unquoted(byte) {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.src[start..self.pos].to_vec()
+    }
+
+    /// A compound key or string value, quoted or bare.
+    pub fn string(&mut self) -> Result<Vec<u8>, Error> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'"') | Some(b'\'') => self.quoted(),
+            Some(byte) if is_unquoted(byte) => Ok(self.unquoted()),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    pub fn compound(&mut self) -> Result<TAGCompound, Error> {
+        self.expect(b'{')?;
+        let mut compound = TAGCompound{tags: Vec::new()};
+        self.whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            compound.tags.push(NBT::default());
+            return Ok(compound);
+        }
+        loop {
+            let name = self.string()?;
+            self.expect(b':')?;
+            let payload = self.value()?;
+            compound.tags.push(NBT{name: TAGString{str: name}, payload});
+            self.whitespace();
+            match self.bump()? {
+                b',' => continue,
+                b'}' => break,
+                byte => return Err(Error::Unexpected(self.pos-1, byte as char)),
+            }
+        }
+        compound.tags.push(NBT::default());
+        Ok(compound)
+    }
+
+    fn list(&mut self) -> Result<Payload, Error> {
+        let start = self.pos;
+        self.expect(b'[')?;
+        self.whitespace();
+
+        let array = match (self.src.get(self.pos), self.src.get(self.pos+1)) {
+            (Some(kind @ (b'B' | b'I' | b'L')), Some(b';')) => Some(*kind),
+            _ => None,
+        };
+        if array.is_some() {
+            self.pos += 2;
+        }
+
+        let mut tags = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+        } else {
+            loop {
+                tags.push(self.value()?);
+                self.whitespace();
+                match self.bump()? {
+                    b',' => continue,
+                    b']' => break,
+                    byte => return Err(Error::Unexpected(self.pos-1, byte as char)),
+                }
+            }
+        }
+
+        match array {
+            Some(b'B') => {
+                let mut body = Vec::new();
+                for tag in tags {
+                    let Payload::Byte(byte) = tag else { return Err(Error::MixedArray(start)) };
+                    body.push(byte);
+                }
+                Ok(Payload::BArray(TAGByteArray{body}))
+            },
+            Some(b'I') => {
+                let mut ints = Vec::new();
+                for tag in tags {
+                    let Payload::Int(int) = tag else { return Err(Error::MixedArray(start)) };
+                    ints.push(int);
+                }
+                Ok(Payload::IArray(TAGIArray{ints}))
+            },
+            Some(_) => {
+                let mut longs = Vec::new();
+                for tag in tags {
+                    let Payload::Long(long) = tag else { return Err(Error::MixedArray(start)) };
+                    longs.push(long);
+                }
+                Ok(Payload::LArray(TAGLArray{longs}))
+            },
+            None => {
+                let id = tags.first().map(Payload::id).unwrap_or(0);
+                if tags.iter().any(|tag| tag.id() != id) {
+                    return Err(Error::MixedList(start));
+                }
+                Ok(Payload::List(TAGList{id, tags}))
+            },
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Payload, Error> {
+        let start = self.pos;
+        if let Some(b'"') | Some(b'\'') = self.peek() {
+            return Ok(Payload::String(TAGString{str: self.quoted()?}));
+        }
+        let word = self.unquoted();
+        if word.is_empty() {
+            return Err(self.unexpected());
+        }
+        match number(&word) {
+            Some(payload) => Ok(payload),
+            None => match word.as_slice() {
+                b"true"  => Ok(Payload::Byte(1)),
+                b"false" => Ok(Payload::Byte(0)),
+                _ if word[0].is_ascii_digit() || word[0] == b'-' && word.len() > 1 && word[1].is_ascii_digit() => {
+                    Err(Error::Number(String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()))
+                },
+                _ => Ok(Payload::String(TAGString{str: word})),
+            },
+        }
+    }
+
+    pub fn value(&mut self) -> Result<Payload, Error> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'{') => Ok(Payload::Compound(self.compound()?)),
+            Some(b'[') => self.list(),
+            Some(_)    => self.scalar(),
+            None       => Err(Error::EndOfInput),
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn at(src: &'a str, pos: usize) -> Self {
+        Self { src: src.as_bytes(), pos }
+    }
+}
+
+/// `str` as a double quoted SNBT string, with quotes and backslashes escaped the way `Parser` reads them.
+pub fn quote(str: &str) -> String {
+    let mut quoted = String::with_capacity(str.len() + 2);
+    quoted.push('"');
+    for c in str.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_unquoted(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b'+')
+}
+
+/// Numbers follow the command suffixes: `b`, `s`, `l`, `f` and `d`. A bare integer is an int and a
+/// bare decimal is a double.
+fn number(word: &[u8]) -> Option<Payload> {
+    let text = std::str::from_utf8(word).ok()?;
+    let (body, suffix) = match text.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => (&text[..i], Some(c.to_ascii_lowercase())),
+        _ => (text, None),
+    };
+    if body.is_empty() || !body.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E')) {
+        return None;
+    }
+    match suffix {
+        Some('b') => body.parse::<i8>().ok().map(|b| Payload::Byte(b as TAGByte)),
+        Some('s') => body.parse().ok().map(Payload::Short),
+        Some('l') => body.parse().ok().map(Payload::Long),
+        Some('f') => body.parse().ok().map(Payload::Float),
+        Some('d') => body.parse().ok().map(Payload::Double),
+        Some(_)   => None,
+        None if body.contains(['.', 'e', 'E']) => body.parse().ok().map(Payload::Double),
+        None      => body.parse().ok().map(Payload::Int),
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::EndOfInput => write!(f, "unexpected end of input"),
+            Error::Unexpected(pos, c) => write!(f, "unexpected '{}' at {}", c, pos),
+            Error::Number(num) => write!(f, "'{}' is not a valid number", num),
+            Error::MixedList(pos) => write!(f, "list at {} mixes element types", pos),
+            Error::MixedArray(pos) => write!(f, "typed array at {} contains a value of the wrong type", pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quote, Parser};
+    use crate::nbt::{Payload, TAGIArray, TAGString};
+    use crate::nbt::access::Lookup;
+
+    fn parse(src: &str) -> Payload {
+        match Parser::at(src, 0).value() {
+            Ok(payload) => payload,
+            Err(e) => panic!("'{}' didn't parse: {}", src, e),
+        }
+    }
+
+    #[test]
+    fn types() {
+        let Payload::Compound(compound) = parse(r#"{Count: 1b, Damage: 3s, id: "minecraft:stone", Time: 5l, Speed: 0.5f, Pos: [1.0, -2d], Ints: [I; 1, -2], ok: true}"#) else {
+            panic!("not a compound");
+        };
+        assert!(compound.get("Count") == Some(&Payload::Byte(1)));
+        assert!(compound.get("Damage") == Some(&Payload::Short(3)));
+        assert!(compound.get("id") == Some(&Payload::from("minecraft:stone")));
+        assert!(compound.get("Time") == Some(&Payload::Long(5)));
+        assert!(compound.get("Speed") == Some(&Payload::Float(0.5)));
+        assert!(compound.get_list("Pos").is_ok_and(|pos| pos.tags == vec![Payload::Double(1.0), Payload::Double(-2.0)]));
+        assert!(compound.get("Ints") == Some(&Payload::IArray(TAGIArray{ ints: vec![1, -2] })));
+        assert!(compound.get("ok") == Some(&Payload::Byte(1)));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Parser::at("[1, 2b]", 0).value().is_err());
+        assert!(Parser::at("[I; 1, 2b]", 0).value().is_err());
+        assert!(Parser::at("{a: 12x}", 0).value().is_err());
+        assert!(Parser::at("{a: 1", 0).value().is_err());
+        assert!(Parser::at("\"open", 0).value().is_err());
+    }
+
+    #[test]
+    fn quote_round_trip() {
+        for str in ["plain", "with space", "\"quoted\"", "back\\slash", "名前", "\u{1f600}", ""] {
+            let quoted = quote(str);
+            assert!(parse(&quoted) == Payload::String(TAGString::from(str)), "{} came back different", quoted);
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::nbt::{
+    Error, TAGByte, TAGShort, TAGInt, TAGLong, TAGFloat, TAGDouble, MAX_NEST_DEPTH,
+};
+
+/// What the reader does after a visitor has seen the start of a tag.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Flow {
+    Continue,
+    /// Skips the tag's payload by its length, without visiting anything inside it.
+    Skip,
+    /// Ends the walk early; `walk` still returns `Ok`.
+    Stop,
+}
+
+/// Callbacks for a streaming walk over binary NBT. Nothing is allocated: strings and arrays are
+/// handed out as views into the input. Every callback defaults to doing nothing, so a visitor only
+/// implements what it cares about.
+#[allow(unused_variables)]
+pub trait Visitor {
+    /// A named tag inside a compound (or the root), before its payload.
+    fn visit_tag(&mut self, name: &[u8], id: TAGByte) -> Flow { Flow::Continue }
+    fn visit_compound_start(&mut self) -> Flow { Flow::Continue }
+    fn visit_compound_end(&mut self) {}
+    fn visit_list_start(&mut self, id: TAGByte, length: usize) -> Flow { Flow::Continue }
+    fn visit_list_end(&mut self) {}
+    fn visit_byte(&mut self, value: TAGByte) {}
+    fn visit_short(&mut self, value: TAGShort) {}
+    fn visit_int(&mut self, value: TAGInt) {}
+    fn visit_long(&mut self, value: TAGLong) {}
+    fn visit_float(&mut self, value: TAGFloat) {}
+    fn visit_double(&mut self, value: TAGDouble) {}
+    fn visit_string(&mut self, value: &[u8]) {}
+    fn visit_byte_array(&mut self, value: &[TAGByte]) {}
+    fn visit_int_array(&mut self, value: Ints<'_>) {}
+    fn visit_long_array(&mut self, value: Longs<'_>) {}
+}
+
+/// The elements of an int array, decoded as they are iterated.
+pub struct Ints<'a> {
+    pub(super) bytes: std::slice::ChunksExact<'a, u8>,
+}
+
+/// The elements of a long array, decoded as they are iterated.
+pub struct Longs<'a> {
+    pub(super) bytes: std::slice::ChunksExact<'a, u8>,
+}
+
+/// A cursor over borrowed NBT bytes, shared with the lazy `NBTRef` view.
+pub(super) struct Reader<'a> {
+    pub(super) bytes: &'a [u8],
+    pub(super) pos:   usize,
+    nest:  usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0, nest: 0 }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let Some(bytes) = self.bytes.get(self.pos..self.pos+length) else {
+            return Err(Error::EndOfBytes);
+        };
+        self.pos += length;
+        Ok(bytes)
+    }
+
+    pub(super) fn byte(&mut self) -> Result<TAGByte, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(super) fn short(&mut self) -> Result<TAGShort, Error> {
+        Ok(TAGShort::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(super) fn int(&mut self) -> Result<TAGInt, Error> {
+        Ok(TAGInt::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(super) fn long(&mut self) -> Result<TAGLong, Error> {
+        Ok(TAGLong::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(super) fn string(&mut self) -> Result<&'a [u8], Error> {
+        let length = self.short()? as u16 as usize;
+        self.take(length)
+    }
+
+    pub(super) fn length(&mut self) -> Result<usize, Error> {
+        match self.int()? {
+            length if length < 0 => Err(Error::NegativeLength(length)),
+            length => Ok(length as usize),
+        }
+    }
+
+    /// An array body of `length` elements `width` bytes wide.
+    pub(super) fn array(&mut self, width: usize) -> Result<&'a [u8], Error> {
+        let length = self.length()?;
+        match length.checked_mul(width) {
+            Some(size) => self.take(size),
+            None       => Err(Error::EndOfBytes),
+        }
+    }
+
+    fn nest(&mut self, from: &str) -> Result<(), Error> {
+        self.nest += 1;
+        if self.nest == MAX_NEST_DEPTH {
+            return Err(Error::NestingDepth(String::from(from)));
+        }
+        Ok(())
+    }
+
+    /// Steps over a payload of type `id` using only the length fields.
+    pub(super) fn skip(&mut self, id: TAGByte) -> Result<(), Error> {
+        match id {
+            1  => { self.take(1)?; },
+            2  => { self.take(2)?; },
+            3 | 5 => { self.take(4)?; },
+            4 | 6 => { self.take(8)?; },
+            7  => { self.array(1)?; },
+            8  => { self.string()?; },
+            9  => {
+                let id = self.byte()?;
+                let length = self.length()?;
+                self.nest("List")?;
+                match fixed_width(id) {
+                    Some(width) => match length.checked_mul(width) {
+                        Some(size) => { self.take(size)?; },
+                        None       => return Err(Error::EndOfBytes),
+                    },
+                    None => for _ in 0..length {
+                        self.skip(id)?;
+                    },
+                }
+                self.nest -= 1;
+            },
+            10 => {
+                self.nest("Compound")?;
+                loop {
+                    let id = self.byte()?;
+                    if id == 0 {
+                        break;
+                    }
+                    self.string()?;
+                    self.skip(id)?;
+                }
+                self.nest -= 1;
+            },
+            11 => { self.array(4)?; },
+            12 => { self.array(8)?; },
+            _  => return Err(Error::InvalidByteSequence(self.pos as u64, id)),
+        }
+        Ok(())
+    }
+
+    /// Visits a payload of type `id`, returning `Flow::Stop` once the visitor asked to end the walk.
+    fn payload<V: Visitor>(&mut self, id: TAGByte, visitor: &mut V) -> Result<Flow, Error> {
+        match id {
+            1  => visitor.visit_byte(self.byte()?),
+            2  => visitor.visit_short(self.short()?),
+            3  => visitor.visit_int(self.int()?),
+            4  => visitor.visit_long(self.long()?),
+            5  => visitor.visit_float(TAGFloat::from_bits(self.int()? as u32)),
+            6  => visitor.visit_double(TAGDouble::from_bits(self.long()? as u64)),
+            7  => visitor.visit_byte_array(self.array(1)?),
+            8  => visitor.visit_string(self.string()?),
+            9  => return self.list(visitor),
+            10 => return self.compound(visitor),
+            11 => visitor.visit_int_array(Ints{ bytes: self.array(4)?.chunks_exact(4) }),
+            12 => visitor.visit_long_array(Longs{ bytes: self.array(8)?.chunks_exact(8) }),
+            _  => return Err(Error::InvalidByteSequence(self.pos as u64, id)),
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn list<V: Visitor>(&mut self, visitor: &mut V) -> Result<Flow, Error> {
+        let start = self.pos;
+        let id = self.byte()?;
+        let length = self.length()?;
+        if length > 0 && !(1..13).contains(&id) {
+            return Err(Error::InvalidListType(id));
+        }
+        match visitor.visit_list_start(id, length) {
+            Flow::Continue => (),
+            Flow::Skip => {
+                self.pos = start;
+                self.skip(9)?;
+                return Ok(Flow::Continue);
+            },
+            Flow::Stop => return Ok(Flow::Stop),
+        }
+        self.nest("List")?;
+        for _ in 0..length {
+            if self.payload(id, visitor)? == Flow::Stop {
+                return Ok(Flow::Stop);
+            }
+        }
+        self.nest -= 1;
+        visitor.visit_list_end();
+        Ok(Flow::Continue)
+    }
+
+    fn compound<V: Visitor>(&mut self, visitor: &mut V) -> Result<Flow, Error> {
+        match visitor.visit_compound_start() {
+            Flow::Continue => (),
+            Flow::Skip => {
+                self.skip(10)?;
+                return Ok(Flow::Continue);
+            },
+            Flow::Stop => return Ok(Flow::Stop),
+        }
+        self.nest("Compound")?;
+        loop {
+            let id = self.byte()?;
+            if id == 0 {
+                break;
+            }
+            if self.tag(id, visitor)? == Flow::Stop {
+                return Ok(Flow::Stop);
+            }
+        }
+        self.nest -= 1;
+        visitor.visit_compound_end();
+        Ok(Flow::Continue)
+    }
+
+    /// The name and payload of a tag whose type byte was already read.
+    fn tag<V: Visitor>(&mut self, id: TAGByte, visitor: &mut V) -> Result<Flow, Error> {
+        let name = self.string()?;
+        match visitor.visit_tag(name, id) {
+            Flow::Continue => self.payload(id, visitor),
+            Flow::Skip => {
+                self.skip(id)?;
+                Ok(Flow::Continue)
+            },
+            Flow::Stop => Ok(Flow::Stop),
+        }
+    }
+}
+
+fn fixed_width(id: TAGByte) -> Option<usize> {
+    match id {
+        0 => Some(0),
+        1 => Some(1),
+        2 => Some(2),
+        3 | 5 => Some(4),
+        4 | 6 => Some(8),
+        _ => None,
+    }
+}
+
+/// Streams the uncompressed NBT in `bytes` through `visitor` without building a tree.
+pub fn walk<V: Visitor>(bytes: &[u8], visitor: &mut V) -> Result<(), Error> {
+    let mut reader = Reader::new(bytes);
+    match reader.byte()? {
+        0  => Ok(()),
+        id @ 1..13 => reader.tag(id, visitor).map(|_| ()),
+        id => Err(Error::InvalidByteSequence(0, id)),
+    }
+}
+
+impl Iterator for Ints<'_> {
+    type Item = TAGInt;
+
+    fn next(&mut self) -> Option<TAGInt> {
+        self.bytes.next().map(|int| TAGInt::from_be_bytes(int.try_into().unwrap()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.bytes.size_hint()
+    }
+}
+
+impl Iterator for Longs<'_> {
+    type Item = TAGLong;
+
+    fn next(&mut self) -> Option<TAGLong> {
+        self.bytes.next().map(|long| TAGLong::from_be_bytes(long.try_into().unwrap()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.bytes.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk, Flow, Longs, Visitor};
+    use crate::nbt::{Writer, TAGByte};
+
+    /// Records every tag name it sees and skips or stops at the names it's given.
+    #[derive(Default)]
+    struct Names {
+        seen: Vec<String>,
+        skip: Option<&'static str>,
+        stop: Option<&'static str>,
+        longs: Vec<i64>,
+        lists: usize,
+        skip_lists: bool,
+    }
+
+    impl Visitor for Names {
+        fn visit_tag(&mut self, name: &[u8], _id: TAGByte) -> Flow {
+            let name = String::from_utf8_lossy(name).into_owned();
+            let flow = match name.as_str() {
+                name if Some(name) == self.skip => Flow::Skip,
+                name if Some(name) == self.stop => Flow::Stop,
+                _ => Flow::Continue,
+            };
+            self.seen.push(name);
+            flow
+        }
+
+        fn visit_list_start(&mut self, _id: TAGByte, _length: usize) -> Flow {
+            self.lists += 1;
+            match self.skip_lists {
+                true  => Flow::Skip,
+                false => Flow::Continue,
+            }
+        }
+
+        fn visit_long_array(&mut self, value: Longs<'_>) {
+            self.longs.extend(value);
+        }
+    }
+
+    fn bytes() -> Vec<u8> {
+        let root = crate::nbt!{
+            "before": 1,
+            "skipped": { "inner": { "deeper": 2 }, "list": [{ "element": 3 }] },
+            "after": { "kept": 4b },
+            "longs": [L; 5l, -6l],
+            "last": "end",
+        };
+        let mut writer = Writer::new();
+        assert!(writer.write(&root).is_ok());
+        writer.bytes()
+    }
+
+    fn walked(skip: Option<&'static str>, stop: Option<&'static str>) -> Names {
+        let mut names = Names{ skip, stop, ..Names::default() };
+        assert!(walk(&bytes(), &mut names).is_ok());
+        names
+    }
+
+    #[test]
+    fn visits_everything() {
+        let names = walked(None, None);
+        assert!(names.seen == ["", "before", "skipped", "inner", "deeper", "list", "element", "after", "kept", "longs", "last"]);
+        assert!(names.longs == [5, -6] && names.lists == 1);
+    }
+
+    #[test]
+    fn skip_hides_nested_tags() {
+        let names = walked(Some("skipped"), None);
+        assert!(names.seen == ["", "before", "skipped", "after", "kept", "longs", "last"]);
+        assert!(names.lists == 0 && names.longs == [5, -6]);
+    }
+
+    #[test]
+    fn skipped_lists_hide_their_elements() {
+        let mut names = Names{ skip_lists: true, ..Names::default() };
+        assert!(walk(&bytes(), &mut names).is_ok());
+        assert!(names.seen == ["", "before", "skipped", "inner", "deeper", "list", "after", "kept", "longs", "last"]);
+        assert!(names.lists == 1);
+    }
+
+    #[test]
+    fn stop_ends_the_walk() {
+        let names = walked(None, Some("after"));
+        assert!(names.seen == ["", "before", "skipped", "inner", "deeper", "list", "element", "after"]);
+        assert!(names.longs.is_empty());
+    }
+
+    #[test]
+    fn truncated_input_fails() {
+        let bytes = bytes();
+        assert!(walk(&bytes[..bytes.len() - 3], &mut Names::default()).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Condvar, Mutex};
+
+/// The items a pool hands out and how far its consumer has got.
+struct Queue<T> {
+    items:  std::iter::Enumerate<std::vec::IntoIter<T>>,
+    /// Index of the next item a worker would take.
+    taken:  usize,
+    /// Index of the next result the sink is waiting for or working on.
+    next:   usize,
+    closed: bool,
+}
+
+/// Runs `work` over `items` on up to `jobs` threads and hands the results to `sink` in the order
+/// of `items`, as soon as every earlier result has been handed over. Workers never run more than
+/// `jobs * 2` items ahead of the sink, so a slow sink bounds how many results are held at once.
+/// The first error `sink` returns ends the run; workers stop picking up new items once nobody is
+/// listening for their results.
+pub fn ordered<T, R, E>(
+    jobs:  usize,
+    items: Vec<T>,
+    work:  impl Fn(T) -> R + Sync,
+    mut sink: impl FnMut(R) -> Result<(), E>,
+) -> Result<(), E>
+where
+    T: Send,
+    R: Send,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    let window = jobs * 2;
+    let queue = Mutex::new(Queue{ items: items.into_iter().enumerate(), taken: 0, next: 0, closed: false });
+    let advanced = Condvar::new();
+    let (send, recv) = mpsc::sync_channel(jobs);
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let (send, queue, advanced, work) = (send.clone(), &queue, &advanced, &work);
+            scope.spawn(move || loop {
+                let mut state = advanced
+                    .wait_while(queue.lock().unwrap(), |state| !state.closed && state.taken >= state.next + window)
+                    .unwrap();
+                if state.closed {
+                    break;
+                }
+                let Some((i, item)) = state.items.next() else {
+                    break;
+                };
+                state.taken += 1;
+                drop(state);
+                if send.send((i, work(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(send);
+
+        let result = (|| {
+            /* only indices inside the window are ever taken, so at most `window` results wait here */
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            for (i, result) in recv {
+                pending.insert(i, result);
+                while let Some(result) = pending.remove(&next) {
+                    sink(result)?;
+                    next += 1;
+                    queue.lock().unwrap().next = next;
+                    advanced.notify_all();
+                }
+            }
+            Ok(())
+        })();
+
+        /* wakes workers waiting on the window when the sink gave up early */
+        queue.lock().unwrap().closed = true;
+        advanced.notify_all();
+        result
+    })
+}
+
+/// Worker threads to use when `--jobs` isn't given: one per core.
+pub fn default_jobs() -> usize {
+    std::thread::available_parallelism().map(|jobs| jobs.get()).unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ordered;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn results_arrive_in_order() {
+        let mut out = Vec::new();
+        let done: Result<(), ()> = ordered(4, (0..200).collect(), |i: u64| {
+            /* later items finish first */
+            std::thread::sleep(std::time::Duration::from_micros(200 - i));
+            i * 2
+        }, |result| {
+            out.push(result);
+            Ok(())
+        });
+        assert!(done.is_ok());
+        assert!(out == (0..200).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stalled_sink_caps_decoded_items() {
+        let started = AtomicUsize::new(0);
+        let mut seen = Vec::new();
+        let done: Result<(), ()> = ordered(2, (0..100).collect(), |i: usize| {
+            started.fetch_add(1, Ordering::SeqCst);
+            i
+        }, |result| {
+            if result == 0 {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            seen.push(started.load(Ordering::SeqCst));
+            Ok(())
+        });
+        assert!(done.is_ok());
+        /* while item 0 is in the sink the workers may only run items 1 to 3 */
+        assert!(seen[0] <= 4, "{} items were decoded while the sink stalled", seen[0]);
+        assert!(started.load(Ordering::SeqCst) == 100);
+    }
+
+    #[test]
+    fn slow_item_caps_decoded_items() {
+        let started = AtomicUsize::new(0);
+        let mut seen = Vec::new();
+        let done: Result<(), ()> = ordered(2, (0..100).collect(), |i: usize| {
+            started.fetch_add(1, Ordering::SeqCst);
+            if i == 0 {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+            }
+            i
+        }, |_| {
+            seen.push(started.load(Ordering::SeqCst));
+            Ok(())
+        });
+        assert!(done.is_ok());
+        /* the sink waits on item 0, so the other worker may only run ahead to item 3 */
+        assert!(seen[0] <= 4, "{} items were decoded while waiting on the first", seen[0]);
+    }
+
+    #[test]
+    fn sink_errors_end_the_run() {
+        let started = AtomicUsize::new(0);
+        let done = ordered(2, (0..1000).collect(), |i: usize| {
+            started.fetch_add(1, Ordering::SeqCst);
+            i
+        }, |result| match result {
+            3 => Err(result),
+            _ => Ok(()),
+        });
+        assert!(done == Err(3));
+        assert!(started.load(Ordering::SeqCst) < 1000);
+    }
+}
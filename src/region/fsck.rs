@@ -0,0 +1,141 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use super::{ENTRIES, BOUNDARY, EXTERNAL, __GZIP, ZLIB, NONE, LZ4, CUSTOM};
+
+/// Something wrong with the layout of a region file, found without decoding any chunk.
+pub enum Problem {
+    Unaligned(usize),
+    Truncated(usize),
+    Location{ index: usize, offset: u32, sectors: u8 },
+    InHeader{ index: usize, offset: u32 },
+    OutOfBounds{ index: usize, offset: u32, sectors: u8, available: usize },
+    Overlap{ index: usize, other: usize },
+    ZeroLength{ index: usize },
+    Length{ index: usize, length: usize, sectors: u8 },
+    Compression{ index: usize, compression: u8 },
+    Timestamp{ index: usize, timestamp: i32 },
+}
+
+fn field(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(bytes[at..at+4].try_into().unwrap())
+}
+
+/// Validates the header of a region file against its contents: where the location table points,
+/// whether allocations overlap, and whether each chunk's length and compression type make sense.
+/// Timestamps after `now` (in seconds since the epoch) are reported as well.
+pub fn check(bytes: &[u8], now: i64) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if !bytes.len().is_multiple_of(BOUNDARY) {
+        problems.push(Problem::Unaligned(bytes.len()));
+    }
+    if bytes.len() < 2*BOUNDARY {
+        /* an empty file is how the game leaves a region it never wrote to */
+        if !bytes.is_empty() {
+            problems.push(Problem::Truncated(bytes.len()));
+        }
+        return problems;
+    }
+
+    let available = bytes.len() / BOUNDARY;
+    let mut ranges = Vec::new();
+    for index in 0..ENTRIES {
+        let location = field(bytes, index*4);
+        let (offset, sectors) = (location >> 8, location as u8);
+        let timestamp = field(bytes, BOUNDARY + index*4) as i32;
+        if location == 0 {
+            continue;
+        }
+        if timestamp as i64 > now {
+            problems.push(Problem::Timestamp{ index, timestamp });
+        }
+        if offset == 0 || sectors == 0 {
+            problems.push(Problem::Location{ index, offset, sectors });
+            continue;
+        }
+        if offset < 2 {
+            problems.push(Problem::InHeader{ index, offset });
+            continue;
+        }
+        let (start, end) = (offset as usize, offset as usize + sectors as usize);
+        if end > available {
+            problems.push(Problem::OutOfBounds{ index, offset, sectors, available });
+            continue;
+        }
+        ranges.push((start, end, index));
+
+        let at = start * BOUNDARY;
+        let length = field(bytes, at) as usize;
+        if length == 0 {
+            problems.push(Problem::ZeroLength{ index });
+            continue;
+        }
+        if (length + 4).div_ceil(BOUNDARY) != sectors as usize {
+            problems.push(Problem::Length{ index, length, sectors });
+        }
+        let compression = bytes[at+4];
+        if !matches!(compression & !EXTERNAL, __GZIP | ZLIB | NONE | LZ4 | CUSTOM) {
+            problems.push(Problem::Compression{ index, compression });
+        }
+    }
+
+    ranges.sort();
+    let mut last: Option<(usize, usize)> = None;
+    for (start, end, index) in ranges {
+        match last {
+            Some((other, other_end)) if start < other_end => {
+                problems.push(Problem::Overlap{ index, other });
+                if end > other_end {
+                    last = Some((index, end));
+                }
+            },
+            _ => last = Some((index, end)),
+        }
+    }
+    problems
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Problem::Unaligned(size) => write!(f, "file size {} is not a multiple of {} byte sectors", size, BOUNDARY),
+            Problem::Truncated(size) => write!(f, "file is {} bytes, too short to hold the {} byte header", size, 2*BOUNDARY),
+            Problem::Location{ index, offset, sectors } => write!(f, "chunk {}: location has offset {} but {} sectors", index, offset, sectors),
+            Problem::InHeader{ index, offset } => write!(f, "chunk {}: offset {} points into the header", index, offset),
+            Problem::OutOfBounds{ index, offset, sectors, available } => {
+                write!(f, "chunk {}: sectors {}..{} run past the end of the file ({} sectors)", index, offset, *offset + *sectors as u32, available)
+            },
+            Problem::Overlap{ index, other } => write!(f, "chunk {}: sectors overlap those of chunk {}", index, other),
+            Problem::ZeroLength{ index } => write!(f, "chunk {}: declared length is 0", index),
+            Problem::Length{ index, length, sectors } => {
+                write!(f, "chunk {}: {} bytes need {} sectors but {} are allocated", index, length, (length + 4).div_ceil(BOUNDARY), sectors)
+            },
+            Problem::Compression{ index, compression } => write!(f, "chunk {}: unknown compression type {}", index, compression),
+            Problem::Timestamp{ index, timestamp } => write!(f, "chunk {}: timestamp {} is in the future", index, timestamp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Problem};
+
+    #[test]
+    fn finds_layout_faults() {
+        let problems = check(&crate::region::tests::damaged(1000), 1000);
+        assert!(problems.len() == 4, "{} problems", problems.len());
+        assert!(problems.iter().any(|problem| matches!(problem, Problem::Compression{ index: 1, compression: 9 })));
+        assert!(problems.iter().any(|problem| matches!(problem, Problem::OutOfBounds{ index: 4, offset: 200, sectors: 1, .. })));
+        assert!(problems.iter().any(|problem| matches!(problem, Problem::Timestamp{ index: 5, timestamp: 1001 })));
+        assert!(problems.iter().any(|problem| matches!(problem, Problem::Overlap{ index: 2, other: 0 } | Problem::Overlap{ index: 0, other: 2 })));
+    }
+
+    #[test]
+    fn short_and_empty_files() {
+        assert!(check(&[], 0).is_empty());
+        let problems = check(&[0; 5000], 0);
+        assert!(matches!(problems[..], [Problem::Unaligned(5000), Problem::Truncated(5000)]));
+        assert!(check(&vec![0; 8192], 0).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+/* The game stores LZ4 chunks the way lz4-java's LZ4BlockOutputStream writes them: a series of blocks,
+ * each with a 21 byte header, closed by an empty block.
+ *
+ *     "LZ4Block" | method << 4 | level | compressed length | length | checksum | data
+ *
+ * Lengths and the checksum are little endian. The checksum is xxhash32 of the uncompressed block,
+ * truncated to 28 bits. */
+
+use xxhash_rust::xxh32::xxh32;
+
+const MAGIC: &[u8; 8] = b"LZ4Block";
+const HEADER: usize = MAGIC.len() + 1 + 4 + 4 + 4;
+const RAW: u8 = 0x10;
+const COMPRESSED: u8 = 0x20;
+const SEED: u32 = 0x9747b28c;
+/* the stream's default block size, 64 KiB, which it records as a level of 6 */
+const BLOCK: usize = 1 << 16;
+const LEVEL: u8 = 6;
+/* a block is at most 1 << (10 + level) bytes, the level being 4 bits, so 32 MiB at most */
+const MIN_BLOCK_SHIFT: u32 = 10;
+
+fn checksum(block: &[u8]) -> u32 {
+    xxh32(block, SEED) & 0x0FFFFFFF
+}
+
+fn header(bytes: &mut Vec<u8>, method: u8, compressed: usize, length: usize, checksum: u32) {
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(method | LEVEL);
+    bytes.extend_from_slice(&(compressed as u32).to_le_bytes());
+    bytes.extend_from_slice(&(length as u32).to_le_bytes());
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+}
+
+fn field(bytes: &[u8], at: usize) -> usize {
+    u32::from_le_bytes(bytes[at..at+4].try_into().unwrap()) as usize
+}
+
+pub(super) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for block in data.chunks(BLOCK) {
+        let compressed = lz4_flex::block::compress(block);
+        /* blocks that don't shrink are stored as they are, like the stream does */
+        if compressed.len() < block.len() {
+            header(&mut bytes, COMPRESSED, compressed.len(), block.len(), checksum(block));
+            bytes.extend_from_slice(&compressed);
+        } else {
+            header(&mut bytes, RAW, block.len(), block.len(), checksum(block));
+            bytes.extend_from_slice(block);
+        }
+    }
+    header(&mut bytes, RAW, 0, 0, 0);
+    bytes
+}
+
+pub(super) fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut pos = 0;
+    /* a stream cut off right after a block still holds every byte written before it */
+    while pos < data.len() {
+        let Some(header) = data.get(pos..pos+HEADER) else {
+            return Err(format!("truncated block header at {}", pos));
+        };
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(format!("missing block magic at {}", pos));
+        }
+        let method = header[MAGIC.len()] & 0xF0;
+        let limit = 1usize << (MIN_BLOCK_SHIFT + (header[MAGIC.len()] & 0x0F) as u32);
+        let (compressed, length) = (field(header, 9), field(header, 13));
+        if length > limit {
+            return Err(format!("block at {} claims {} bytes but its level allows {}", pos, length, limit));
+        }
+        let expected = field(header, 17) as u32;
+        pos += HEADER;
+        if compressed == 0 && length == 0 {
+            break;
+        }
+        let Some(block) = data.get(pos..pos+compressed) else {
+            return Err(format!("block of {} bytes at {} runs past the end", compressed, pos));
+        };
+        pos += compressed;
+
+        let start = bytes.len();
+        match method {
+            RAW if compressed == length => bytes.extend_from_slice(block),
+            RAW => return Err(format!("raw block of {} bytes claims {} uncompressed", compressed, length)),
+            COMPRESSED => match lz4_flex::block::decompress(block, length) {
+                Ok(block) => bytes.extend_from_slice(&block),
+                Err(e)    => return Err(e.to_string()),
+            },
+            _ => return Err(format!("unknown block method {:#02x}", method)),
+        }
+        if checksum(&bytes[start..]) != expected {
+            return Err(format!("checksum mismatch in block at {}", pos - compressed - HEADER));
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress, BLOCK, HEADER};
+
+    /// Compressible text followed by bytes that don't shrink, so both block methods are written.
+    fn data() -> Vec<u8> {
+        let mut data = "minecraft:stone ".repeat(BLOCK / 8).into_bytes();
+        data.extend((0..BLOCK as u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8));
+        data
+    }
+
+    #[test]
+    fn round_trips() {
+        for data in [Vec::new(), b"a".to_vec(), data()] {
+            let compressed = compress(&data);
+            assert!(decompress(&compressed).is_ok_and(|back| back == data));
+        }
+        /* the text blocks shrink, the noise block is stored raw */
+        let compressed = compress(&data());
+        assert!(compressed.len() < data().len());
+        assert!(compressed[8] == 0x26 && compressed.windows(9).any(|w| w == b"LZ4Block\x16"));
+    }
+
+    #[test]
+    fn checksums_are_verified() {
+        let mut compressed = compress(&data());
+        /* the last byte of the final data block, just ahead of the closing empty block */
+        let at = compressed.len() - HEADER - 1;
+        compressed[at] ^= 1;
+        assert!(decompress(&compressed).is_err_and(|e| e.contains("checksum")));
+    }
+
+    #[test]
+    fn damaged_streams_fail() {
+        let compressed = compress(b"minecraft:stone minecraft:stone");
+        assert!(decompress(&compressed[..HEADER - 1]).is_err());
+        assert!(decompress(&compressed[..HEADER + 2]).is_err());
+        let mut bad = compressed.clone();
+        bad[0] = b'X';
+        assert!(decompress(&bad).is_err());
+        /* a stream missing only its closing block still decodes */
+        assert!(decompress(&compressed[..compressed.len() - HEADER]).is_ok());
+    }
+
+    #[test]
+    fn lengths_past_the_block_size_are_refused() {
+        let mut compressed = compress(&data());
+        /* level 6 allows 64 KiB, claim one byte more */
+        compressed[13..17].copy_from_slice(&(BLOCK as u32 + 1).to_le_bytes());
+        assert!(decompress(&compressed).is_err_and(|e| e.contains("level allows")));
+        compressed[8] = 0x2F;
+        compressed[13..17].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decompress(&compressed).is_err_and(|e| e.contains("level allows")));
+    }
+}
@@ -2,10 +2,18 @@
 // Created by Justin Tunheim on 6/26/24
 //
 
-use std::io::{Read, Cursor, Seek, SeekFrom};
+pub mod fsck;
+pub mod repair;
+pub mod relocate;
+pub mod section;
+mod lz4;
+
+use std::io::{Read, Write, Cursor, Seek, SeekFrom};
 use byteorder::{BigEndian, ReadBytesExt};
-use flate2::read::ZlibDecoder;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::ZlibEncoder;
 use colored::*;
+use memmap2::Mmap;
 
 use crate::nbt::{self, NBT};
 
@@ -18,6 +26,10 @@ const ZLIB  : u8 = 2;
 const NONE  : u8 = 3;
 const LZ4   : u8 = 4;
 const CUSTOM: u8 = 127;
+/* set on the compression type when the chunk is stored in a `c.<x>.<z>.mcc` file next to the region */
+const EXTERNAL: u8 = 0x80;
+/* the most a location entry can describe; anything bigger goes to a `.mcc` file */
+const MAX_SECTORS: usize = 255;
 
 pub enum Error {
     CouldntSortChunks,
@@ -26,6 +38,10 @@ pub enum Error {
     CompressionType(u8),
     Decompress(u8, String),
     ChunkNBT(nbt::Error),
+    Location(u32, u8),
+    OutsideRegion(i32, i32),
+    External(String, std::io::Error),
+    Encode(nbt::Error),
     Unimplemented,
 }
 
@@ -39,13 +55,53 @@ pub struct Responsible {
 pub struct Report {
     err: Error,
     pos: u64,
-    responsible: [Responsible; 20],
+    responsible: Vec<Responsible>,
+}
+
+/// A region file mapped into memory, so only the pages a `Parser` actually touches (the header and
+/// the sectors of the chunks it reads) are loaded from disk. The mapping can be shared across threads.
+#[derive(Default)]
+pub struct Mapped {
+    /* empty files can't be mapped on every platform, so they are represented by no mapping */
+    map: Option<Mmap>,
+}
+
+/// How a `Writer` compresses the chunks it stores.
+#[derive(Clone, Copy, Debug)]
+pub enum Compression {
+    Zlib,
+    Lz4,
+    None,
+}
+
+/// Builds a region file from whole chunks, the write-side counterpart of `Parser`. Chunks are
+/// compressed as they are added and laid out when the writer is finished.
+pub struct Writer {
+    region:      (i32, i32),
+    compression: Compression,
+    /* timestamp, compression type and compressed data of each slot */
+    chunks:      Vec<Option<(i32, u8, Vec<u8>)>>,
+    /* slots copied as stubs of chunks kept in `.mcc` files */
+    copied:      Vec<usize>,
+}
+
+/// A finished region file, and the `.mcc` files holding chunks too big for it.
+pub struct Written {
+    pub region:   Vec<u8>,
+    /// File name and contents of each external chunk.
+    pub external: Vec<(String, Vec<u8>)>,
+    /// Names of the `.mcc` files behind copied stubs whose chunks were replaced by ones that fit.
+    pub stale:    Vec<String>,
 }
 
-pub struct Parser {
+/// Reads a region file in place; chunks are inflated straight out of the borrowed buffer.
+pub struct Parser<'a> {
     length: usize,
-    bytes:  Cursor<Vec<u8>>,
-    copy:   Vec<u8>,
+    bytes:  Cursor<&'a [u8]>,
+    /// Populated header slots in index order, read on first use.
+    header: Option<Vec<ChunkHeaderPair>>,
+    /// Folder and region coordinates of the file, to find chunks stored in `.mcc` files.
+    beside: Option<(std::path::PathBuf, (i32, i32))>,
 }
 
 pub struct Chunk {
@@ -53,6 +109,21 @@ pub struct Chunk {
     root: NBT,
 }
 
+/// A chunk `Parser::parse_tolerant` skipped, by its index in the region.
+pub struct Failure {
+    pub index:  usize,
+    pub report: Report,
+}
+
+/// A populated slot of the region header.
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub index:     usize,
+    pub offset:    u32,
+    pub sectors:   u8,
+    pub timestamp: i32,
+}
+
 #[derive(Clone, Copy, Debug)]
 struct Location {
     offset: u32,
@@ -66,11 +137,12 @@ struct Timestamp {
 
 #[derive(Clone, Debug)]
 struct ChunkHeaderPair {
+    index: usize,
     location: Location,
     timestamp: Timestamp,
 }
 
-impl Parser {
+impl<'a> Parser<'a> {
     fn locations(&mut self) -> Result<[Location; ENTRIES], Error> {
         let mut locations: [Location; ENTRIES] = [Location {offset: 0, sector: 0}; ENTRIES];
         for location in locations.iter_mut() {
@@ -92,21 +164,22 @@ impl Parser {
         let Ok(raw_length) = self.bytes.read_u32::<BigEndian>() else {
             return Err(Error::ChunkLength);
         };
+        if raw_length == 0 {
+            return Err(Error::ChunkLength);
+        }
         let length = raw_length as usize - 1;
         let Ok(compression) = self.bytes.read_u8() else {
             return Err(Error::Compression);
         };
 
-        let mut nbt_data = Vec::new();
         let pos = self.bytes.position() as usize;
-        match compression {
-            ZLIB => {
-                if let Err(e) = ZlibDecoder::new(&self.copy[pos..pos+length]).read_to_end(&mut nbt_data) {
-                    return Err(Error::Decompress(compression, e.to_string()));
-                };
-            }
-            _ => return Err(Error::CompressionType(compression)),
-        }
+        let Some(data) = self.bytes.get_ref().get(pos..pos+length) else {
+            return Err(Error::ChunkLength);
+        };
+        let nbt_data = match compression & EXTERNAL {
+            0 => inflate(compression, data)?,
+            _ => inflate(compression & !EXTERNAL, &self.external(hdr_pair.index, compression)?)?,
+        };
 
         let mut root = NBT::default();
         if let Err(e) = nbt::Parser::new(nbt_data).parse(&mut root) {
@@ -118,9 +191,42 @@ impl Parser {
 }
 
 
-impl Parser {
+impl<'a> Parser<'a> {
+    /// Every populated header slot, in header order. The tables are only read once per parser.
+    fn header(&mut self) -> Result<&[ChunkHeaderPair], Report> {
+        if self.header.is_none() {
+            self.header = Some(self.read_header()?);
+        }
+        Ok(self.header.as_deref().unwrap_or_default())
+    }
+
+    fn read_header(&mut self) -> Result<Vec<ChunkHeaderPair>, Report> {
+        if self.length < 2*BOUNDARY {
+            return Ok(Vec::new())
+        }
+        self.bytes.seek(SeekFrom::Start(0)).expect("Invalid header offset");
+        let locations = match self.locations() {
+            Ok(locs) => locs,
+            Err(e)   => return Err(Report::new(e, &mut self.bytes.clone())),
+        };
+        let timestamps = match self.timestamps() {
+            Ok(times) => times,
+            Err(e)    => return Err(Report::new(e, &mut self.bytes.clone())),
+        };
+
+        let mut pairs = Vec::new();
+        for (index, (location, timestamp)) in locations.into_iter().zip(timestamps).enumerate() {
+            if location.offset != 0 && location.sector != 0 {
+                pairs.push(ChunkHeaderPair{ index, location, timestamp });
+            }
+        }
+        Ok(pairs)
+    }
+}
+
+impl<'a> Parser<'a> {
     pub fn parse(&mut self) -> Result<Vec<Chunk>, Report> {
-        if self.copy.len() == 0 {
+        if self.length == 0 {
             return Ok(Vec::new())
         }
         let locations = match self.locations() {
@@ -135,9 +241,9 @@ impl Parser {
         let mut chunk_data = Vec::new();
         let mut chunks = Vec::new();
 
-        for (location, timestamp) in locations.into_iter().zip(timestamps.into_iter()) {
+        for (index, (location, timestamp)) in locations.into_iter().zip(timestamps.into_iter()).enumerate() {
             if location.offset != 0 && location.sector != 0 {
-                chunk_data.push(ChunkHeaderPair{ location, timestamp });
+                chunk_data.push(ChunkHeaderPair{ index, location, timestamp });
             }
         }
 
@@ -157,15 +263,307 @@ impl Parser {
         Ok(chunks)
     }
 
-    pub fn new(bytes: Vec<u8>) -> Self {
+    /// Like `parse`, but a chunk that fails to decode is reported and skipped instead of failing
+    /// the whole file. Only an unreadable header is still an error.
+    pub fn parse_tolerant(&mut self) -> Result<(Vec<Chunk>, Vec<Failure>), Report> {
+        let mut pairs = self.header()?.to_vec();
+        pairs.sort_by_key(|pair| pair.location.offset);
+
+        let (mut chunks, mut failures) = (Vec::new(), Vec::new());
+        for pair in pairs {
+            let index = pair.index;
+            let offset = pair.location.offset as usize * BOUNDARY;
+            self.bytes.seek(SeekFrom::Start(offset as u64)).expect("Invalid chunk offset");
+            match self.chunk(pair) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(e)    => failures.push(Failure{ index, report: Report::new(e, &mut self.bytes.clone()) }),
+            }
+        }
+        Ok((chunks, failures))
+    }
+
+    /// Parses only the chunk stored at `index` (`x + z * 32` within the region), if present.
+    pub fn chunk_at(&mut self, index: usize) -> Result<Option<Chunk>, Report> {
+        if index >= ENTRIES {
+            return Ok(None)
+        }
+        let pairs = self.header()?;
+        let Ok(found) = pairs.binary_search_by_key(&index, |pair| pair.index) else {
+            return Ok(None)
+        };
+        let hdr_pair = pairs[found].clone();
+        let offset = hdr_pair.location.offset as usize * BOUNDARY;
+        self.bytes.seek(SeekFrom::Start(offset as u64)).expect("Invalid chunk offset");
+        match self.chunk(hdr_pair) {
+            Ok(chunk) => Ok(Some(chunk)),
+            Err(e)    => Err(Report::new(e, &mut self.bytes.clone())),
+        }
+    }
+
+    /// Reads only the location and timestamp tables, leaving every chunk undecoded.
+    pub fn entries(&mut self) -> Result<Vec<Entry>, Report> {
+        Ok(self.header()?.iter().map(|pair| Entry {
+            index:     pair.index,
+            offset:    pair.location.offset,
+            sectors:   pair.location.sector,
+            timestamp: pair.timestamp.entry,
+        }).collect())
+    }
+
+    /// The chunk's uncompressed NBT, for readers that walk it without building a tree.
+    pub fn inflate(&mut self, entry: &Entry) -> Result<Vec<u8>, Report> {
+        let start = entry.offset as usize * BOUNDARY;
+        self.bytes.seek(SeekFrom::Start(start as u64 + 5)).expect("Invalid chunk offset");
+        let Some(raw) = self.raw(entry) else {
+            return Err(Report::new(Error::ChunkLength, &mut self.bytes.clone()));
+        };
+        let inflated = match raw.get(4..) {
+            Some([compression, ..]) if compression & EXTERNAL != 0 => self.external(entry.index, *compression)
+                .and_then(|data| inflate(compression & !EXTERNAL, &data)),
+            Some([compression, data @ ..]) => inflate(*compression, data),
+            _ => Err(Error::Compression),
+        };
+        match inflated {
+            Ok(data) => Ok(data),
+            Err(e)   => Err(Report::new(e, &mut self.bytes.clone())),
+        }
+    }
+
+    /// The compressed data of the chunk at `index` from its `.mcc` file. Without knowing where the
+    /// region lives (see `beside`) such chunks can't be read and fail like an unknown compression.
+    fn external(&self, index: usize, compression: u8) -> Result<Vec<u8>, Error> {
+        let Some((dir, region)) = &self.beside else {
+            return Err(Error::CompressionType(compression));
+        };
+        let name = external_name(*region, index);
+        std::fs::read(dir.join(&name)).map_err(|e| Error::External(name, e))
+    }
+
+    /// Reads chunks the game moved to `.mcc` files from the folder of the region file at `path`.
+    pub fn beside(mut self, path: &std::path::Path) -> Self {
+        if let Some(region) = coordinates(path) {
+            let dir = path.parent().unwrap_or(std::path::Path::new("."));
+            self.beside = Some((dir.to_path_buf(), region));
+        }
+        self
+    }
+
+    /// A copy of the region holding only the chunks `keep` accepts, compacted like `repair` does.
+    /// Kept chunks are copied as stored, without being decoded.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Entry) -> bool) -> Result<Vec<u8>, Report> {
+        let mut kept = Vec::new();
+        for entry in self.entries()?.into_iter().filter(|entry| keep(entry)) {
+            let Some(stored) = self.raw(&entry).filter(|stored| stored.len() <= MAX_SECTORS*BOUNDARY) else {
+                self.bytes.seek(SeekFrom::Start(entry.offset as u64 * BOUNDARY as u64)).expect("Invalid chunk offset");
+                return Err(Report::new(Error::ChunkLength, &mut self.bytes.clone()));
+            };
+            kept.push((entry.index, entry.timestamp, stored));
+        }
+        Ok(assemble(kept))
+    }
+
+    /// The chunk exactly as stored: length, compression type and compressed data.
+    pub fn raw(&self, entry: &Entry) -> Option<&'a [u8]> {
+        let bytes: &'a [u8] = self.bytes.get_ref();
+        let start = entry.offset as usize * BOUNDARY;
+        let length = u32::from_be_bytes(bytes.get(start..start+4)?.try_into().ok()?) as usize;
+        bytes.get(start..start+4+length)
+    }
+
+    pub fn new(bytes: &'a [u8]) -> Self {
         Self {
             length: bytes.len(),
-            bytes:  Cursor::new(bytes.clone()),
-            copy:   bytes.clone(),
+            bytes:  Cursor::new(bytes),
+            header: None,
+            beside: None,
         }
     }
 }
 
+impl Writer {
+    /// Adds the chunk at chunk coordinates `x`, `z`, replacing any chunk already written there.
+    /// `timestamp` is the time of its last save in seconds since the epoch.
+    pub fn chunk(&mut self, x: i32, z: i32, root: &NBT, timestamp: i32) -> Result<(), Error> {
+        if (x >> 5, z >> 5) != self.region {
+            return Err(Error::OutsideRegion(x, z));
+        }
+        let mut writer = nbt::Writer::new();
+        writer.write(root).map_err(Error::Encode)?;
+        let data = deflate(self.compression, &writer.bytes());
+        let compression = match self.compression {
+            Compression::Zlib => ZLIB,
+            Compression::Lz4  => LZ4,
+            Compression::None => NONE,
+        };
+        self.chunks[(x & 31) as usize + (z & 31) as usize * 32] = Some((timestamp, compression, data));
+        Ok(())
+    }
+
+    /// Adds a chunk exactly as another region stores it (see `Parser::raw`) at header slot `index`,
+    /// without decoding it. Stubs of chunks kept in `.mcc` files are copied as they are, so the
+    /// `.mcc` file has to stay next to the region. Replacing such a stub with `chunk` later marks
+    /// its `.mcc` file stale when the new chunk fits in the region.
+    pub fn stored(&mut self, index: usize, timestamp: i32, stored: &[u8]) -> Result<(), Error> {
+        let [_, _, _, _, compression, data @ ..] = stored else {
+            return Err(Error::Compression);
+        };
+        if compression & EXTERNAL != 0 {
+            self.copied.push(index);
+        }
+        self.chunks[index] = Some((timestamp, *compression, data.to_vec()));
+        Ok(())
+    }
+
+    /// Lays the chunks out in sectors behind the header. A chunk that needs more sectors than a
+    /// location can describe is written to `c.<x>.<z>.mcc`, leaving a one sector stub in the region.
+    pub fn finish(self) -> Written {
+        let mut stored = Vec::new();
+        let mut external = Vec::new();
+        let mut stale = Vec::new();
+        for (index, chunk) in self.chunks.into_iter().enumerate() {
+            let Some((timestamp, compression, data)) = chunk else {
+                continue;
+            };
+            let mut bytes = Vec::with_capacity(data.len() + 5);
+            if compression & EXTERNAL != 0 {
+                bytes.extend_from_slice(&1u32.to_be_bytes());
+                bytes.push(compression);
+            } else if data.len() + 5 > MAX_SECTORS*BOUNDARY {
+                bytes.extend_from_slice(&1u32.to_be_bytes());
+                bytes.push(compression | EXTERNAL);
+                external.push((external_name(self.region, index), data));
+            } else {
+                bytes.extend_from_slice(&(data.len() as u32 + 1).to_be_bytes());
+                bytes.push(compression);
+                bytes.extend_from_slice(&data);
+                if self.copied.contains(&index) {
+                    stale.push(external_name(self.region, index));
+                }
+            }
+            stored.push((index, timestamp, bytes));
+        }
+        Written {
+            region: assemble(stored.iter().map(|(index, timestamp, bytes)| (*index, *timestamp, &bytes[..]))),
+            external,
+            stale,
+        }
+    }
+
+    /// A writer for the region at region coordinates `x`, `z`, i.e. the file `r.<x>.<z>.mca`.
+    pub fn new(x: i32, z: i32, compression: Compression) -> Self {
+        Self {
+            region: (x, z),
+            compression,
+            chunks: vec![None; ENTRIES],
+            copied: Vec::new(),
+        }
+    }
+}
+
+impl Written {
+    /// Writes the region to `path` and its external chunks next to it. The region goes through a
+    /// temporary file after the new `.mcc` files and before the stale ones are deleted, so neither
+    /// the old nor the new region ever points at a `.mcc` file that isn't there.
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let dir = path.parent().unwrap_or(std::path::Path::new("."));
+        for (name, bytes) in self.external.iter() {
+            std::fs::write(dir.join(name), bytes)?;
+        }
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, &self.region)?;
+        std::fs::rename(&temporary, path)?;
+        for name in self.stale.iter() {
+            match std::fs::remove_file(dir.join(name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Region coordinates from a region file name, e.g `r.-1.2.mca`.
+pub fn coordinates(path: &std::path::Path) -> Option<(i32, i32)> {
+    let name = path.file_name()?.to_str()?;
+    let mut parts = name.split('.');
+    if parts.next()? != "r" {
+        return None;
+    }
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    Some((x, z))
+}
+
+/// The `.mcc` file the chunk at header slot `index` of region `rx`, `rz` is moved to when it's too big.
+fn external_name((rx, rz): (i32, i32), index: usize) -> String {
+    format!("c.{}.{}.mcc", rx * 32 + (index % 32) as i32, rz * 32 + (index / 32) as i32)
+}
+
+/// Maps the region file at `path` for reading.
+pub fn open(path: &std::path::Path) -> std::io::Result<Mapped> {
+    let file = std::fs::File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(Mapped::default());
+    }
+    /* SAFETY: the mapping is read only. Should the game rewrite the file while we hold it, we can
+     * read torn chunk data, which the parser rejects like any other corrupt chunk. */
+    let map = unsafe { Mmap::map(&file)? };
+    Ok(Mapped{ map: Some(map) })
+}
+
+/// Lays chunks out the way the game does: both header tables, then each chunk in its own run of
+/// sectors. `stored` is the chunk as it sits on disk (length field, compression type and data) and
+/// must fit in the 255 sectors a location entry can describe.
+fn assemble<'a>(chunks: impl IntoIterator<Item = (usize, i32, &'a [u8])>) -> Vec<u8> {
+    let mut bytes = vec![0; 2*BOUNDARY];
+    for (index, timestamp, stored) in chunks {
+        let offset = (bytes.len() / BOUNDARY) as u32;
+        let sectors = stored.len().div_ceil(BOUNDARY) as u32;
+        bytes[index*4..index*4+4].copy_from_slice(&(offset << 8 | sectors).to_be_bytes());
+        bytes[BOUNDARY+index*4..BOUNDARY+index*4+4].copy_from_slice(&timestamp.to_be_bytes());
+        bytes.extend_from_slice(stored);
+        bytes.resize(bytes.len().next_multiple_of(BOUNDARY), 0);
+    }
+    bytes
+}
+
+fn inflate(compression: u8, data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut nbt_data = Vec::new();
+    match compression {
+        __GZIP => {
+            if let Err(e) = GzDecoder::new(data).read_to_end(&mut nbt_data) {
+                return Err(Error::Decompress(compression, e.to_string()));
+            };
+        }
+        ZLIB => {
+            if let Err(e) = ZlibDecoder::new(data).read_to_end(&mut nbt_data) {
+                return Err(Error::Decompress(compression, e.to_string()));
+            };
+        }
+        NONE => nbt_data.extend_from_slice(data),
+        LZ4 => match lz4::decompress(data) {
+            Ok(data) => nbt_data = data,
+            Err(e)   => return Err(Error::Decompress(compression, e)),
+        },
+        _ => return Err(Error::CompressionType(compression)),
+    }
+    Ok(nbt_data)
+}
+
+fn deflate(compression: Compression, data: &[u8]) -> Vec<u8> {
+    match compression {
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            /* writing into a Vec can't fail */
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        },
+        Compression::Lz4  => lz4::compress(data),
+        Compression::None => data.to_vec(),
+    }
+}
+
 fn sort_chunk_data_by_location(data: Vec<ChunkHeaderPair>) -> Result<Vec<ChunkHeaderPair>, Error> {
     let mut chunk_data = Vec::new();
     for datum in data.into_iter() {
@@ -181,12 +579,13 @@ fn sort_chunk_data_by_location(data: Vec<ChunkHeaderPair>) -> Result<Vec<ChunkHe
 }
 
 impl Chunk {
-    pub fn nbt(&self) -> &NBT {
-        &self.root
+    /// Position of the chunk inside its region file, `x + z * 32`.
+    pub fn index(&self) -> usize {
+        self.hdr_pair.index
     }
 
-    pub fn nbt_mut(&mut self) -> &mut NBT {
-        &mut self.root
+    pub fn nbt(&self) -> &NBT {
+        &self.root
     }
 
     pub fn nbt_owned(self) -> NBT {
@@ -204,32 +603,37 @@ impl std::fmt::Display for Error {
             Error::CompressionType(compression) => write!(f, "invalid compression type: {:#02x}", compression),
             Error::Decompress(compression, err) => write!(f, "failed decompression of type {}: {}", compression, err),
             Error::ChunkNBT(err) => write!(f, "failed parsing chunk nbt: {}", err),
+            Error::Location(offset, sectors) => write!(f, "sectors {}..{} lie outside the chunk area of the file", offset, *offset + *sectors as u32),
+            Error::OutsideRegion(x, z) => write!(f, "chunk [{}, {}] lies outside the region being written", x, z),
+            Error::Encode(err) => write!(f, "unable to encode chunk nbt: {}", err),
+            Error::External(name, err) => write!(f, "unable to read external chunk {}: {}", name, err),
             Error::Unimplemented => write!(f, "{}", "not implemented (yet :^)"),
         }
     }
 }
 
 impl Report {
-    pub fn new(err: Error, bytes: &mut Cursor<Vec<u8>>) -> Self {
+    pub fn new(err: Error, bytes: &mut Cursor<&[u8]>) -> Self {
         /* FIXME:
         *     We need to know the size of what we just tried to scan
         *     so we can calculate how far back the actual source of the error lies.
         *     For now, we just assume it was the last byte pulled out from the cursor.
         *
         *                                   vv */
-        let start = bytes.position().clone()-1;
+        let start = bytes.position().saturating_sub(1);
 
         match start {
             0..10 => bytes.seek(SeekFrom::Start(0)),
             _     => bytes.seek(SeekFrom::Current(-10)),
         };
-        let mut blame: [Responsible; 20] = [Responsible{ source: false, addr: 0, byte: 0} ; 20];
-        for responsible in blame.iter_mut() {
-            responsible.addr = bytes.position() as usize;
-            responsible.byte = bytes.read_u8().unwrap();
-            if responsible.addr == start as usize {
-                responsible.source = true;
-            }
+        /* errors near the end of a truncated file have less than 20 bytes of context */
+        let mut blame = Vec::with_capacity(20);
+        for _ in 0..20 {
+            let addr = bytes.position() as usize;
+            let Ok(byte) = bytes.read_u8() else {
+                break;
+            };
+            blame.push(Responsible{ source: addr == start as usize, addr, byte });
         }
         Self { err: err, pos: start, responsible: blame }
     }
@@ -253,11 +657,164 @@ impl std::fmt::Display for Report {
     }
 }
 
+impl std::ops::Deref for Mapped {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.map {
+            Some(map) => map,
+            None      => &[],
+        }
+    }
+}
+
 impl Default for ChunkHeaderPair {
     fn default() -> Self {
         Self {
+            index: 0,
             location: Location { offset: 0, sector: 0 },
             timestamp: Timestamp { entry: 0 },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Compression, Parser, Writer};
+    use crate::nbt::NBT;
+
+    fn chunk(x: i32, z: i32) -> NBT {
+        crate::nbt!{
+            "DataVersion": 3955,
+            "xPos": (x),
+            "zPos": (z),
+            "Status": "minecraft:full",
+            "Heightmaps": { "WORLD_SURFACE": [L; 1l, 2l, 3l] },
+        }
+    }
+
+    /// A region at 1, -1 holding the chunks at `indices`, each saved at `100 + index`.
+    pub(super) fn region(compression: Compression, indices: &[usize]) -> Vec<u8> {
+        let mut writer = Writer::new(1, -1, compression);
+        for &index in indices {
+            let (x, z) = (32 + (index % 32) as i32, -32 + (index / 32) as i32);
+            assert!(writer.chunk(x, z, &chunk(x, z), 100 + index as i32).is_ok());
+        }
+        let written = writer.finish();
+        assert!(written.external.is_empty());
+        written.region
+    }
+
+    /// A region with chunks at 0 to 5 and one fault each in 1 to 5: chunk 1 has an unknown
+    /// compression type, chunk 2 points into the sectors of chunk 0, chunk 3 has a broken zlib
+    /// stream, chunk 4 runs past the end of the file and chunk 5 is dated `now + 1`.
+    pub(super) fn damaged(now: i32) -> Vec<u8> {
+        let mut bytes = region(Compression::Zlib, &[0, 1, 2, 3, 4, 5]);
+        let sector = |bytes: &[u8], index: usize| (u32::from_be_bytes(bytes[index*4..index*4+4].try_into().unwrap()) >> 8) as usize * 4096;
+        let at = sector(&bytes, 1);
+        bytes[at+4] = 9;
+        let location: [u8; 4] = bytes[0..4].try_into().unwrap();
+        bytes[8..12].copy_from_slice(&location);
+        let at = sector(&bytes, 3);
+        bytes[at+5..at+12].fill(0xff);
+        bytes[16..20].copy_from_slice(&(200u32 << 8 | 1).to_be_bytes());
+        bytes[4096+20..4096+24].copy_from_slice(&(now + 1).to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn writer_round_trips_through_parser() {
+        for compression in [Compression::Zlib, Compression::Lz4, Compression::None] {
+            let bytes = region(compression, &[0, 31, 33, 1023]);
+            assert!(bytes.len().is_multiple_of(4096));
+            let Ok((chunks, failures)) = Parser::new(&bytes).parse_tolerant() else {
+                panic!("{:?} region header didn't parse", compression);
+            };
+            assert!(failures.is_empty());
+            let indices: Vec<usize> = chunks.iter().map(|chunk| chunk.index()).collect();
+            assert!(indices == [0, 31, 33, 1023]);
+            for read in chunks {
+                let (x, z) = (32 + (read.index() % 32) as i32, -32 + (read.index() / 32) as i32);
+                assert!(*read.nbt() == chunk(x, z), "{:?} chunk {} differs", compression, read.index());
+            }
+            let entries = Parser::new(&bytes).entries().unwrap_or_default();
+            assert!(entries.iter().all(|entry| entry.timestamp == 100 + entry.index as i32));
+        }
+    }
+
+    #[test]
+    fn chunk_at_reads_one_slot() {
+        let bytes = region(Compression::Zlib, &[5, 700]);
+        let mut parser = Parser::new(&bytes);
+        for _ in 0..2 {
+            assert!(matches!(parser.chunk_at(700), Ok(Some(found)) if *found.nbt() == chunk(32 + 700 % 32, -32 + 700 / 32)));
+            assert!(matches!(parser.chunk_at(5), Ok(Some(found)) if found.index() == 5));
+            assert!(matches!(parser.chunk_at(6), Ok(None)));
+            assert!(matches!(parser.chunk_at(1024), Ok(None)));
+        }
+        let entries = parser.entries().unwrap_or_default();
+        assert!(entries.iter().map(|entry| (entry.index, entry.timestamp)).eq([(5, 105), (700, 800)]));
+    }
+
+    #[test]
+    fn oversized_chunks_round_trip_through_mcc_files() {
+        use crate::nbt::TAGByteArray;
+
+        let dir = std::env::temp_dir().join(format!("rave-mcc-{}", std::process::id()));
+        assert!(std::fs::create_dir_all(&dir).is_ok());
+        let path = dir.join("r.1.-1.mca");
+
+        /* stored uncompressed and patterned so it can't shrink below the 255 sectors a location holds */
+        let body = (0..1_200_000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+        let big = crate::nbt!{ "xPos": 32, "zPos": -32, "Noise": (TAGByteArray{ body }) };
+
+        let mut writer = Writer::new(1, -1, Compression::None);
+        assert!(writer.chunk(32, -32, &big, 1).is_ok());
+        assert!(writer.chunk(33, -32, &chunk(33, -32), 2).is_ok());
+        let written = writer.finish();
+        assert!(written.external.len() == 1 && written.external[0].0 == "c.32.-32.mcc");
+        assert!(written.region.len() == 4 * 4096);
+        assert!(written.save(&path).is_ok());
+
+        let bytes = std::fs::read(&path).unwrap_or_default();
+        let read = Parser::new(&bytes).beside(&path).parse_tolerant();
+        assert!(matches!(&read, Ok((chunks, failures)) if chunks.len() == 2 && failures.is_empty()));
+        assert!(matches!(Parser::new(&bytes).beside(&path).chunk_at(0), Ok(Some(found)) if *found.nbt() == big));
+        /* without the folder the stub can't be followed */
+        assert!(Parser::new(&bytes).chunk_at(0).is_err());
+
+        /* once the chunk fits again its .mcc file goes away */
+        let mut writer = Writer::new(1, -1, Compression::Zlib);
+        let mut parser = Parser::new(&bytes);
+        for entry in parser.entries().unwrap_or_default() {
+            assert!(parser.raw(&entry).is_some_and(|stored| writer.stored(entry.index, entry.timestamp, stored).is_ok()));
+        }
+        assert!(writer.chunk(32, -32, &chunk(32, -32), 3).is_ok());
+        assert!(writer.finish().save(&path).is_ok());
+        assert!(!dir.join("c.32.-32.mcc").exists());
+        let bytes = std::fs::read(&path).unwrap_or_default();
+        assert!(matches!(Parser::new(&bytes).beside(&path).chunk_at(0), Ok(Some(found)) if *found.nbt() == chunk(32, -32)));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn copied_stubs_keep_their_mcc_files() {
+        let mut writer = Writer::new(0, 0, Compression::Zlib);
+        assert!(writer.stored(7, 0, &[0, 0, 0, 1, 2 | super::EXTERNAL]).is_ok());
+        assert!(writer.stored(8, 0, &[0, 0, 0, 1, 2 | super::EXTERNAL]).is_ok());
+        assert!(writer.chunk(8, 0, &chunk(8, 0), 0).is_ok());
+        assert!(writer.chunk(9, 0, &chunk(9, 0), 0).is_ok());
+        let written = writer.finish();
+        /* only the replaced stub's file goes, slots that never had one are left alone */
+        assert!(written.stale == [String::from("c.8.0.mcc")]);
+    }
+
+    #[test]
+    fn chunks_outside_the_region_are_refused() {
+        let mut writer = Writer::new(0, 0, Compression::Zlib);
+        assert!(writer.chunk(32, 0, &chunk(32, 0), 0).is_err());
+        assert!(writer.chunk(-1, 0, &chunk(-1, 0), 0).is_err());
+        assert!(Parser::new(&writer.finish().region).entries().is_ok_and(|entries| entries.is_empty()));
+    }
+}
@@ -0,0 +1,199 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+/* Moving a chunk means rewriting every absolute coordinate it stores. Offsets are given in chunks;
+ * positions inside the chunk are stored in blocks, so they move by 16 times as much. Structure
+ * references are left alone, the game drops starts it can't find. */
+
+use crate::nbt::{NBT, Payload, TAGCompound};
+use crate::structure::paste::uuid;
+
+fn shift_int(compound: &mut TAGCompound, name: &str, by: i32) {
+    if let Some(Payload::Int(int)) = compound.get_mut(name) {
+        *int = int.wrapping_add(by);
+    }
+}
+
+/// Shifts the first and last element of an `[x, z]` or `[x, y, z]` int array.
+fn shift_int_array(compound: &mut TAGCompound, name: &str, dx: i32, dz: i32) {
+    if let Some(Payload::IArray(array)) = compound.get_mut(name) {
+        if let [x, .., z] = array.ints.as_mut_slice() {
+            *x = x.wrapping_add(dx);
+            *z = z.wrapping_add(dz);
+        }
+    }
+}
+
+/// The compounds of the list `name`, if there is one.
+fn compounds_mut<'a>(compound: &'a mut TAGCompound, name: &str) -> impl Iterator<Item = &'a mut TAGCompound> {
+    compound.get_mut(name)
+        .and_then(|list| list.as_list_mut().ok())
+        .into_iter()
+        .flat_map(|list| list.tags.iter_mut())
+        .filter_map(|tag| tag.as_compound_mut().ok())
+}
+
+fn entity(entity: &mut TAGCompound, dx: i32, dz: i32) {
+    if let Some(Payload::List(pos)) = entity.get_mut("Pos") {
+        if let [Payload::Double(x), _, Payload::Double(z)] = pos.tags.as_mut_slice() {
+            *x += dx as f64;
+            *z += dz as f64;
+        }
+    }
+    /* hanging entities like paintings and item frames also store the block they hang on */
+    shift_int(entity, "TileX", dx);
+    shift_int(entity, "TileZ", dz);
+    shift_int_array(entity, "block_pos", dx, dz);
+    for passenger in compounds_mut(entity, "Passengers") {
+        self::entity(passenger, dx, dz);
+    }
+}
+
+fn level(level: &mut TAGCompound, dx: i32, dz: i32) {
+    shift_int(level, "xPos", dx);
+    shift_int(level, "zPos", dz);
+    let (bx, bz) = (dx.wrapping_mul(16), dz.wrapping_mul(16));
+    for list in ["block_entities", "TileEntities", "block_ticks", "fluid_ticks", "TileTicks", "LiquidTicks"] {
+        for tag in compounds_mut(level, list) {
+            shift_int(tag, "x", bx);
+            shift_int(tag, "z", bz);
+        }
+    }
+    for tag in compounds_mut(level, "Entities") {
+        entity(tag, bx, bz);
+    }
+}
+
+/// Moves a chunk of a `region` folder by `dx`, `dz` chunks: its position, block entities, scheduled
+/// ticks, and before 1.17 its entities.
+pub fn terrain(root: &mut NBT, dx: i32, dz: i32) {
+    let Ok(root) = root.payload.as_compound_mut() else {
+        return;
+    };
+    /* before 1.18 everything lives in a `Level` compound */
+    match root.get_mut("Level") {
+        Some(Payload::Compound(compound)) => level(compound, dx, dz),
+        _ => level(root, dx, dz),
+    }
+}
+
+/// Moves a chunk of an `entities` folder by `dx`, `dz` chunks.
+pub fn entities(root: &mut NBT, dx: i32, dz: i32) {
+    let Ok(root) = root.payload.as_compound_mut() else {
+        return;
+    };
+    shift_int_array(root, "Position", dx, dz);
+    for tag in compounds_mut(root, "Entities") {
+        entity(tag, dx.wrapping_mul(16), dz.wrapping_mul(16));
+    }
+}
+
+/// Gives an entity and its passengers new UUIDs, in whichever form it stores them.
+fn renew(entity: &mut TAGCompound) {
+    let ints = uuid().ints;
+    if let Some(Payload::IArray(array)) = entity.get_mut("UUID") {
+        array.ints = ints.clone();
+    }
+    /* before 1.16 UUIDs were two longs */
+    let long = |high: i32, low: i32| ((high as i64) << 32) | (low as u32 as i64);
+    if let Some(Payload::Long(most)) = entity.get_mut("UUIDMost") {
+        *most = long(ints[0], ints[1]);
+    }
+    if let Some(Payload::Long(least)) = entity.get_mut("UUIDLeast") {
+        *least = long(ints[2], ints[3]);
+    }
+    for passenger in compounds_mut(entity, "Passengers") {
+        renew(passenger);
+    }
+}
+
+/// Gives the entities of a `region` or `entities` chunk new UUIDs, so a copy doesn't share them
+/// with the original.
+pub fn uuids(root: &mut NBT) {
+    let Ok(root) = root.payload.as_compound_mut() else {
+        return;
+    };
+    let root = match root.get_mut("Level") {
+        Some(Payload::Compound(level)) => level,
+        _ => root,
+    };
+    for entity in compounds_mut(root, "Entities") {
+        renew(entity);
+    }
+}
+
+/// Moves a chunk of a `poi` folder by `dx`, `dz` chunks.
+pub fn poi(root: &mut NBT, dx: i32, dz: i32) {
+    let Ok(root) = root.payload.as_compound_mut() else {
+        return;
+    };
+    let Some(Payload::Compound(sections)) = root.get_mut("Sections") else {
+        return;
+    };
+    for section in sections.tags.iter_mut().filter_map(|tag| tag.payload.as_compound_mut().ok()) {
+        for record in compounds_mut(section, "Records") {
+            shift_int_array(record, "pos", dx.wrapping_mul(16), dz.wrapping_mul(16));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nbt::TAGCompound;
+    use crate::nbt::access::Lookup;
+
+    #[test]
+    fn terrain_moves_by_whole_chunks() {
+        let mut root = crate::nbt!{ "xPos": 1, "zPos": 2, "block_entities": [{ "id": "minecraft:chest", "x": 16, "y": 64, "z": 40 }] };
+        super::terrain(&mut root, 2, -1);
+        assert!(root == crate::nbt!{ "xPos": 3, "zPos": 1, "block_entities": [{ "id": "minecraft:chest", "x": 48, "y": 64, "z": 24 }] });
+
+        /* before 1.18 the chunk and its entities sit in `Level` */
+        let mut root = crate::nbt!{ "Level": {
+            "xPos": 0, "zPos": 0,
+            "TileEntities": [{ "x": 5, "y": 1, "z": 6 }],
+            "Entities": [{ "Pos": [1.5d, 64.0d, 2.5d], "TileX": 1, "TileY": 64, "TileZ": 2, "Passengers": [{ "Pos": [1.5d, 65.0d, 2.5d] }] }],
+        } };
+        super::terrain(&mut root, 2, -1);
+        assert!(root == crate::nbt!{ "Level": {
+            "xPos": 2, "zPos": -1,
+            "TileEntities": [{ "x": 37, "y": 1, "z": -10 }],
+            "Entities": [{ "Pos": [33.5d, 64.0d, -13.5d], "TileX": 33, "TileY": 64, "TileZ": -14, "Passengers": [{ "Pos": [33.5d, 65.0d, -13.5d] }] }],
+        } });
+    }
+
+    #[test]
+    fn entities_and_poi_move_by_whole_chunks() {
+        let mut root = crate::nbt!{ "Position": [I; 1, 2], "Entities": [{ "Pos": [16.0d, 70.0d, 32.0d], "block_pos": [I; 16, 70, 32] }] };
+        super::entities(&mut root, 2, -1);
+        assert!(root == crate::nbt!{ "Position": [I; 3, 1], "Entities": [{ "Pos": [48.0d, 70.0d, 16.0d], "block_pos": [I; 48, 70, 16] }] });
+
+        let mut root = crate::nbt!{ "Sections": { "4": { "Records": [{ "pos": [I; 16, 70, 32], "type": "minecraft:home" }] } } };
+        super::poi(&mut root, 2, -1);
+        assert!(root == crate::nbt!{ "Sections": { "4": { "Records": [{ "pos": [I; 48, 70, 16], "type": "minecraft:home" }] } } });
+    }
+
+    #[test]
+    fn copies_get_new_uuids() {
+        let mut root = crate::nbt!{ "Entities": [{ "UUID": [I; 1, 2, 3, 4], "Passengers": [{ "UUIDMost": 1l, "UUIDLeast": 2l }] }] };
+        super::uuids(&mut root);
+        let entities = root.payload.as_compound().and_then(|root| root.get_list_of::<TAGCompound>("Entities"));
+        let Ok([entity]) = entities.as_deref() else {
+            panic!("expected one entity");
+        };
+        assert!(entity.get_int_array("UUID").is_ok_and(|uuid| uuid.len() == 4 && uuid != [1, 2, 3, 4] && (uuid[1] as u32 >> 12) & 0xf == 4));
+        let passengers = entity.get_list_of::<TAGCompound>("Passengers");
+        let Ok([passenger]) = passengers.as_deref() else {
+            panic!("expected one passenger");
+        };
+        assert!(passenger.get_long("UUIDMost").is_ok_and(|most| most != 1 && (most >> 12) & 0xf == 4));
+        assert!(passenger.get_long("UUIDLeast").is_ok_and(|least| least != 2));
+
+        /* entities without a UUID don't get one */
+        let mut root = crate::nbt!{ "Level": { "Entities": [{ "id": "minecraft:pig" }] } };
+        let before = root.clone();
+        super::uuids(&mut root);
+        assert!(root == before);
+    }
+}
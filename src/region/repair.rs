@@ -0,0 +1,113 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use std::io::{Seek, SeekFrom};
+
+use super::{assemble, Error, Parser, Report, BOUNDARY, CUSTOM, EXTERNAL, MAX_SECTORS};
+
+/// A region file rebuilt from the chunks of another that could still be read.
+pub struct Repaired {
+    /// The compacted region file.
+    pub region:     Vec<u8>,
+    /// A region file holding the sectors of every dropped chunk, for later inspection.
+    pub quarantine: Vec<u8>,
+    pub kept:       usize,
+    /// Header index and reason for each chunk that was left out.
+    pub dropped:    Vec<(usize, Error)>,
+}
+
+/// Rebuilds a region file: chunks that don't decompress and parse are dropped, and the rest are
+/// copied byte for byte into fresh, consecutive sectors. That also gives chunks that shared
+/// sectors their own copy and returns the free space between chunks to the file system.
+///
+/// Chunks stored in `.mcc` files or with compression this parser can't decode are kept unverified.
+pub fn repair(bytes: &[u8]) -> Result<Repaired, Report> {
+    let mut parser = Parser::new(bytes);
+    let mut pairs = parser.header()?.to_vec();
+    pairs.sort_by_key(|pair| pair.location.offset);
+
+    let available = bytes.len() / BOUNDARY;
+    let (mut kept, mut dropped) = (Vec::new(), Vec::new());
+    for pair in pairs {
+        let (index, timestamp) = (pair.index, pair.timestamp.entry);
+        let (offset, sectors) = (pair.location.offset, pair.location.sector);
+        let (start, end) = (offset as usize, offset as usize + sectors as usize);
+        if start < 2 || end > available {
+            dropped.push((index, timestamp, Error::Location(offset, sectors), None));
+            continue;
+        }
+        let allocated = &bytes[start*BOUNDARY..end*BOUNDARY];
+        parser.bytes.seek(SeekFrom::Start((start*BOUNDARY) as u64)).expect("Invalid chunk offset");
+        let checked = match parser.chunk(pair) {
+            Ok(_) => Ok(()),
+            Err(Error::CompressionType(compression)) if compression & EXTERNAL != 0 || compression == CUSTOM => Ok(()),
+            Err(e) => Err(e),
+        };
+        /* the length field was bounds checked by `chunk`, but may claim more than a location can describe */
+        let stored = match parser.raw(&super::Entry{ index, offset, sectors, timestamp }) {
+            Some(stored) if stored.len() <= MAX_SECTORS*BOUNDARY => stored,
+            _ => {
+                dropped.push((index, timestamp, Error::ChunkLength, Some(allocated)));
+                continue;
+            },
+        };
+        match checked {
+            Ok(())  => kept.push((index, timestamp, stored)),
+            Err(e)  => dropped.push((index, timestamp, e, Some(allocated))),
+        }
+    }
+
+    let quarantined: Vec<_> = dropped.iter()
+        .filter_map(|(index, timestamp, _, allocated)| allocated.map(|allocated| (*index, *timestamp, allocated)))
+        .collect();
+    Ok(Repaired {
+        region:     assemble(kept.iter().copied()),
+        quarantine: if quarantined.is_empty() { Vec::new() } else { assemble(quarantined) },
+        kept:       kept.len(),
+        dropped:    dropped.into_iter().map(|(index, _, e, _)| (index, e)).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::repair;
+    use crate::region::{fsck, Error, Parser};
+
+    #[test]
+    fn rebuilds_a_damaged_region() {
+        let Ok(repaired) = repair(&crate::region::tests::damaged(1000)) else {
+            panic!("the header of the damaged region didn't parse");
+        };
+        /* chunk 2 shares the sectors of chunk 0, so both decode and each gets its own copy */
+        assert!(repaired.kept == 3);
+        let dropped: Vec<usize> = repaired.dropped.iter().map(|(index, _)| *index).collect();
+        assert!(dropped == [1, 3, 4], "dropped {:?}", dropped);
+        assert!(matches!(repaired.dropped[0].1, Error::CompressionType(9)));
+        assert!(matches!(repaired.dropped[1].1, Error::Decompress(..) | Error::ChunkNBT(_)));
+        assert!(matches!(repaired.dropped[2].1, Error::Location(200, 1)));
+
+        assert!(fsck::check(&repaired.region, 2000).is_empty());
+        let Ok((chunks, failures)) = Parser::new(&repaired.region).parse_tolerant() else {
+            panic!("the repaired region didn't parse");
+        };
+        assert!(failures.is_empty());
+        let kept: Vec<usize> = chunks.iter().map(|chunk| chunk.index()).collect();
+        assert!(kept == [0, 2, 5]);
+        assert!(chunks[0].nbt() == chunks[1].nbt());
+
+        /* the chunk past the end of the file had no sectors to keep */
+        let quarantined = Parser::new(&repaired.quarantine).entries().unwrap_or_default();
+        assert!(quarantined.iter().map(|entry| entry.index).eq([1, 3]));
+    }
+
+    #[test]
+    fn intact_regions_come_back_unchanged() {
+        let bytes = crate::region::tests::region(crate::region::Compression::Lz4, &[0, 40, 1000]);
+        let Ok(repaired) = repair(&bytes) else {
+            panic!("the region didn't parse");
+        };
+        assert!(repaired.kept == 3 && repaired.dropped.is_empty() && repaired.quarantine.is_empty());
+        assert!(repaired.region == bytes);
+    }
+}
@@ -0,0 +1,405 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+/* Block states of a section are a palette of states and one index into it per block, packed into
+ * longs with as few bits as the palette needs and never fewer than 4. Since 1.16 an index never
+ * spans two longs. A palette of one state may come without any data. */
+
+use crate::nbt::{NBT, Payload, TAGCompound, TAGList, TAGLArray};
+use crate::nbt::access::Lookup;
+use crate::nbt::build::Builder;
+
+/// Blocks in a section.
+pub const VOLUME: usize = 4096;
+pub const AIR: &str = "minecraft:air";
+
+/// The block states of one 16 block tall section of a chunk, unpacked.
+pub struct Section {
+    /// Section coordinate, the block y of the bottom of the section divided by 16.
+    pub y:       i32,
+    /// Block states, each a `Name` and optional `Properties` compound.
+    pub palette: Vec<TAGCompound>,
+    /// Palette index of every block, indexed `(y * 16 + z) * 16 + x`.
+    pub blocks:  Vec<u32>,
+}
+
+/// The block states of a section as the chunk stores them, read in place.
+pub struct States<'a> {
+    pub palette: &'a TAGList,
+    data:        &'a [i64],
+    bits:        usize,
+}
+
+/// Bits per index for a palette of `len` states.
+pub fn bits(len: usize) -> usize {
+    bits_at_least(len, 4)
+}
+
+/// Bits per value needed to index a palette of `len` entries, never fewer than `min`. Biomes go down to 1.
+pub fn bits_at_least(len: usize, min: usize) -> usize {
+    ((usize::BITS - len.saturating_sub(1).leading_zeros()) as usize).max(min)
+}
+
+/// Value `index` of an array packed `bits` at a time into longs, without values spanning two longs.
+/// Past the end of `data` that's 0.
+pub fn packed(data: &[i64], bits: usize, index: usize) -> usize {
+    let per = 64 / bits;
+    match data.get(index / per) {
+        Some(long) => ((*long as u64 >> ((index % per) * bits)) & ((1 << bits) - 1)) as usize,
+        None       => 0,
+    }
+}
+
+/// The chunk compound holding the sections, `Level` before 1.18.
+pub fn level(root: &NBT) -> Option<&TAGCompound> {
+    let root = root.payload.as_compound().ok()?;
+    Some(root.get_compound("Level").unwrap_or(root))
+}
+
+/// `name[key=value,...]`, the way commands and Sponge schematics write a block state.
+pub fn state_string(state: &TAGCompound) -> String {
+    let mut string = String::from(state.get_str("Name").unwrap_or(AIR));
+    let properties: Vec<String> = match state.get_compound("Properties") {
+        Ok(properties) => properties.iter()
+            .map(|tag| format!("{}={}", tag.name, tag.payload.as_str().unwrap_or_default()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    if !properties.is_empty() {
+        string.push_str(&format!("[{}]", properties.join(",")));
+    }
+    string
+}
+
+/// What `replace` did to one chunk.
+#[derive(Default)]
+pub struct Replaced {
+    /// Sections where renaming palette entries was enough.
+    pub renamed:        usize,
+    /// Sections that had to be repacked, because the new state was already in their palette.
+    pub repacked:       usize,
+    /// Sections that needed a repack but whose blocks couldn't be read.
+    pub unreadable:     usize,
+    pub block_entities: usize,
+}
+
+/// Whether `state` is the block `pattern` names, with every property `pattern` gives.
+pub fn matches(pattern: &TAGCompound, state: &TAGCompound) -> bool {
+    if pattern.get_str("Name").ok() != state.get_str("Name").ok() {
+        return false;
+    }
+    let Ok(properties) = pattern.get_compound("Properties") else {
+        return true;
+    };
+    let Ok(actual) = state.get_compound("Properties") else {
+        return properties.iter().next().is_none();
+    };
+    properties.iter().all(|property| actual.get(&property.name.to_string()) == Some(&property.payload))
+}
+
+/// Whether `a` and `b` are the same block state, whatever order their properties are stored in.
+pub fn same_state(a: &TAGCompound, b: &TAGCompound) -> bool {
+    matches(a, b) && matches(b, a)
+}
+
+/// Drops a chunk's heightmaps and marks its lighting as not done, so the game computes both again
+/// when it loads the chunk after its blocks were changed.
+pub fn relight(level: &mut TAGCompound) {
+    level.insert("Heightmaps", Payload::Compound(Builder::new().build()));
+    level.insert("isLightOn", Payload::Byte(0));
+}
+
+/// The palette of a section, in either layout.
+fn palette_mut(section: &mut TAGCompound) -> Option<&mut TAGList> {
+    if section.get("block_states").is_some() {
+        return section.get_mut("block_states")?.as_compound_mut().ok()?.get_mut("palette")?.as_list_mut().ok();
+    }
+    section.get_mut("Palette")?.as_list_mut().ok()
+}
+
+/// Replaces every block `pattern` matches with `to` in a chunk. Renaming palette entries is enough
+/// unless `to` ends up in a palette twice, then the section is repacked with one entry for it.
+/// Block entities of blocks that became a different block are removed, as they belonged to the old one.
+pub fn replace(root: &mut NBT, pattern: &TAGCompound, to: &TAGCompound) -> Replaced {
+    let mut replaced = Replaced::default();
+    let Ok(root) = root.payload.as_compound_mut() else {
+        return replaced;
+    };
+    let level = match root.get_mut("Level") {
+        Some(Payload::Compound(level)) => level,
+        _ => root,
+    };
+    let renamed = pattern.get_str("Name").ok() != to.get_str("Name").ok();
+    let block_entities: Vec<(i32, i32, i32)> = match level.get_list("block_entities").or_else(|_| level.get_list("TileEntities")) {
+        Ok(list) => list.iter().filter_map(|tag| {
+            let tag = tag.as_compound().ok()?;
+            Some((tag.get_int("x").ok()?, tag.get_int("y").ok()?, tag.get_int("z").ok()?))
+        }).collect(),
+        Err(_) => Vec::new(),
+    };
+    let mut orphaned = Vec::new();
+
+    let name = if level.get("sections").is_some() { "sections" } else { "Sections" };
+    let sections = level.get_mut(name).and_then(|list| list.as_list_mut().ok());
+    for section in sections.into_iter().flat_map(|list| list.tags.iter_mut()).filter_map(|tag| tag.as_compound_mut().ok()) {
+        let Some(palette) = palette_mut(section) else {
+            continue;
+        };
+        let hits: Vec<usize> = palette.iter().enumerate()
+            .filter(|(_, state)| state.as_compound().is_ok_and(|state| matches(pattern, state) && !same_state(state, to)))
+            .map(|(index, _)| index)
+            .collect();
+        if hits.is_empty() {
+            continue;
+        }
+        let existing = palette.iter().position(|state| state.as_compound().is_ok_and(|state| same_state(state, to)));
+
+        /* blocks keep their index when only the palette changes, so old blocks are found before the edit */
+        let parsed = match renamed && !block_entities.is_empty() || hits.len() > 1 || existing.is_some() {
+            true  => Section::parse(section),
+            false => None,
+        };
+        if let Some(parsed) = parsed.as_ref().filter(|_| renamed) {
+            for (x, y, z) in &block_entities {
+                if y >> 4 == parsed.y && hits.contains(&(parsed.blocks[(((y & 15) * 16 + (z & 15)) * 16 + (x & 15)) as usize] as usize)) {
+                    orphaned.push((*x, *y, *z));
+                }
+            }
+        }
+
+        if hits.len() == 1 && existing.is_none() {
+            let palette = palette_mut(section).unwrap();
+            palette.tags[hits[0]] = Payload::Compound(to.clone());
+            replaced.renamed += 1;
+            continue;
+        }
+        let Some(mut parsed) = parsed else {
+            replaced.unreadable += 1;
+            continue;
+        };
+        let target = existing.unwrap_or(hits[0]);
+        parsed.palette[target] = to.clone();
+        for block in parsed.blocks.iter_mut() {
+            if hits.contains(&(*block as usize)) {
+                *block = target as u32;
+            }
+        }
+        parsed.store(section);
+        replaced.repacked += 1;
+    }
+
+    if !orphaned.is_empty() {
+        let name = if level.get("block_entities").is_some() { "block_entities" } else { "TileEntities" };
+        if let Some(list) = level.get_mut(name).and_then(|list| list.as_list_mut().ok()) {
+            let before = list.tags.len();
+            list.tags.retain(|tag| match tag.as_compound() {
+                Ok(tag) => match (tag.get_int("x"), tag.get_int("y"), tag.get_int("z")) {
+                    (Ok(x), Ok(y), Ok(z)) => !orphaned.contains(&(x, y, z)),
+                    _ => true,
+                },
+                Err(_) => true,
+            });
+            replaced.block_entities = before - list.tags.len();
+        }
+    }
+    if replaced.renamed + replaced.repacked > 0 {
+        relight(level);
+    }
+    replaced
+}
+
+/// Parses `name[key=value,...]` back into a block state compound, `None` for malformed properties.
+pub fn state_compound(string: &str) -> Option<TAGCompound> {
+    let (name, properties) = match string.split_once('[') {
+        Some((name, properties)) => (name, Some(properties.strip_suffix(']')?)),
+        None => (string, None),
+    };
+    let mut state = Builder::new().tag("Name", name.trim());
+    if let Some(properties) = properties.filter(|properties| !properties.trim().is_empty()) {
+        let mut compound = Builder::new();
+        for property in properties.split(',') {
+            let (key, value) = property.split_once('=')?;
+            compound = compound.tag(key.trim(), value.trim());
+        }
+        state = state.tag("Properties", compound);
+    }
+    Some(state.build())
+}
+
+/// Packs palette indices `bits` at a time into longs, without indices spanning two longs.
+pub fn pack(blocks: &[u32], bits: usize) -> Vec<i64> {
+    let per = 64 / bits;
+    let mut data = vec![0i64; blocks.len().div_ceil(per)];
+    for (at, block) in blocks.iter().enumerate() {
+        data[at / per] |= ((*block as u64) << ((at % per) * bits)) as i64;
+    }
+    data
+}
+
+impl<'a> States<'a> {
+    /// `None` for compounds without block states this can read, like the spanning indices of
+    /// saves from before 1.16.
+    pub fn read(section: &'a TAGCompound) -> Option<Self> {
+        /* 1.18 moved the palette and its data into a `block_states` compound */
+        let (palette, data) = match section.get_compound("block_states") {
+            Ok(states) => (states.get_list("palette").ok()?, states.get_long_array("data").unwrap_or(&[])),
+            Err(_)     => (section.get_list("Palette").ok()?, section.get_long_array("BlockStates").unwrap_or(&[])),
+        };
+        if palette.tags.is_empty() {
+            return None;
+        }
+        let bits = bits(palette.tags.len());
+        if !data.is_empty() && data.len() != VOLUME.div_ceil(64 / bits) {
+            return None;
+        }
+        Some(Self{ palette, data, bits })
+    }
+
+    /// Palette index of the block at section relative `x`, `y`, `z`.
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        packed(self.data, self.bits, (y * 16 + z) * 16 + x)
+    }
+}
+
+/// Section coordinate of a section compound, the block y of its bottom divided by 16.
+pub fn section_y(section: &TAGCompound) -> Option<i32> {
+    Some(section.get("Y")?.as_integer()? as i32)
+}
+
+impl Section {
+    /// A section of air.
+    pub fn empty(y: i32) -> Self {
+        Self{ y, palette: vec![Builder::new().tag("Name", AIR).build()], blocks: vec![0; VOLUME] }
+    }
+
+    /// Sets the block at section relative `x`, `y`, `z`, adding `state` to the palette if it's new.
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: &TAGCompound) {
+        let index = match self.palette.iter().position(|other| same_state(other, state)) {
+            Some(index) => index,
+            None => {
+                self.palette.push(state.clone());
+                self.palette.len() - 1
+            },
+        };
+        self.blocks[(y * 16 + z) * 16 + x] = index as u32;
+    }
+
+    /// Writes the block states back into `section`, in the layout it already uses, which is the
+    /// one from before 1.18 when it holds a `Palette`. States no block uses are left out.
+    pub fn store(&self, section: &mut TAGCompound) {
+        let mut remap = vec![None; self.palette.len()];
+        let mut palette = Vec::new();
+        let mut blocks = Vec::with_capacity(VOLUME);
+        for block in &self.blocks {
+            let block = (*block as usize).min(self.palette.len() - 1);
+            let index = *remap[block].get_or_insert_with(|| {
+                palette.push(Payload::Compound(self.palette[block].clone()));
+                palette.len() as u32 - 1
+            });
+            blocks.push(index);
+        }
+        let (single, data) = (palette.len() == 1, TAGLArray{ longs: pack(&blocks, bits(palette.len())) });
+        let palette = TAGList::of(palette).unwrap();
+
+        if section.get("Palette").is_some() {
+            /* before 1.18 the data was always there, even for a single state */
+            section.insert("Palette", Payload::List(palette));
+            section.insert("BlockStates", Payload::LArray(data));
+            return;
+        }
+        let mut states = Builder::new().tag("palette", palette);
+        if !single {
+            states = states.tag("data", data);
+        }
+        section.insert("block_states", Payload::Compound(states.build()));
+    }
+
+    /// `None` for compounds without block states this can read (see [`States::read`]) and for
+    /// palettes holding anything but compounds.
+    pub fn parse(section: &TAGCompound) -> Option<Self> {
+        let y = section_y(section)?;
+        let states = States::read(section)?;
+        let palette = states.palette.iter().map(|state| state.as_compound().ok().cloned()).collect::<Option<Vec<_>>>()?;
+        let blocks = (0..VOLUME).map(|at| packed(states.data, states.bits, at) as u32).collect();
+        Some(Self{ y, palette, blocks })
+    }
+
+    /// Every section of a chunk that [`Section::parse`] can read, and how many it couldn't.
+    pub fn all(root: &NBT) -> (Vec<Self>, usize) {
+        let Some(sections) = level(root).and_then(|level| level.get_list("sections").or_else(|_| level.get_list("Sections")).ok()) else {
+            return (Vec::new(), 0);
+        };
+        let (mut parsed, mut unreadable) = (Vec::new(), 0);
+        for section in sections.iter().filter_map(|section| section.as_compound().ok()) {
+            /* sections holding only light have no block states at all */
+            if section.get("block_states").is_none() && section.get("Palette").is_none() {
+                continue;
+            }
+            match Self::parse(section) {
+                Some(section) => parsed.push(section),
+                None => unreadable += 1,
+            }
+        }
+        (parsed, unreadable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replace, same_state, state_compound, Section};
+    use crate::nbt::{NBT, Payload, TAGList};
+    use crate::nbt::access::Lookup;
+    use crate::nbt::build::Builder;
+
+    /// A 1.18 chunk of one section at y 0 holding `states`, placed one after another along x.
+    fn chunk(states: &[&str]) -> NBT {
+        let mut section = Section::empty(0);
+        for (x, state) in states.iter().enumerate() {
+            section.set(x, 0, 0, &state_compound(state).unwrap());
+        }
+        let mut compound = Builder::new().tag("Y", Payload::Byte(0)).build();
+        section.store(&mut compound);
+        crate::nbt!{ "DataVersion": 3955, "sections": (TAGList::of(vec![Payload::Compound(compound)]).unwrap()) }
+    }
+
+    fn section(root: &NBT) -> Section {
+        let Ok(sections) = root.payload.as_compound().and_then(|root| root.get_list("sections")) else {
+            panic!("the chunk lost its sections");
+        };
+        let Some(section) = sections.tags[0].as_compound().ok().and_then(Section::parse) else {
+            panic!("the section didn't read back");
+        };
+        section
+    }
+
+    #[test]
+    fn property_order_does_not_matter() {
+        let a = state_compound("minecraft:oak_stairs[facing=north,half=top]").unwrap();
+        let b = state_compound("minecraft:oak_stairs[half=top,facing=north]").unwrap();
+        assert!(same_state(&a, &b));
+        assert!(!same_state(&a, &state_compound("minecraft:oak_stairs[facing=north]").unwrap()));
+        assert!(!same_state(&a, &state_compound("minecraft:oak_stairs[facing=north,half=bottom]").unwrap()));
+        assert!(same_state(&state_compound("minecraft:stone").unwrap(), &state_compound("minecraft:stone[]").unwrap()));
+    }
+
+    #[test]
+    fn replacing_with_a_reordered_state_reuses_its_entry() {
+        let mut root = chunk(&["minecraft:stone", "minecraft:oak_stairs[half=top,facing=north]"]);
+        let to = state_compound("minecraft:oak_stairs[facing=north,half=top]").unwrap();
+        let replaced = replace(&mut root, &state_compound("minecraft:stone").unwrap(), &to);
+        assert!(replaced.repacked == 1 && replaced.renamed == 0);
+        let section = section(&root);
+        /* air and one entry for the stairs, not two */
+        assert!(section.palette.len() == 2);
+        assert!(section.blocks[0] == section.blocks[1]);
+        assert!(same_state(&section.palette[section.blocks[0] as usize], &to));
+    }
+
+    #[test]
+    fn a_reordered_target_is_not_a_hit() {
+        let mut root = chunk(&["minecraft:oak_stairs[half=top,facing=north]"]);
+        let replaced = replace(&mut root, &state_compound("minecraft:oak_stairs").unwrap(), &state_compound("minecraft:oak_stairs[facing=north,half=top]").unwrap());
+        assert!(replaced.renamed + replaced.repacked == 0);
+    }
+}
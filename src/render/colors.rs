@@ -0,0 +1,190 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+/* Average colors of the top face of common blocks. Blocks missing from the table fall back to a
+ * color guessed from their name, so modded and new blocks still show up as something sensible. */
+
+pub type Color = [u8; 3];
+
+/// Blocks the game colors by biome.
+#[derive(Clone, Copy)]
+pub enum Tint {
+    Grass,
+    Foliage,
+    Water,
+}
+
+/// The tints of one biome, in the order of `Tint`.
+pub struct Biome {
+    pub grass:   Color,
+    pub foliage: Color,
+    pub water:   Color,
+}
+
+const PLAINS: Biome = Biome{ grass: [0x91, 0xbd, 0x59], foliage: [0x77, 0xab, 0x2f], water: [0x3f, 0x76, 0xe4] };
+
+pub fn tint(name: &str) -> Option<Tint> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    match name {
+        "grass_block" | "grass" | "short_grass" | "tall_grass" | "fern" | "large_fern" | "sugar_cane" => Some(Tint::Grass),
+        "oak_leaves" | "jungle_leaves" | "acacia_leaves" | "dark_oak_leaves" | "mangrove_leaves" | "vine" => Some(Tint::Foliage),
+        "water" | "bubble_column" => Some(Tint::Water),
+        _ => None,
+    }
+}
+
+pub fn biome(name: &str) -> Biome {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    match name {
+        "desert" | "savanna" | "savanna_plateau" | "windswept_savanna" | "badlands" | "eroded_badlands" | "wooded_badlands" => {
+            Biome{ grass: [0xbf, 0xb7, 0x55], foliage: [0xae, 0xa4, 0x2a], water: [0x3f, 0x76, 0xe4] }
+        },
+        "swamp" => Biome{ grass: [0x6a, 0x70, 0x39], foliage: [0x6a, 0x70, 0x39], water: [0x61, 0x7b, 0x64] },
+        "mangrove_swamp" => Biome{ grass: [0x6a, 0x70, 0x39], foliage: [0x8d, 0xb1, 0x27], water: [0x3a, 0x7a, 0x6a] },
+        "jungle" | "bamboo_jungle" | "sparse_jungle" => Biome{ grass: [0x59, 0xc9, 0x3c], foliage: [0x30, 0xbb, 0x0b], water: [0x3f, 0x76, 0xe4] },
+        "forest" | "flower_forest" => Biome{ grass: [0x79, 0xc0, 0x5a], foliage: [0x59, 0xae, 0x30], water: [0x3f, 0x76, 0xe4] },
+        "birch_forest" | "old_growth_birch_forest" => Biome{ grass: [0x88, 0xbb, 0x67], foliage: [0x6b, 0xa9, 0x41], water: [0x3f, 0x76, 0xe4] },
+        "dark_forest" => Biome{ grass: [0x50, 0x7a, 0x32], foliage: [0x59, 0xae, 0x30], water: [0x3f, 0x76, 0xe4] },
+        "taiga" | "old_growth_pine_taiga" | "old_growth_spruce_taiga" => {
+            Biome{ grass: [0x86, 0xb7, 0x83], foliage: [0x68, 0xa4, 0x64], water: [0x3f, 0x76, 0xe4] }
+        },
+        "snowy_taiga" | "snowy_plains" | "ice_spikes" | "snowy_slopes" | "frozen_peaks" | "jagged_peaks" | "grove" => {
+            Biome{ grass: [0x80, 0xb4, 0x97], foliage: [0x60, 0xa1, 0x7b], water: [0x3d, 0x57, 0xd6] }
+        },
+        "windswept_hills" | "windswept_gravelly_hills" | "windswept_forest" | "stony_shore" => {
+            Biome{ grass: [0x8a, 0xb6, 0x89], foliage: [0x6d, 0xa3, 0x6b], water: [0x3f, 0x76, 0xe4] }
+        },
+        "meadow" | "cherry_grove" => Biome{ grass: [0x83, 0xbb, 0x6d], foliage: [0x63, 0xa9, 0x48], water: [0x0e, 0x4e, 0xcf] },
+        "warm_ocean" => Biome{ water: [0x43, 0xd5, 0xee], ..PLAINS },
+        "lukewarm_ocean" | "deep_lukewarm_ocean" => Biome{ water: [0x45, 0xad, 0xf2], ..PLAINS },
+        "cold_ocean" | "deep_cold_ocean" => Biome{ water: [0x3d, 0x57, 0xd6], ..PLAINS },
+        "frozen_ocean" | "deep_frozen_ocean" | "frozen_river" => Biome{ water: [0x39, 0x38, 0xc9], ..PLAINS },
+        "mushroom_fields" => Biome{ grass: [0x55, 0xc9, 0x3f], foliage: [0x2b, 0xbb, 0x0f], water: [0x3f, 0x76, 0xe4] },
+        _ => PLAINS,
+    }
+}
+
+/// The tint of `tint` in plains, used when biomes aren't shown.
+pub fn plains(tint: Tint) -> Color {
+    biome("plains").get(tint)
+}
+
+impl Biome {
+    pub fn get(&self, tint: Tint) -> Color {
+        match tint {
+            Tint::Grass   => self.grass,
+            Tint::Foliage => self.foliage,
+            Tint::Water   => self.water,
+        }
+    }
+}
+
+/// The color of a block seen from above, `None` for blocks that can't be seen like air and barriers.
+pub fn block(name: &str) -> Option<Color> {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    Some(match name {
+        "air" | "cave_air" | "void_air" | "barrier" | "light" | "structure_void" => return None,
+        "stone" | "stone_slab" | "stone_stairs" | "stone_bricks" | "cobblestone" | "andesite" => [0x7d, 0x7d, 0x7d],
+        "granite" => [0x95, 0x67, 0x55],
+        "diorite" | "calcite" => [0xbc, 0xbc, 0xbc],
+        "deepslate" | "cobbled_deepslate" => [0x50, 0x50, 0x52],
+        "tuff" => [0x6c, 0x6d, 0x66],
+        "bedrock" => [0x55, 0x55, 0x55],
+        "dirt" | "coarse_dirt" | "rooted_dirt" | "dirt_path" | "farmland" => [0x86, 0x60, 0x43],
+        "podzol" => [0x5b, 0x3f, 0x18],
+        "mycelium" => [0x6f, 0x63, 0x69],
+        "mud" => [0x3c, 0x39, 0x3d],
+        "clay" => [0xa0, 0xa6, 0xb3],
+        "gravel" => [0x83, 0x7f, 0x7e],
+        "sand" | "sandstone" | "smooth_sandstone" | "cut_sandstone" => [0xdb, 0xd3, 0xa0],
+        "red_sand" | "red_sandstone" => [0xbe, 0x66, 0x21],
+        "terracotta" => [0x98, 0x5e, 0x43],
+        "snow" | "snow_block" | "powder_snow" => [0xf9, 0xfe, 0xfe],
+        "ice" | "frosted_ice" => [0x91, 0xb7, 0xfd],
+        "packed_ice" => [0x8d, 0xb4, 0xfa],
+        "blue_ice" => [0x74, 0xa7, 0xfd],
+        "lava" => [0xcf, 0x5b, 0x14],
+        "obsidian" | "crying_obsidian" => [0x14, 0x12, 0x1d],
+        "magma_block" => [0x8e, 0x3f, 0x1f],
+        "netherrack" => [0x61, 0x26, 0x26],
+        "nether_bricks" => [0x2c, 0x15, 0x1a],
+        "soul_sand" | "soul_soil" => [0x51, 0x3e, 0x32],
+        "basalt" | "polished_basalt" => [0x49, 0x49, 0x4e],
+        "blackstone" => [0x2a, 0x23, 0x28],
+        "glowstone" | "shroomlight" => [0xf0, 0xb0, 0x5a],
+        "crimson_nylium" | "nether_wart_block" => [0x83, 0x1f, 0x1f],
+        "warped_nylium" | "warped_wart_block" => [0x2b, 0x72, 0x65],
+        "end_stone" | "end_stone_bricks" => [0xdb, 0xde, 0x9e],
+        "purpur_block" | "purpur_pillar" => [0xa9, 0x7d, 0xa9],
+        "birch_leaves" => [0x80, 0xa7, 0x55],
+        "spruce_leaves" => [0x61, 0x99, 0x61],
+        "cherry_leaves" => [0xe5, 0xad, 0xc2],
+        "azalea_leaves" | "azalea" => [0x5a, 0x73, 0x2c],
+        "flowering_azalea_leaves" | "flowering_azalea" => [0x64, 0x6f, 0x3d],
+        "moss_block" | "moss_carpet" => [0x59, 0x6d, 0x2d],
+        "lily_pad" => [0x20, 0x80, 0x30],
+        "cactus" => [0x55, 0x7f, 0x2b],
+        "pumpkin" | "carved_pumpkin" => [0xc6, 0x76, 0x18],
+        "melon" => [0x6f, 0x91, 0x1e],
+        "hay_block" => [0xa6, 0x88, 0x0c],
+        "bricks" => [0x96, 0x61, 0x53],
+        "glass" => [0xaf, 0xd5, 0xdb],
+        "bookshelf" => [0x75, 0x5e, 0x3b],
+        "brown_mushroom_block" => [0x95, 0x6f, 0x51],
+        "red_mushroom_block" => [0xc8, 0x2e, 0x2d],
+        "dripstone_block" | "pointed_dripstone" => [0x86, 0x6b, 0x5c],
+        "amethyst_block" | "budding_amethyst" => [0x85, 0x61, 0xbf],
+        "sculk" => [0x0d, 0x1e, 0x24],
+        "prismarine" => [0x63, 0x9c, 0x97],
+        "sea_lantern" => [0xac, 0xc8, 0xbe],
+        "kelp" | "kelp_plant" | "seagrass" | "tall_seagrass" => [0x36, 0x7a, 0x2a],
+        "bamboo" => [0x5e, 0x8c, 0x1c],
+        "dandelion" | "sunflower" => [0xe8, 0xd0, 0x3a],
+        "poppy" | "rose_bush" | "red_tulip" => [0xc0, 0x2a, 0x20],
+        "torch" | "wall_torch" => [0xff, 0xd8, 0x60],
+        name => return Some(guess(name)),
+    })
+}
+
+/* wood, wool and other families differ mostly by a prefix, so they are matched by name */
+fn guess(name: &str) -> Color {
+    const WOODS: [(&str, Color); 11] = [
+        ("oak", [0xa2, 0x83, 0x4f]), ("spruce", [0x72, 0x54, 0x30]), ("birch", [0xc0, 0xaf, 0x79]),
+        ("jungle", [0xa0, 0x73, 0x50]), ("acacia", [0xa8, 0x5a, 0x32]), ("dark_oak", [0x42, 0x2b, 0x14]),
+        ("mangrove", [0x75, 0x36, 0x30]), ("cherry", [0xe2, 0xb2, 0xac]), ("bamboo", [0xc2, 0xaf, 0x50]),
+        ("crimson", [0x65, 0x30, 0x46]), ("warped", [0x2b, 0x68, 0x63]),
+    ];
+    const DYES: [(&str, Color); 16] = [
+        ("white", [0xe9, 0xec, 0xec]), ("light_gray", [0x8e, 0x8e, 0x86]), ("gray", [0x3e, 0x44, 0x47]),
+        ("black", [0x14, 0x15, 0x19]), ("brown", [0x72, 0x47, 0x28]), ("red", [0xa1, 0x27, 0x22]),
+        ("orange", [0xf0, 0x76, 0x13]), ("yellow", [0xf8, 0xc5, 0x27]), ("lime", [0x70, 0xb9, 0x19]),
+        ("green", [0x54, 0x6d, 0x1b]), ("cyan", [0x15, 0x89, 0x91]), ("light_blue", [0x3a, 0xaf, 0xd9]),
+        ("blue", [0x35, 0x39, 0x9d]), ("purple", [0x79, 0x2a, 0xac]), ("magenta", [0xbd, 0x44, 0xb3]),
+        ("pink", [0xed, 0x8d, 0xac]),
+    ];
+    /* "light_gray" has to be tried before "gray", and "dark_oak" before "oak" */
+    let prefixed = |table: &[(&str, Color)]| {
+        table.iter()
+            .filter(|(prefix, _)| name.starts_with(prefix) || name.starts_with(&format!("stripped_{}", prefix)))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, color)| *color)
+    };
+    if let Some(color) = prefixed(&DYES) {
+        return color;
+    }
+    if let Some(color) = prefixed(&WOODS) {
+        return color;
+    }
+    match name {
+        _ if name.contains("leaves") => [0x4a, 0x7a, 0x2a],
+        _ if name.contains("copper") => [0xb4, 0x6a, 0x4f],
+        _ if name.contains("deepslate") => [0x50, 0x50, 0x52],
+        _ if name.contains("blackstone") => [0x2a, 0x23, 0x28],
+        _ if name.contains("quartz") => [0xec, 0xe6, 0xdf],
+        _ if name.contains("sandstone") => [0xdb, 0xd3, 0xa0],
+        _ if name.contains("brick") || name.contains("stone") => [0x7d, 0x7d, 0x7d],
+        _ if name.contains("ore") => [0x7d, 0x7d, 0x7d],
+        _ => [0x8f, 0x8f, 0x8f],
+    }
+}
@@ -0,0 +1,101 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use super::colors::Color;
+use super::png;
+
+/* cold to hot, evenly spaced */
+const GRADIENT: [Color; 5] = [[0x30, 0x12, 0x3b], [0x28, 0x8c, 0xe0], [0x3c, 0xd6, 0x5c], [0xf5, 0xc4, 0x1e], [0xd2, 0x2a, 0x0c]];
+
+fn gradient(t: f64) -> Color {
+    let scaled = t.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f64;
+    let (low, high) = (scaled.floor() as usize, scaled.ceil() as usize);
+    let between = scaled - low as f64;
+    [0, 1, 2].map(|i| (GRADIENT[low][i] as f64 * (1.0 - between) + GRADIENT[high][i] as f64 * between) as u8)
+}
+
+/// The chunks in the corners of the rectangle holding every value, `None` without values.
+pub fn bounds(values: &[((i32, i32), i64)]) -> Option<((i32, i32), (i32, i32))> {
+    values.iter().map(|(at, _)| *at).fold(None, |bounds, (x, z)| match bounds {
+        None => Some(((x, z), (x, z))),
+        Some((min, max)) => Some(((x.min(min.0), z.min(min.1)), (x.max(max.0), z.max(max.1)))),
+    })
+}
+
+/// Draws one pixel per chunk of `bounds`, north up, colored from the smallest value to the largest.
+/// With `log`, values are spread on a logarithmic scale, for counts where most chunks have few and
+/// some many. Chunks without a value stay transparent.
+pub fn png(values: &[((i32, i32), i64)], ((min_x, min_z), (max_x, max_z)): ((i32, i32), (i32, i32)), log: bool) -> Vec<u8> {
+    let (width, height) = ((max_x as i64 - min_x as i64 + 1) as usize, (max_z as i64 - min_z as i64 + 1) as usize);
+
+    let scale = |value: i64| match log {
+        true  => (value.max(0) as f64).ln_1p(),
+        false => value as f64,
+    };
+    let low = values.iter().map(|(_, value)| scale(*value)).fold(f64::INFINITY, f64::min);
+    let high = values.iter().map(|(_, value)| scale(*value)).fold(f64::NEG_INFINITY, f64::max);
+    let range = if high > low { high - low } else { 1.0 };
+
+    let mut pixels = vec![[0; 4]; width * height];
+    for ((x, z), value) in values {
+        let [r, g, b] = gradient((scale(*value) - low) / range);
+        pixels[(z - min_z) as usize * width + (x - min_x) as usize] = [r, g, b, 0xff];
+    }
+    png::encode(width as u32, height as u32, &pixels)
+}
+
+/// One `x,z,value` row per chunk, under a header naming the value.
+pub fn csv(values: &[((i32, i32), i64)], name: &str) -> String {
+    let mut csv = format!("x,z,{}\n", name);
+    for ((x, z), value) in values {
+        csv.push_str(&format!("{},{},{}\n", x, z, value));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use super::GRADIENT;
+
+    /// The filtered rows of an encoded image, each a filter byte and its RGBA pixels.
+    fn rows(png: &[u8]) -> Vec<u8> {
+        let length = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        let mut rows = Vec::new();
+        assert!(flate2::read::ZlibDecoder::new(&png[41..41+length]).read_to_end(&mut rows).is_ok());
+        rows
+    }
+
+    #[test]
+    fn csv_has_a_row_per_chunk() {
+        let values = [((0, 0), 5), ((-3, 12), 0), ((7, -1), 1_000_000)];
+        assert!(super::csv(&values, "inhabited_time") == "x,z,inhabited_time\n0,0,5\n-3,12,0\n7,-1,1000000\n");
+        assert!(super::csv(&[], "entities") == "x,z,entities\n");
+    }
+
+    #[test]
+    fn bounds_span_negative_chunks() {
+        assert!(super::bounds(&[]).is_none());
+        assert!(super::bounds(&[((-5, 3), 1)]) == Some(((-5, 3), (-5, 3))));
+        assert!(super::bounds(&[((-5, 3), 1), ((2, -8), 1), ((-1, -1), 1)]) == Some(((-5, -8), (2, 3))));
+    }
+
+    #[test]
+    fn gradient_ends_at_the_smallest_and_largest_values() {
+        /* a 3 x 1 image: the largest value, a gap, then the smallest */
+        let values = [((-2, 4), 900), ((0, 4), -100)];
+        for log in [false, true] {
+            let png = super::png(&values, ((-2, 4), (0, 4)), log);
+            assert!(png[16..24] == [0, 0, 0, 3, 0, 0, 0, 1]);
+            let rows = rows(&png);
+            let (hot, cold) = (GRADIENT[GRADIENT.len() - 1], GRADIENT[0]);
+            assert!(rows[1..5] == [hot[0], hot[1], hot[2], 0xff]);
+            assert!(rows[5..9] == [0, 0, 0, 0]);
+            assert!(rows[9..13] == [cold[0], cold[1], cold[2], 0xff]);
+        }
+        /* a single value has nothing to compare with and takes the cold end */
+        let rows = rows(&super::png(&[((0, 0), 7)], ((0, 0), (0, 0)), false));
+        assert!(rows[1..4] == GRADIENT[0]);
+    }
+}
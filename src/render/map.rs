@@ -0,0 +1,224 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use crate::nbt::{NBT, TAGCompound};
+use crate::nbt::access::{Error, Lookup};
+use super::colors::Color;
+use super::png;
+
+/// Pixels along each side of a map.
+pub const SIZE: usize = 128;
+
+/* Minecraft's map colors, by id. Id 0 is transparent, the rest are each drawn in four shades. */
+const PALETTE: [Color; 62] = [
+    [0x00, 0x00, 0x00], [0x7f, 0xb2, 0x38], [0xf7, 0xe9, 0xa3], [0xc7, 0xc7, 0xc7], [0xff, 0x00, 0x00], [0xa0, 0xa0, 0xff],
+    [0xa7, 0xa7, 0xa7], [0x00, 0x7c, 0x00], [0xff, 0xff, 0xff], [0xa4, 0xa8, 0xb8], [0x97, 0x6d, 0x4d], [0x70, 0x70, 0x70],
+    [0x40, 0x40, 0xff], [0x8f, 0x77, 0x48], [0xff, 0xfc, 0xf5], [0xd8, 0x7f, 0x33], [0xb2, 0x4c, 0xd8], [0x66, 0x99, 0xd8],
+    [0xe5, 0xe5, 0x33], [0x7f, 0xcc, 0x19], [0xf2, 0x7f, 0xa5], [0x4c, 0x4c, 0x4c], [0x99, 0x99, 0x99], [0x4c, 0x7f, 0x99],
+    [0x7f, 0x3f, 0xb2], [0x33, 0x4c, 0xb2], [0x66, 0x4c, 0x33], [0x66, 0x7f, 0x33], [0x99, 0x33, 0x33], [0x19, 0x19, 0x19],
+    [0xfa, 0xee, 0x4d], [0x5c, 0xdb, 0xd5], [0x4a, 0x80, 0xff], [0x00, 0xd9, 0x3a], [0x81, 0x56, 0x31], [0x70, 0x02, 0x00],
+    [0xd1, 0xb1, 0xa1], [0x9f, 0x52, 0x24], [0x95, 0x57, 0x6c], [0x70, 0x6c, 0x8a], [0xba, 0x85, 0x24], [0x67, 0x75, 0x35],
+    [0xa0, 0x4d, 0x4e], [0x39, 0x29, 0x23], [0x87, 0x6b, 0x62], [0x57, 0x5c, 0x5c], [0x7a, 0x49, 0x58], [0x4c, 0x3e, 0x5c],
+    [0x4c, 0x32, 0x23], [0x4c, 0x52, 0x2a], [0x8e, 0x3c, 0x2e], [0x25, 0x16, 0x10], [0xbd, 0x30, 0x31], [0x94, 0x3f, 0x61],
+    [0x5c, 0x19, 0x1d], [0x16, 0x7e, 0x86], [0x3a, 0x8e, 0x8c], [0x56, 0x2c, 0x3e], [0x14, 0xb4, 0x85], [0x64, 0x64, 0x64],
+    [0xd8, 0xaf, 0x93], [0x7f, 0xa7, 0x96],
+];
+/* brightness of each shade, out of 255 */
+const SHADES: [u32; 4] = [180, 220, 255, 135];
+
+/// A banner marked on a map.
+pub struct Banner {
+    pub pos:   (i32, i32, i32),
+    pub color: String,
+    /// The custom name as stored, a JSON text component before 1.20.5.
+    pub name:  Option<String>,
+}
+
+/// An in-game map from `data/map_<id>.dat`.
+pub struct Map {
+    pub id:        i32,
+    /// Each pixel covers `2^scale` blocks along each side.
+    pub scale:     u8,
+    pub dimension: String,
+    /// The block at the middle of the map.
+    pub center:    (i32, i32),
+    pub locked:    bool,
+    pub banners:   Vec<Banner>,
+    colors:        Vec<u8>,
+}
+
+/// RGBA of a map color byte, transparent for colors without a base color.
+fn pixel(byte: u8) -> [u8; 4] {
+    let (base, shade) = (byte as usize / 4, byte as usize % 4);
+    match PALETTE.get(base) {
+        Some(color) if base != 0 => {
+            let [r, g, b] = color.map(|channel| (channel as u32 * SHADES[shade] / 255) as u8);
+            [r, g, b, 0xff]
+        },
+        _ => [0; 4],
+    }
+}
+
+fn banner(banner: &TAGCompound) -> Option<Banner> {
+    /* 1.20.5 replaced the `Pos` compound with an int array and lowercased the names */
+    let pos = match (banner.get_compound("Pos"), banner.get_int_array("pos")) {
+        (Ok(pos), _) => (pos.get_int("X").ok()?, pos.get_int("Y").ok()?, pos.get_int("Z").ok()?),
+        (_, Ok([x, y, z])) => (*x, *y, *z),
+        _ => return None,
+    };
+    let color = banner.get_str("Color").or_else(|_| banner.get_str("color")).unwrap_or("white");
+    let name = banner.get_str("Name").or_else(|_| banner.get_str("name")).ok();
+    Some(Banner{ pos, color: String::from(color), name: name.map(String::from) })
+}
+
+impl Map {
+    /// Reads a map file's `data` compound, `id` being the number in its file name.
+    pub fn parse(id: i32, root: &NBT) -> Result<Self, Error> {
+        let data = root.payload.as_compound()?.get_compound("data")?;
+        /* before 1.16 dimensions were stored as a number */
+        let dimension = match data.get_str("dimension") {
+            Ok(name) => String::from(name),
+            Err(_) => match data.get("dimension").and_then(|dimension| dimension.as_integer()) {
+                Some(-1) => String::from("minecraft:the_nether"),
+                Some(1)  => String::from("minecraft:the_end"),
+                _        => String::from("minecraft:overworld"),
+            },
+        };
+        let banners = data.get_list_of::<TAGCompound>("banners")
+            .map(|banners| banners.into_iter().filter_map(banner).collect())
+            .unwrap_or_default();
+        Ok(Self {
+            id,
+            scale:   data.get_byte("scale").unwrap_or(0).min(4),
+            dimension,
+            center:  (data.get_int("xCenter")?, data.get_int("zCenter")?),
+            locked:  data.get_byte("locked").unwrap_or(0) != 0,
+            banners,
+            colors:  data.get_byte_array("colors")?.to_vec(),
+        })
+    }
+
+    /// Blocks covered along each side.
+    pub fn span(&self) -> i32 {
+        (SIZE as i32) << self.scale
+    }
+
+    /// The block at the top left corner.
+    pub fn corner(&self) -> (i32, i32) {
+        (self.center.0 - self.span() / 2, self.center.1 - self.span() / 2)
+    }
+
+    /// Rows of RGBA pixels from the top left, north up. Maps with a short `colors` array are transparent where it ends.
+    pub fn pixels(&self) -> Vec<[u8; 4]> {
+        (0..SIZE * SIZE).map(|at| self.colors.get(at).map_or([0; 4], |byte| pixel(*byte))).collect()
+    }
+
+    pub fn png(&self) -> Vec<u8> {
+        png::encode(SIZE as u32, SIZE as u32, &self.pixels())
+    }
+}
+
+/// Top left pixel of each map in an image of all of them, and the width and height of that image.
+pub type Layout = (Vec<(usize, usize)>, (usize, usize));
+
+/// Where `maps` go in one image, pixels covering `2^scale` blocks of the first map's scale; `None` without maps.
+pub fn layout(maps: &[Map]) -> Option<Layout> {
+    let scale = maps.first()?.scale;
+    let corners: Vec<(i64, i64)> = maps.iter().map(|map| ((map.corner().0 >> scale) as i64, (map.corner().1 >> scale) as i64)).collect();
+    let (left, top) = (corners.iter().map(|corner| corner.0).min()?, corners.iter().map(|corner| corner.1).min()?);
+    let (right, bottom) = (corners.iter().map(|corner| corner.0).max()?, corners.iter().map(|corner| corner.1).max()?);
+    let offsets = corners.iter().map(|corner| ((corner.0 - left) as usize, (corner.1 - top) as usize)).collect();
+    Some((offsets, ((right - left) as usize + SIZE, (bottom - top) as usize + SIZE)))
+}
+
+/// Draws `maps` into one image laid out by [`layout`], later maps over earlier ones. The maps must
+/// share a scale; `None` without maps.
+pub fn stitch(maps: &[Map]) -> Option<Vec<u8>> {
+    let (offsets, (width, height)) = layout(maps)?;
+    let mut pixels = vec![[0; 4]; width * height];
+    for (map, (x, z)) in maps.iter().zip(offsets) {
+        for (at, pixel) in map.pixels().into_iter().enumerate() {
+            if pixel[3] != 0 {
+                pixels[(z + at / SIZE) * width + x + at % SIZE] = pixel;
+            }
+        }
+    }
+    Some(png::encode(width as u32, height as u32, &pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use crate::nbt::{NBT, TAGByteArray};
+    use super::{Map, SIZE};
+
+    fn map(center: (i32, i32), colors: Vec<u8>) -> NBT {
+        crate::nbt!{ "DataVersion": 3953, "data": {
+            "xCenter": (center.0), "zCenter": (center.1), "scale": (0i8), "dimension": "minecraft:overworld",
+            "locked": 1b, "colors": (TAGByteArray{ body: colors }),
+            "banners": [{ "pos": [I; 4, 70, 6], "color": "blue", "name": "Home" }],
+        } }
+    }
+
+    #[test]
+    fn map_files_decode() {
+        let Ok(parsed) = Map::parse(3, &map((64, -64), vec![0; SIZE * SIZE])) else {
+            panic!("map didn't parse");
+        };
+        assert!(parsed.id == 3 && parsed.scale == 0 && parsed.locked && parsed.dimension == "minecraft:overworld");
+        assert!(parsed.center == (64, -64) && parsed.span() == 128 && parsed.corner() == (0, -128));
+        assert!(matches!(parsed.banners.as_slice(), [banner] if banner.pos == (4, 70, 6) && banner.color == "blue" && banner.name.as_deref() == Some("Home")));
+
+        /* before 1.16 the dimension was a number, and before 1.20.5 banners had a `Pos` compound */
+        let old = crate::nbt!{ "data": {
+            "xCenter": 0, "zCenter": 0, "scale": 2b, "dimension": -1, "colors": (TAGByteArray{ body: Vec::new() }),
+            "banners": [{ "Pos": { "X": 1, "Y": 2, "Z": 3 }, "Color": "red" }, { "Color": "lost" }],
+        } };
+        let Ok(parsed) = Map::parse(0, &old) else {
+            panic!("old map didn't parse");
+        };
+        assert!(parsed.dimension == "minecraft:the_nether" && parsed.span() == 512 && !parsed.locked);
+        assert!(matches!(parsed.banners.as_slice(), [banner] if banner.pos == (1, 2, 3) && banner.color == "red" && banner.name.is_none()));
+        assert!(parsed.pixels().iter().all(|pixel| *pixel == [0; 4]));
+
+        assert!(Map::parse(0, &crate::nbt!{ "data": { "xCenter": 0 } }).is_err());
+    }
+
+    #[test]
+    fn color_bytes_are_shaded_palette_entries() {
+        assert!(super::pixel(0) == [0; 4] && super::pixel(3) == [0; 4]);
+        /* grass, 0x7fb238, in each of its four shades */
+        assert!(super::pixel(4) == [0x59, 0x7d, 0x27, 0xff]);
+        assert!(super::pixel(5) == [0x6d, 0x99, 0x30, 0xff]);
+        assert!(super::pixel(6) == [0x7f, 0xb2, 0x38, 0xff]);
+        assert!(super::pixel(7) == [0x43, 0x5e, 0x1d, 0xff]);
+        /* past the end of the palette */
+        assert!(super::pixel(255) == [0; 4]);
+    }
+
+    #[test]
+    fn maps_are_stitched_side_by_side() {
+        let (mut west, mut east) = (vec![0; SIZE * SIZE], vec![0; SIZE * SIZE]);
+        west[0] = 6;
+        east[0] = 34;
+        let maps: Vec<Map> = [map((64, 64), west), map((192, 64), east)].iter()
+            .filter_map(|root| Map::parse(0, root).ok())
+            .collect();
+        assert!(super::layout(&maps).is_some_and(|(offsets, size)| offsets == [(0, 0), (128, 0)] && size == (256, 128)));
+
+        let Some(png) = super::stitch(&maps) else {
+            panic!("expected an image");
+        };
+        assert!(png[16..24] == [0, 0, 1, 0, 0, 0, 0, 128]);
+        let length = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        let mut rows = Vec::new();
+        assert!(flate2::read::ZlibDecoder::new(&png[41..41+length]).read_to_end(&mut rows).is_ok());
+        let at = |x: usize| &rows[1 + x * 4..1 + x * 4 + 4];
+        assert!(at(0) == [0x7f, 0xb2, 0x38, 0xff]);
+        assert!(at(1) == [0; 4]);
+        /* 34 is snow, 0xffffff, at full brightness */
+        assert!(at(128) == [0xff, 0xff, 0xff, 0xff]);
+        assert!(super::stitch(&[]).is_none());
+    }
+}
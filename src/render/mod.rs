@@ -0,0 +1,260 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+pub mod colors;
+pub mod heatmap;
+pub mod map;
+mod png;
+
+use std::collections::BTreeMap;
+
+use crate::nbt::{NBT, TAGCompound};
+use crate::nbt::access::Lookup;
+use crate::region::section::{self, States, AIR};
+use colors::{Color, Tint};
+
+/* pixels nothing was drawn on */
+const UNDRAWN: i32 = i32::MIN;
+
+/// The top of each of a chunk's 16 x 16 columns, indexed `x + z * 16`.
+pub struct Surface {
+    pub colors:  [Option<Color>; 256],
+    pub heights: [i32; 256],
+}
+
+/// An image of a rectangle of chunks, one pixel per block, north up.
+pub struct Canvas {
+    /// Chunk coordinates of the top left chunk.
+    origin:  (i32, i32),
+    width:   usize,
+    height:  usize,
+    pixels:  Vec<[u8; 4]>,
+    heights: Vec<i32>,
+}
+
+/// The block states and biomes of one 16 block tall section of a chunk.
+struct Section<'a> {
+    states: States<'a>,
+    /// Block name of each palette entry.
+    names:  Vec<&'a str>,
+    biomes: Vec<&'a str>,
+    cells:  &'a [i64],
+}
+
+impl<'a> Section<'a> {
+    /// `None` for sections this renderer can't read, which are then treated as empty.
+    fn parse(section: &'a TAGCompound) -> Option<(i32, Self)> {
+        let y = section::section_y(section)?;
+        let states = States::read(section)?;
+        let names = states.palette.iter()
+            .map(|state| state.as_compound().and_then(|state| state.get_str("Name")).unwrap_or(AIR))
+            .collect();
+        let (biomes, cells) = match section.get_compound("biomes") {
+            Ok(biomes) => (
+                biomes.get_list("palette").map(|palette| palette.iter().filter_map(|biome| biome.as_str().ok()).collect()).unwrap_or_default(),
+                biomes.get_long_array("data").unwrap_or(&[]),
+            ),
+            Err(_) => (Vec::new(), &[][..]),
+        };
+        Some((y, Self{ states, names, biomes, cells }))
+    }
+
+    fn block(&self, x: usize, y: usize, z: usize) -> &'a str {
+        self.names.get(self.states.index(x, y, z)).copied().unwrap_or(AIR)
+    }
+
+    /// Biomes are stored per 4 x 4 x 4 cell.
+    fn biome(&self, x: usize, y: usize, z: usize) -> Option<&'a str> {
+        if self.biomes.len() <= 1 {
+            return self.biomes.first().copied();
+        }
+        let index = section::packed(self.cells, section::bits_at_least(self.biomes.len(), 1), (y / 4 * 4 + z / 4) * 4 + x / 4);
+        self.biomes.get(index).copied()
+    }
+}
+
+fn blend(a: Color, b: Color, amount: f32) -> Color {
+    [0, 1, 2].map(|i| (a[i] as f32 * amount + b[i] as f32 * (1.0 - amount)) as u8)
+}
+
+fn is_water(name: &str) -> bool {
+    matches!(name, "minecraft:water" | "minecraft:bubble_column" | "minecraft:seagrass" | "minecraft:tall_seagrass" | "minecraft:kelp" | "minecraft:kelp_plant")
+}
+
+/// Finds the topmost visible block of every column of a chunk and colors it, tinting grass, leaves
+/// and water by the biome they're in when `biome_tint` is set and like plains otherwise. Water is
+/// mixed with the floor below it, darker the deeper it is. `None` when the chunk has no sections.
+pub fn surface(root: &NBT, biome_tint: bool) -> Option<Surface> {
+    let level = section::level(root)?;
+    let sections: BTreeMap<i32, Section> = level.get_list("sections").or_else(|_| level.get_list("Sections")).ok()?
+        .iter()
+        .filter_map(|section| Section::parse(section.as_compound().ok()?))
+        .collect();
+    let (&bottom, &top) = (sections.keys().next()?, sections.keys().next_back()?);
+    let heightmap = level.get_compound("Heightmaps").ok()
+        .and_then(|heightmaps| heightmaps.get_long_array("MOTION_BLOCKING").or_else(|_| heightmaps.get_long_array("WORLD_SURFACE")).ok())
+        .filter(|heightmap| !heightmap.is_empty());
+    let min_y = level.get_int("yPos").unwrap_or(0) * 16;
+
+    let mut surface = Surface{ colors: [None; 256], heights: [UNDRAWN; 256] };
+    for column in 0..256 {
+        let (x, z) = (column % 16, column / 16);
+        /* heightmaps hold the height above the bottom of the world of the first air block above the surface */
+        let start = match heightmap {
+            Some(heightmap) => min_y + section::packed(heightmap, 64 / 256usize.div_ceil(heightmap.len()), column) as i32 - 1,
+            None => top * 16 + 15,
+        };
+        let mut water: Option<(Color, i32)> = None;
+        let mut y = start.min(top * 16 + 15);
+        while y >= bottom * 16 {
+            let Some(section) = sections.get(&(y >> 4)) else {
+                y = (y >> 4) * 16 - 1;
+                continue;
+            };
+            let local = (y & 15) as usize;
+            let name = section.block(x, local, z);
+            let tinted = |tint: Tint| match section.biome(x, local, z) {
+                Some(biome) if biome_tint => colors::biome(biome).get(tint),
+                _ => colors::plains(tint),
+            };
+            if is_water(name) {
+                if water.is_none() {
+                    water = Some((tinted(Tint::Water), y));
+                }
+                y -= 1;
+                continue;
+            }
+            let color = match colors::tint(name) {
+                Some(tint) => Some(tinted(tint)),
+                None => colors::block(name),
+            };
+            if let Some(color) = color {
+                surface.colors[column] = Some(match water {
+                    Some((water, surface)) => blend(water, color, (0.6 + (surface - y) as f32 * 0.04).min(0.92)),
+                    None => color,
+                });
+                surface.heights[column] = water.map_or(y, |(_, surface)| surface);
+                break;
+            }
+            y -= 1;
+        }
+        /* water too deep to find a floor in this chunk */
+        if let (None, Some((water, surface_y))) = (surface.colors[column], water) {
+            surface.colors[column] = Some(water);
+            surface.heights[column] = surface_y;
+        }
+    }
+    Some(surface)
+}
+
+impl Canvas {
+    /// A transparent canvas covering the chunks from `min` to `max`, inclusive.
+    pub fn new(min: (i32, i32), max: (i32, i32)) -> Self {
+        let width = (max.0 - min.0 + 1).max(0) as usize * 16;
+        let height = (max.1 - min.1 + 1).max(0) as usize * 16;
+        Self {
+            origin:  min,
+            width,
+            height,
+            pixels:  vec![[0; 4]; width * height],
+            heights: vec![UNDRAWN; width * height],
+        }
+    }
+
+    /// Draws the chunk at chunk coordinates `x`, `z`, if it lies on the canvas.
+    pub fn draw(&mut self, x: i32, z: i32, surface: &Surface) {
+        let (left, top) = ((x - self.origin.0) as i64 * 16, (z - self.origin.1) as i64 * 16);
+        if left < 0 || top < 0 || left as usize >= self.width || top as usize >= self.height {
+            return;
+        }
+        for (column, color) in surface.colors.iter().enumerate() {
+            let Some([r, g, b]) = color else {
+                continue;
+            };
+            let at = (top as usize + column / 16) * self.width + left as usize + column % 16;
+            self.pixels[at] = [*r, *g, *b, 0xff];
+            self.heights[at] = surface.heights[column];
+        }
+    }
+
+    /// Lights the terrain from the north west: slopes facing it get brighter, the others darker.
+    pub fn shade(&mut self) {
+        for at in self.width + 1..self.pixels.len() {
+            if at % self.width == 0 {
+                continue;
+            }
+            let (here, north, west) = (self.heights[at], self.heights[at - self.width], self.heights[at - 1]);
+            if here == UNDRAWN || north == UNDRAWN || west == UNDRAWN {
+                continue;
+            }
+            let slope = ((here - north) + (here - west)).clamp(-4, 4);
+            let factor = 1.0 + slope as f32 * 0.06;
+            for channel in self.pixels[at][..3].iter_mut() {
+                *channel = (*channel as f32 * factor).clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn png(&self) -> Vec<u8> {
+        png::encode(self.width as u32, self.height as u32, &self.pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{colors, Canvas, Surface, UNDRAWN};
+    use colors::Tint;
+
+    #[test]
+    fn chunks_are_drawn_at_their_offset() {
+        let mut canvas = Canvas::new((-1, -1), (0, 0));
+        assert!(canvas.width() == 32 && canvas.height() == 32);
+        let mut surface = Surface{ colors: [None; 256], heights: [UNDRAWN; 256] };
+        surface.colors[0] = Some([1, 2, 3]);
+        surface.colors[17] = Some([4, 5, 6]);
+        surface.heights[17] = 70;
+
+        /* the chunk north east of the origin lands top right */
+        canvas.draw(0, -1, &surface);
+        assert!(canvas.pixels[16] == [1, 2, 3, 255]);
+        assert!(canvas.pixels[32 + 17] == [4, 5, 6, 255] && canvas.heights[32 + 17] == 70);
+        canvas.draw(-1, 0, &surface);
+        assert!(canvas.pixels[16 * 32] == [1, 2, 3, 255]);
+        /* columns without a color and chunks off the canvas leave it alone */
+        canvas.draw(1, 0, &surface);
+        canvas.draw(0, -2, &surface);
+        assert!(canvas.pixels.iter().filter(|pixel| pixel[3] != 0).count() == 4);
+    }
+
+    #[test]
+    fn water_is_mixed_with_the_floor_below() {
+        let root = crate::nbt!{ "sections": [
+            { "Y": (0i8), "block_states": { "palette": [{ "Name": "minecraft:stone" }] } },
+            { "Y": 1b, "block_states": { "palette": [{ "Name": "minecraft:water" }] } },
+            { "Y": 2b, "block_states": { "palette": [{ "Name": "minecraft:air" }] } },
+        ] };
+        let Some(surface) = super::surface(&root, false) else {
+            panic!("expected a surface");
+        };
+        let Some(stone) = colors::block("minecraft:stone") else {
+            panic!("stone has no color");
+        };
+        /* 16 blocks of water is past the deepest mix */
+        let expected = super::blend(colors::plains(Tint::Water), stone, 0.92);
+        assert!(surface.colors.iter().all(|color| *color == Some(expected)));
+        assert!(surface.heights.iter().all(|height| *height == 31));
+
+        let dry = crate::nbt!{ "sections": [{ "Y": (0i8), "block_states": { "palette": [{ "Name": "minecraft:stone" }] } }] };
+        assert!(super::surface(&dry, false).is_some_and(|surface| surface.colors[0] == Some(stone) && surface.heights[0] == 15));
+        assert!(super::surface(&crate::nbt!{ "sections": [] }, false).is_none());
+    }
+}
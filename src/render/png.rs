@@ -0,0 +1,71 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+use std::io::Write;
+use flate2::write::ZlibEncoder;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/* 8 bits per channel, RGBA */
+const DEPTH: u8 = 8;
+const RGBA: u8 = 6;
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let mut crc = flate2::Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+/// Encodes `pixels`, rows of RGBA pixels from the top left, as a PNG image.
+pub fn encode(width: u32, height: u32, pixels: &[[u8; 4]]) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    /* bit depth, color type, then deflate compression, adaptive filtering and no interlacing */
+    header.extend_from_slice(&[DEPTH, RGBA, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+
+    /* every row starts with its filter type; 0 leaves the row as it is */
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    for row in pixels.chunks(width.max(1) as usize) {
+        encoder.write_all(&[0]).unwrap();
+        encoder.write_all(row.as_flattened()).unwrap();
+    }
+    chunk(&mut png, b"IDAT", &encoder.finish().unwrap());
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    #[test]
+    fn header_and_rows() {
+        let pixels = [[1, 2, 3, 255], [4, 5, 6, 255], [7, 8, 9, 0], [10, 11, 12, 0], [13, 14, 15, 128], [16, 17, 18, 128]];
+        let png = super::encode(3, 2, &pixels);
+        assert!(png[..8] == super::SIGNATURE);
+        assert!(png[8..12] == 13u32.to_be_bytes() && &png[12..16] == b"IHDR");
+        assert!(png[16..20] == 3u32.to_be_bytes() && png[20..24] == 2u32.to_be_bytes());
+        assert!(png[24..29] == [8, 6, 0, 0, 0]);
+        let mut crc = flate2::Crc::new();
+        crc.update(&png[12..29]);
+        assert!(png[29..33] == crc.sum().to_be_bytes());
+
+        /* each row is its filter type followed by its pixels */
+        let length = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert!(&png[37..41] == b"IDAT");
+        let mut rows = Vec::new();
+        assert!(flate2::read::ZlibDecoder::new(&png[41..41+length]).read_to_end(&mut rows).is_ok());
+        assert!(rows.len() == 2 * (1 + 3 * 4));
+        assert!(rows[0] == 0 && rows[1..13] == *pixels[..3].as_flattened());
+        assert!(rows[13] == 0 && rows[14..] == *pixels[3..].as_flattened());
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+    }
+}
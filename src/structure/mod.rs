@@ -0,0 +1,261 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+pub mod paste;
+pub mod sponge;
+pub mod vanilla;
+
+use std::collections::HashMap;
+
+use crate::nbt::{NBT, Payload, TAGCompound, TAGList};
+use crate::nbt::access::{self, Lookup};
+use crate::nbt::build::Builder;
+use crate::region::section::{self, Section};
+
+/// Blocks a structure may hold, so a typo in an area can't exhaust memory.
+pub const MAX_BLOCKS: usize = 1 << 28;
+
+pub enum Error {
+    Access(access::Error),
+    /// A Sponge schematic version this can't read.
+    Version(i32),
+    /// A palette entry that isn't a valid block state.
+    State(String),
+    /// Block data that doesn't fit the structure's size or palette.
+    Data,
+}
+
+/// A box of blocks with their block entities and entities, cut out of a world or read from a file.
+pub struct Structure {
+    pub data_version:   i32,
+    /// Width along x, height along y and length along z.
+    pub size:           (i32, i32, i32),
+    /// Block states, each a `Name` and optional `Properties` compound.
+    pub palette:        Vec<TAGCompound>,
+    /// Palette index of every block, indexed `x + z * width + y * width * length`.
+    pub blocks:         Vec<u32>,
+    /// Block entities by position inside the structure, with their `id` but without `x`, `y` and `z`.
+    pub block_entities: Vec<((i32, i32, i32), TAGCompound)>,
+    /// Entities by position inside the structure, with their `id` but without `Pos` and `UUID`.
+    pub entities:       Vec<((f64, f64, f64), TAGCompound)>,
+}
+
+/// Builds a [`Structure`] of the blocks from `min` to `max`, inclusive, one chunk at a time.
+pub struct Extract {
+    min:       (i32, i32, i32),
+    max:       (i32, i32, i32),
+    structure: Structure,
+    /// Palette index of each state string, to share one entry between chunks.
+    states:    HashMap<String, u32>,
+}
+
+fn state(name: &str) -> TAGCompound {
+    Builder::new().tag("Name", name).build()
+}
+
+fn double(payload: &Payload) -> Option<f64> {
+    match payload {
+        Payload::Double(double) => Some(*double),
+        Payload::Float(float)   => Some(*float as f64),
+        _ => None,
+    }
+}
+
+impl Structure {
+    pub fn volume(&self) -> usize {
+        self.size.0 as usize * self.size.1 as usize * self.size.2 as usize
+    }
+
+    /// Whether structure relative `x`, `y`, `z` lies inside the structure.
+    pub fn contains(&self, (x, y, z): (i32, i32, i32)) -> bool {
+        (0..self.size.0).contains(&x) && (0..self.size.1).contains(&y) && (0..self.size.2).contains(&z)
+    }
+
+    /// Index into `blocks` of structure relative `x`, `y`, `z`.
+    pub fn index(&self, x: i32, y: i32, z: i32) -> usize {
+        x as usize + z as usize * self.size.0 as usize + y as usize * self.size.0 as usize * self.size.2 as usize
+    }
+}
+
+impl Extract {
+    /// Every block starts out as air, for chunks that were never generated.
+    pub fn new(min: (i32, i32, i32), max: (i32, i32, i32)) -> Self {
+        let size = (max.0 - min.0 + 1, max.1 - min.1 + 1, max.2 - min.2 + 1);
+        let structure = Structure {
+            data_version:   0,
+            size,
+            palette:        vec![state(section::AIR)],
+            blocks:         vec![0; size.0 as usize * size.1 as usize * size.2 as usize],
+            block_entities: Vec::new(),
+            entities:       Vec::new(),
+        };
+        Self{ min, max, structure, states: HashMap::from([(String::from(section::AIR), 0)]) }
+    }
+
+    fn contains(&self, (x, y, z): (i32, i32, i32)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y) && (self.min.2..=self.max.2).contains(&z)
+    }
+
+    fn data_version(&mut self, root: &NBT) {
+        if let Some(version) = root.payload.as_compound().ok().and_then(|root| root.get_int("DataVersion").ok()) {
+            self.structure.data_version = self.structure.data_version.max(version);
+        }
+    }
+
+    fn entity(&mut self, entity: &TAGCompound) {
+        let Some(pos) = entity.get_list("Pos").ok().and_then(doubles) else {
+            return;
+        };
+        if !self.contains((pos.0.floor() as i32, pos.1.floor() as i32, pos.2.floor() as i32)) {
+            return;
+        }
+        let mut entity = entity.clone();
+        /* a pasted copy must not share its UUID with the original */
+        entity.remove("Pos");
+        entity.remove("UUID");
+        let relative = (pos.0 - self.min.0 as f64, pos.1 - self.min.1 as f64, pos.2 - self.min.2 as f64);
+        self.structure.entities.push((relative, entity));
+    }
+
+    /// Copies the blocks and block entities of the chunk at chunk coordinates `cx`, `cz` that lie in
+    /// the box, and before 1.17 its entities. Returns how many sections it couldn't read.
+    pub fn terrain(&mut self, cx: i32, cz: i32, root: &NBT) -> usize {
+        self.data_version(root);
+        let (sections, unreadable) = Section::all(root);
+        for section in &sections {
+            let indices: Vec<u32> = section.palette.iter().map(|state| {
+                let key = section::state_string(state);
+                let next = self.structure.palette.len() as u32;
+                *self.states.entry(key).or_insert_with(|| {
+                    self.structure.palette.push(state.clone());
+                    next
+                })
+            }).collect();
+            for y in (section.y * 16).max(self.min.1)..=(section.y * 16 + 15).min(self.max.1) {
+                for z in (cz * 16).max(self.min.2)..=(cz * 16 + 15).min(self.max.2) {
+                    for x in (cx * 16).max(self.min.0)..=(cx * 16 + 15).min(self.max.0) {
+                        let block = section.blocks[(((y & 15) * 16 + (z & 15)) * 16 + (x & 15)) as usize];
+                        let at = self.structure.index(x - self.min.0, y - self.min.1, z - self.min.2);
+                        /* indices past the palette are read as air, like the game does */
+                        self.structure.blocks[at] = indices.get(block as usize).copied().unwrap_or(0);
+                    }
+                }
+            }
+        }
+
+        let Some(level) = section::level(root) else {
+            return unreadable;
+        };
+        let block_entities = level.get_list_of::<TAGCompound>("block_entities").or_else(|_| level.get_list_of("TileEntities"));
+        for block_entity in block_entities.unwrap_or_default() {
+            let (Ok(x), Ok(y), Ok(z)) = (block_entity.get_int("x"), block_entity.get_int("y"), block_entity.get_int("z")) else {
+                continue;
+            };
+            if !self.contains((x, y, z)) {
+                continue;
+            }
+            let mut block_entity = block_entity.clone();
+            for name in ["x", "y", "z", "keepPacked"] {
+                block_entity.remove(name);
+            }
+            self.structure.block_entities.push(((x - self.min.0, y - self.min.1, z - self.min.2), block_entity));
+        }
+        for entity in level.get_list_of::<TAGCompound>("Entities").unwrap_or_default() {
+            self.entity(entity);
+        }
+        unreadable
+    }
+
+    /// Copies the entities in the box from a chunk of an `entities` folder.
+    pub fn entities(&mut self, root: &NBT) {
+        self.data_version(root);
+        let Ok(entities) = root.payload.as_compound().and_then(|root| root.get_list_of::<TAGCompound>("Entities")) else {
+            return;
+        };
+        for entity in entities {
+            self.entity(entity);
+        }
+    }
+
+    /// `data_version` is used when no chunk had one.
+    pub fn finish(mut self, data_version: i32) -> Structure {
+        if self.structure.data_version == 0 {
+            self.structure.data_version = data_version;
+        }
+        self.structure
+    }
+}
+
+/// Three ints from a list, like a block position.
+fn ints(list: &TAGList) -> Option<(i32, i32, i32)> {
+    match list.tags.as_slice() {
+        [x, y, z] => Some((x.as_integer()? as i32, y.as_integer()? as i32, z.as_integer()? as i32)),
+        _ => None,
+    }
+}
+
+/// Three doubles from a list, like an entity position.
+fn doubles(list: &TAGList) -> Option<(f64, f64, f64)> {
+    match list.tags.as_slice() {
+        [x, y, z] => Some((double(x)?, double(y)?, double(z)?)),
+        _ => None,
+    }
+}
+
+/// The `id` of a block entity or entity, empty without one.
+fn id(compound: &TAGCompound) -> String {
+    String::from(compound.get_str("id").unwrap_or_default())
+}
+
+/// A copy of `compound` without the tags `names`.
+fn without(compound: &TAGCompound, names: &[&str]) -> TAGCompound {
+    TAGCompound{ tags: compound.tags.iter().filter(|tag| !names.iter().any(|name| tag.name.str == name.as_bytes())).cloned().collect() }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Access(e) => write!(f, "{}", e),
+            Error::Version(version) => write!(f, "sponge schematic version {} is not supported, only 1 to 3 are", version),
+            Error::State(state) => write!(f, "'{}' is not a block state", state),
+            Error::Data => write!(f, "block data doesn't match the structure's size or palette"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{state, Structure};
+    use crate::nbt::{Payload, TAGList};
+    use crate::nbt::build::Builder;
+
+    /// A 2 x 2 x 1 structure with a block state that has properties, a block entity and an entity.
+    /// Their `id`s come last, where the Sponge reader puts them back.
+    pub(super) fn sample() -> Structure {
+        let stairs = Builder::new()
+            .tag("Name", "minecraft:oak_stairs")
+            .tag("Properties", Builder::new().tag("facing", "north").build())
+            .build();
+        Structure {
+            data_version:   3955,
+            size:           (2, 2, 1),
+            palette:        vec![state("minecraft:stone"), stairs],
+            blocks:         vec![0, 1, 1, 0],
+            block_entities: vec![((1, 0, 0), Builder::new().tag("Items", TAGList::of(Vec::new()).unwrap()).tag("id", "minecraft:chest").build())],
+            entities:       vec![((0.5, 1.0, 0.5), Builder::new().tag("Health", Payload::Float(10.0)).tag("id", "minecraft:pig").build())],
+        }
+    }
+
+    #[test]
+    fn index_runs_x_then_z_then_y() {
+        let structure = Structure{ size: (2, 3, 4), ..sample() };
+        assert!(structure.index(1, 0, 0) == 1);
+        assert!(structure.index(0, 0, 1) == 2);
+        assert!(structure.index(0, 1, 0) == 8);
+        assert!(structure.index(1, 2, 3) == structure.volume() - 1);
+        assert!(structure.contains((1, 2, 3)));
+        assert!(!structure.contains((2, 0, 0)));
+        assert!(!structure.contains((0, -1, 0)));
+    }
+}
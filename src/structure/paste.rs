@@ -0,0 +1,215 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+/* Pasting edits chunks in place: sections get their palettes and packed indices rebuilt, block
+ * entities where a block was placed are replaced, and entities get a new UUID. Heightmaps are
+ * dropped and lighting is marked as not done, so the game computes both again when it loads the
+ * chunk. Sections the chunk doesn't have are only added before 1.18, where they may be missing
+ * anywhere; since then every section within the world's height exists. */
+
+use std::collections::HashSet;
+
+use crate::nbt::{NBT, Payload, TAGCompound, TAGIArray, TAGList};
+use crate::nbt::access::Lookup;
+use crate::nbt::build::Builder;
+use crate::region::section::{self, Section};
+use super::{vanilla, Structure};
+
+/// Data version of 20w45a, the snapshot that moved entities into their own folder.
+pub const ENTITY_FOLDER: i32 = 2681;
+
+/// What pasting did to one chunk.
+#[derive(Default)]
+pub struct Placed {
+    pub blocks:   usize,
+    /// Blocks in sections that couldn't be read or don't exist.
+    pub skipped:  usize,
+    pub block_entities: usize,
+    pub entities: usize,
+}
+
+/// A random version 4 UUID, stored as four ints like the game does.
+pub(crate) fn uuid() -> TAGIArray {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("a random source from the operating system");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    TAGIArray{ ints: bytes.chunks(4).map(|int| i32::from_be_bytes([int[0], int[1], int[2], int[3]])).collect() }
+}
+
+/// Appends `tag` to the list `name` of `compound`, creating the list if there is none.
+fn push(compound: &mut TAGCompound, name: &str, tag: Payload) {
+    let mut tags = match compound.get_mut(name).and_then(|list| list.as_list_mut().ok()) {
+        Some(list) => std::mem::take(&mut list.tags),
+        None => Vec::new(),
+    };
+    tags.push(tag);
+    if let Some(list) = TAGList::of(tags) {
+        compound.insert(name, Payload::List(list));
+    }
+}
+
+impl Structure {
+    /// The chunks the structure covers with its corner at `at`, smallest first.
+    pub fn chunks(&self, at: (i32, i32, i32)) -> ((i32, i32), (i32, i32)) {
+        ((at.0 >> 4, at.2 >> 4), ((at.0 + self.size.0 - 1) >> 4, (at.2 + self.size.2 - 1) >> 4))
+    }
+
+    /// Entities with their position in the world and the chunk they land in.
+    fn placed_entities(&self, at: (i32, i32, i32)) -> impl Iterator<Item = ((i32, i32), (f64, f64, f64), &TAGCompound)> {
+        self.entities.iter().map(move |((x, y, z), entity)| {
+            let pos = (at.0 as f64 + x, at.1 as f64 + y, at.2 as f64 + z);
+            (((pos.0.floor() as i32) >> 4, (pos.2.floor() as i32) >> 4), pos, entity)
+        })
+    }
+
+    /// Whether any entity lands in chunk `cx`, `cz`.
+    pub fn has_entities(&self, at: (i32, i32, i32), cx: i32, cz: i32) -> bool {
+        self.placed_entities(at).any(|(chunk, _, _)| chunk == (cx, cz))
+    }
+
+    fn entities_into(&self, at: (i32, i32, i32), cx: i32, cz: i32, list: &mut TAGCompound, name: &str) -> usize {
+        let mut placed = 0;
+        for (_, (x, y, z), entity) in self.placed_entities(at).filter(|(chunk, _, _)| *chunk == (cx, cz)) {
+            let mut entity = entity.clone();
+            entity.insert("Pos", Payload::List(TAGList::of(vec![Payload::Double(x), Payload::Double(y), Payload::Double(z)]).unwrap()));
+            entity.insert("UUID", Payload::IArray(uuid()));
+            push(list, name, Payload::Compound(entity));
+            placed += 1;
+        }
+        placed
+    }
+
+    /// Pastes the part of the structure that lands in the chunk at `cx`, `cz` of a `region` folder,
+    /// with its corner at block `at`. Before entities got their own folder, they go in here too.
+    pub fn paste(&self, at: (i32, i32, i32), cx: i32, cz: i32, root: &mut NBT) -> Placed {
+        let mut placed = Placed::default();
+        let data_version = root.payload.as_compound().ok().and_then(|root| root.get_int("DataVersion").ok()).unwrap_or(0);
+        let Ok(root) = root.payload.as_compound_mut() else {
+            return placed;
+        };
+        let legacy = root.get("Level").is_some();
+        let level = match root.get_mut("Level") {
+            Some(Payload::Compound(level)) => level,
+            _ => root,
+        };
+        let (x_range, z_range) = ((cx * 16).max(at.0)..=(cx * 16 + 15).min(at.0 + self.size.0 - 1), (cz * 16).max(at.2)..=(cz * 16 + 15).min(at.2 + self.size.2 - 1));
+
+        /* sections */
+        let sections_name = if level.get("sections").is_some() { "sections" } else { "Sections" };
+        let mut sections = match level.get_mut(sections_name).and_then(|list| list.as_list_mut().ok()) {
+            Some(list) => std::mem::take(&mut list.tags),
+            None => Vec::new(),
+        };
+        let mut filled = HashSet::new();
+        for sy in at.1 >> 4..=(at.1 + self.size.1 - 1) >> 4 {
+            let y_range = (sy * 16).max(at.1)..=(sy * 16 + 15).min(at.1 + self.size.1 - 1);
+            let existing = sections.iter_mut()
+                .filter_map(|section| section.as_compound_mut().ok())
+                .find(|section| section.get("Y").and_then(|y| y.as_integer()) == Some(sy as i64));
+            let mut created = None;
+            let compound = match existing {
+                Some(compound) => compound,
+                None if legacy => created.insert(Builder::new().tag("Y", sy as i8).tag("Palette", TAGList::of(Vec::new()).unwrap()).build()),
+                None => {
+                    placed.skipped += y_range.clone().count() * x_range.clone().count() * z_range.clone().count();
+                    continue;
+                },
+            };
+            let mut section = match compound.get("Palette").and_then(|palette| palette.as_list().ok()).is_some_and(|palette| palette.is_empty()) {
+                true => Some(Section::empty(sy)),
+                false => Section::parse(compound),
+            };
+            let Some(section) = section.as_mut() else {
+                placed.skipped += y_range.clone().count() * x_range.clone().count() * z_range.clone().count();
+                continue;
+            };
+            for y in y_range {
+                for z in z_range.clone() {
+                    for x in x_range.clone() {
+                        let state = &self.palette[self.blocks[self.index(x - at.0, y - at.1, z - at.2)] as usize];
+                        if state.get_str("Name").is_ok_and(|name| name == vanilla::VOID) {
+                            continue;
+                        }
+                        section.set((x & 15) as usize, (y & 15) as usize, (z & 15) as usize, state);
+                        filled.insert((x, y, z));
+                        placed.blocks += 1;
+                    }
+                }
+            }
+            section.store(compound);
+            if let Some(created) = created {
+                sections.push(Payload::Compound(created));
+            }
+        }
+        if let Some(list) = TAGList::of(sections) {
+            level.insert(sections_name, Payload::List(list));
+        }
+
+        /* block entities of replaced blocks go, the structure's come in */
+        let block_entities_name = if legacy { "TileEntities" } else { "block_entities" };
+        if let Some(list) = level.get_mut(block_entities_name).and_then(|list| list.as_list_mut().ok()) {
+            list.tags.retain(|tag| {
+                let Ok(block_entity) = tag.as_compound() else {
+                    return true;
+                };
+                match (block_entity.get_int("x"), block_entity.get_int("y"), block_entity.get_int("z")) {
+                    (Ok(x), Ok(y), Ok(z)) => !filled.contains(&(x, y, z)),
+                    _ => true,
+                }
+            });
+        }
+        for ((x, y, z), block_entity) in &self.block_entities {
+            let (x, y, z) = (at.0 + x, at.1 + y, at.2 + z);
+            if !x_range.contains(&x) || !z_range.contains(&z) || !filled.contains(&(x, y, z)) {
+                continue;
+            }
+            let mut block_entity = block_entity.clone();
+            block_entity.insert("x", Payload::Int(x));
+            block_entity.insert("y", Payload::Int(y));
+            block_entity.insert("z", Payload::Int(z));
+            push(level, block_entities_name, Payload::Compound(block_entity));
+            placed.block_entities += 1;
+        }
+        if data_version < ENTITY_FOLDER {
+            placed.entities = self.entities_into(at, cx, cz, level, "Entities");
+        }
+
+        if placed.blocks > 0 {
+            section::relight(level);
+        }
+        placed
+    }
+
+    /// Adds the entities that land in chunk `cx`, `cz` to its chunk of an `entities` folder, or to a
+    /// new one when `root` is `None`. Returns how many were added.
+    pub fn paste_entities(&self, at: (i32, i32, i32), cx: i32, cz: i32, root: Option<NBT>, data_version: i32) -> (NBT, usize) {
+        let mut root = root.unwrap_or_else(|| crate::nbt!{
+            "DataVersion": (data_version),
+            "Position": (TAGIArray{ ints: vec![cx, cz] }),
+            "Entities": [],
+        });
+        let placed = match root.payload.as_compound_mut() {
+            Ok(compound) => self.entities_into(at, cx, cz, compound, "Entities"),
+            Err(_) => 0,
+        };
+        (root, placed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::uuid;
+
+    #[test]
+    fn uuids_are_random_version_four() {
+        let (a, b) = (uuid(), uuid());
+        assert!(a.ints != b.ints);
+        for uuid in [a, b] {
+            assert!(uuid.ints.len() == 4);
+            assert!((uuid.ints[1] as u32 >> 12) & 0xf == 4);
+            assert!(uuid.ints[2] as u32 >> 30 == 0b10);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+/* Sponge schematics, version 3, as written by WorldEdit: a `Schematic` compound whose `Blocks`
+ * hold a palette of state strings and one varint index per block, x fastest, then z, then y.
+ * Block entities and entities keep their position and id apart from the rest of their data.
+ * Versions 1 and 2 keep the palette and block data at the top, and the rest of a block entity's
+ * or entity's data next to its position and id. */
+
+use crate::nbt::{NBT, Payload, TAGByteArray, TAGCompound, TAGIArray, TAGList};
+use crate::nbt::access::Lookup;
+use crate::nbt::build::Builder;
+use crate::region::section;
+use super::{Error, Structure};
+
+pub const VERSION: i32 = 3;
+
+fn varints(values: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(values.len());
+    for value in values {
+        let mut value = *value;
+        while value >= 0x80 {
+            bytes.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
+    }
+    bytes
+}
+
+fn read_varints(bytes: &[u8]) -> Option<Vec<u32>> {
+    let mut values = Vec::with_capacity(bytes.len());
+    let (mut value, mut shift) = (0u32, 0);
+    for byte in bytes {
+        if shift > 28 {
+            return None;
+        }
+        value |= ((byte & 0x7f) as u32) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            (value, shift) = (0, 0);
+        }
+    }
+    match shift {
+        0 => Some(values),
+        _ => None,
+    }
+}
+
+/// A block entity's or entity's data with its `id`: in version 3 the `Data` compound, before it
+/// every tag but the position.
+fn contents(compound: &TAGCompound, version: i32) -> TAGCompound {
+    let mut data = match (version, compound.get_compound("Data")) {
+        (3, Ok(data)) => data.clone(),
+        _ => super::without(compound, &["Pos", "Id", "Data"]),
+    };
+    if let Ok(id) = compound.get_str("Id") {
+        data.insert("id", Payload::from(id));
+    }
+    data
+}
+
+/// Reads a schematic of version 1 to 3. Its `Offset` is left out; pastes put the corner where they're told.
+pub fn read(root: &NBT) -> Result<Structure, Error> {
+    let root = root.payload.as_compound().map_err(Error::Access)?;
+    /* version 3 wraps everything in a `Schematic` compound, earlier versions name the root so */
+    let schematic = root.get_compound("Schematic").unwrap_or(root);
+    let version = schematic.get_int("Version").map_err(Error::Access)?;
+    let (palette, data, block_entities) = match version {
+        3 => {
+            let blocks = schematic.get_compound("Blocks").map_err(Error::Access)?;
+            (blocks.get_compound("Palette"), blocks.get_byte_array("Data"), blocks.get_list_of::<TAGCompound>("BlockEntities"))
+        },
+        1 | 2 => (
+            schematic.get_compound("Palette"),
+            schematic.get_byte_array("BlockData"),
+            schematic.get_list_of::<TAGCompound>("BlockEntities").or_else(|_| schematic.get_list_of("TileEntities")),
+        ),
+        _ => return Err(Error::Version(version)),
+    };
+    let size = ["Width", "Height", "Length"].map(|side| schematic.get_short(side).map(|side| side as u16 as i32));
+    let [Ok(width), Ok(height), Ok(length)] = size else {
+        return Err(Error::Data);
+    };
+
+    let mut states = Vec::new();
+    for entry in palette.map_err(Error::Access)?.iter() {
+        let (Some(name), Some(index)) = (entry.name.as_str(), entry.payload.as_integer()) else {
+            return Err(Error::Data);
+        };
+        let Some(state) = section::state_compound(name) else {
+            return Err(Error::State(String::from(name)));
+        };
+        states.push((index as usize, state));
+    }
+    let Some(len) = states.iter().map(|(index, _)| index + 1).max().filter(|len| *len <= states.len()) else {
+        return Err(Error::Data);
+    };
+    /* indices no state claims are read as air */
+    let mut palette = vec![super::state(section::AIR); len];
+    for (index, state) in states {
+        palette[index] = state;
+    }
+    let blocks = read_varints(data.map_err(Error::Access)?).ok_or(Error::Data)?;
+    if blocks.len() != width as usize * height as usize * length as usize || blocks.iter().any(|block| *block as usize >= len) {
+        return Err(Error::Data);
+    }
+
+    let mut structure = Structure {
+        data_version:   schematic.get_int("DataVersion").unwrap_or(0),
+        size:           (width, height, length),
+        palette,
+        blocks,
+        block_entities: Vec::new(),
+        entities:       Vec::new(),
+    };
+    for block_entity in block_entities.unwrap_or_default() {
+        if let Ok([x, y, z]) = block_entity.get_int_array("Pos") {
+            structure.block_entities.push(((*x, *y, *z), contents(block_entity, version)));
+        }
+    }
+    for entity in schematic.get_list_of::<TAGCompound>("Entities").unwrap_or_default() {
+        if let Some(pos) = entity.get_list("Pos").ok().and_then(super::doubles) {
+            let mut entity = contents(entity, version);
+            entity.remove("UUID");
+            structure.entities.push((pos, entity));
+        }
+    }
+    Ok(structure)
+}
+
+/// `structure` as a schematic made at `date`, in milliseconds since the epoch.
+pub fn write(structure: &Structure, date: i64) -> NBT {
+    let mut palette = Builder::new();
+    for (index, state) in structure.palette.iter().enumerate() {
+        palette = palette.tag(&section::state_string(state), index as i32);
+    }
+    let block_entities = structure.block_entities.iter().map(|((x, y, z), block_entity)| {
+        Payload::Compound(Builder::new()
+            .tag("Pos", TAGIArray{ ints: vec![*x, *y, *z] })
+            .tag("Id", super::id(block_entity))
+            .tag("Data", super::without(block_entity, &["id"]))
+            .build())
+    }).collect();
+    let entities = structure.entities.iter().map(|((x, y, z), entity)| {
+        Payload::Compound(Builder::new()
+            .tag("Pos", TAGList::of(vec![Payload::Double(*x), Payload::Double(*y), Payload::Double(*z)]).unwrap())
+            .tag("Id", super::id(entity))
+            .tag("Data", super::without(entity, &["id"]))
+            .build())
+    }).collect();
+
+    crate::nbt!{
+        "Schematic": {
+            "Version": (VERSION),
+            "DataVersion": (structure.data_version),
+            "Metadata": { "Date": (date) },
+            "Width": (structure.size.0 as u16 as i16),
+            "Height": (structure.size.1 as u16 as i16),
+            "Length": (structure.size.2 as u16 as i16),
+            "Offset": [I; 0, 0, 0],
+            "Blocks": {
+                "Palette": (palette),
+                "Data": (TAGByteArray{ body: varints(&structure.blocks) }),
+                "BlockEntities": (TAGList::of(block_entities).unwrap()),
+            },
+            "Entities": (TAGList::of(entities).unwrap()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, read_varints, varints, write};
+    use crate::nbt::{NBT, Payload, TAGByteArray, TAGCompound};
+    use crate::nbt::access::Lookup;
+    use crate::nbt::build::literal;
+    use crate::structure::{tests::sample, Error};
+
+    /// The `Schematic` compound of `root`, changed by `edit`.
+    fn edited(root: &NBT, edit: impl FnOnce(&mut TAGCompound)) -> NBT {
+        let mut root = root.clone();
+        if let Payload::Compound(root) = &mut root.payload {
+            if let Some(Payload::Compound(schematic)) = root.tags.iter_mut().find(|tag| tag.name.str == b"Schematic").map(|tag| &mut tag.payload) {
+                edit(schematic);
+            }
+        }
+        root
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        let values = vec![0, 1, 127, 128, 300, 16383, 16384, u32::MAX];
+        assert!(varints(&values).len() == 1 + 1 + 1 + 2 + 2 + 2 + 3 + 5);
+        assert!(read_varints(&varints(&values)) == Some(values));
+        /* a final byte still asking for more */
+        assert!(read_varints(&[0x80]).is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        let structure = sample();
+        let root = write(&structure, 0);
+        let Ok(read) = read(&root) else {
+            panic!("a written schematic didn't read back");
+        };
+        assert!(read.data_version == structure.data_version);
+        assert!(read.size == structure.size);
+        assert!(read.palette == structure.palette);
+        assert!(read.blocks == structure.blocks);
+        assert!(read.block_entities == structure.block_entities);
+        assert!(read.entities == structure.entities);
+    }
+
+    #[test]
+    fn version_two() {
+        let root = crate::nbt!{
+            "Version": 2,
+            "DataVersion": 2586,
+            "Width": 2s,
+            "Height": 1s,
+            "Length": 1s,
+            "Palette": { "minecraft:air": 0, "minecraft:chest[facing=west]": 1 },
+            "BlockData": (TAGByteArray{ body: vec![1, 0] }),
+            "BlockEntities": [{ "Pos": [I; 0, 0, 0], "Id": "minecraft:chest", "Lock": "" }],
+        };
+        let Ok(read) = read(&root) else {
+            panic!("a version 2 schematic didn't read");
+        };
+        assert!(read.size == (2, 1, 1));
+        assert!(read.blocks == vec![1, 0]);
+        assert!(read.palette[1].get_compound("Properties").and_then(|properties| properties.get_str("facing")).ok() == Some("west"));
+        assert!(read.block_entities.len() == 1);
+        assert!(read.block_entities[0].1.get_str("id").ok() == Some("minecraft:chest"));
+        assert!(read.block_entities[0].1.get_str("Lock").ok() == Some(""));
+    }
+
+    #[test]
+    fn bad_schematics_fail() {
+        let root = write(&sample(), 0);
+        let version = edited(&root, |schematic| {
+            schematic.insert("Version", Payload::Int(4));
+        });
+        assert!(matches!(read(&version), Err(Error::Version(4))));
+        let short = edited(&root, |schematic| {
+            schematic.insert("Width", Payload::Short(1));
+        });
+        assert!(matches!(read(&short), Err(Error::Data)));
+        let state = edited(&root, |schematic| {
+            if let Ok(mut blocks) = schematic.get_compound("Blocks").cloned() {
+                blocks.insert("Palette", literal(r#"{"minecraft:stone[": 0, "minecraft:oak_stairs[facing=north]": 1}"#));
+                schematic.insert("Blocks", Payload::Compound(blocks));
+            }
+        });
+        assert!(matches!(read(&state), Err(Error::State(_))));
+    }
+}
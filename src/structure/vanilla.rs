@@ -0,0 +1,161 @@
+//
+// Created by Justin Tunheim on 10/18/26
+//
+
+/* Structure block files: a list of every block with its position and palette index, block
+ * entities stored with the block they belong to, and entities with both their exact and block
+ * position. Positions are relative to the structure's corner. */
+
+use std::collections::HashMap;
+
+use crate::nbt::{NBT, Payload, TAGCompound, TAGList};
+use crate::nbt::access::Lookup;
+use crate::nbt::build::Builder;
+use super::{Error, Structure};
+
+/* positions a structure leaves out are structure voids, which keep the blocks already there */
+pub const VOID: &str = "minecraft:structure_void";
+
+fn ints(values: [i32; 3]) -> TAGList {
+    TAGList::of(values.map(Payload::Int).to_vec()).unwrap()
+}
+
+/// Reads a structure block file. Files with several palettes, like shipwrecks, are read with the first.
+pub fn read(root: &NBT) -> Result<Structure, Error> {
+    let root = root.payload.as_compound().map_err(Error::Access)?;
+    let size = root.get_list("size").ok().and_then(super::ints).ok_or(Error::Data)?;
+    if size.0 < 0 || size.1 < 0 || size.2 < 0 {
+        return Err(Error::Data);
+    }
+    let volume = (size.0 as usize).checked_mul(size.1 as usize)
+        .and_then(|area| area.checked_mul(size.2 as usize))
+        .filter(|volume| *volume <= super::MAX_BLOCKS)
+        .ok_or(Error::Data)?;
+    let palette = match root.get_list("palette") {
+        Ok(palette) => palette,
+        Err(e) => match root.get_list("palettes").ok().and_then(|palettes| palettes.tags.first()).and_then(|palette| palette.as_list().ok()) {
+            Some(palette) => palette,
+            None => return Err(Error::Access(e)),
+        },
+    };
+    let mut palette = palette.iter()
+        .map(|state| state.as_compound().cloned().map_err(|_| Error::Data))
+        .collect::<Result<Vec<_>, _>>()?;
+    palette.push(super::state(VOID));
+
+    let mut structure = Structure {
+        data_version:   root.get_int("DataVersion").unwrap_or(0),
+        size,
+        blocks:         vec![palette.len() as u32 - 1; volume],
+        palette,
+        block_entities: Vec::new(),
+        entities:       Vec::new(),
+    };
+    for block in root.get_list_of::<TAGCompound>("blocks").map_err(Error::Access)? {
+        let (Some(pos), Ok(state)) = (block.get_list("pos").ok().and_then(super::ints), block.get_int("state")) else {
+            return Err(Error::Data);
+        };
+        if !structure.contains(pos) || state < 0 || state as usize >= structure.palette.len() - 1 {
+            return Err(Error::Data);
+        }
+        let at = structure.index(pos.0, pos.1, pos.2);
+        structure.blocks[at] = state as u32;
+        if let Ok(nbt) = block.get_compound("nbt") {
+            structure.block_entities.push((pos, nbt.clone()));
+        }
+    }
+    for entity in root.get_list_of::<TAGCompound>("entities").unwrap_or_default() {
+        let (Some(pos), Ok(nbt)) = (entity.get_list("pos").ok().and_then(super::doubles), entity.get_compound("nbt")) else {
+            continue;
+        };
+        structure.entities.push((pos, super::without(nbt, &["Pos", "UUID"])));
+    }
+    Ok(structure)
+}
+
+/// `structure` as the contents of a structure block `.nbt` file.
+pub fn write(structure: &Structure) -> NBT {
+    let block_entities: HashMap<_, _> = structure.block_entities.iter().map(|(at, block_entity)| (*at, block_entity)).collect();
+    let mut blocks = Vec::with_capacity(structure.blocks.len());
+    for y in 0..structure.size.1 {
+        for z in 0..structure.size.2 {
+            for x in 0..structure.size.0 {
+                let mut block = Builder::new()
+                    .tag("pos", ints([x, y, z]))
+                    .tag("state", structure.blocks[structure.index(x, y, z)] as i32);
+                if let Some(block_entity) = block_entities.get(&(x, y, z)) {
+                    block = block.tag("nbt", (*block_entity).clone());
+                }
+                blocks.push(Payload::Compound(block.build()));
+            }
+        }
+    }
+    let entities = structure.entities.iter().map(|((x, y, z), entity)| {
+        Payload::Compound(Builder::new()
+            .tag("pos", TAGList::of(vec![Payload::Double(*x), Payload::Double(*y), Payload::Double(*z)]).unwrap())
+            .tag("blockPos", ints([x.floor() as i32, y.floor() as i32, z.floor() as i32]))
+            .tag("nbt", entity.clone())
+            .build())
+    }).collect();
+
+    crate::nbt!{
+        "DataVersion": (structure.data_version),
+        "size": (ints([structure.size.0, structure.size.1, structure.size.2])),
+        "palette": (TAGList::of(structure.palette.iter().cloned().map(Payload::Compound).collect()).unwrap()),
+        "blocks": (TAGList::of(blocks).unwrap()),
+        "entities": (TAGList::of(entities).unwrap()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write, VOID};
+    use crate::nbt::{NBT, Payload};
+    use crate::nbt::build::literal;
+    use crate::structure::{state, tests::sample, Error};
+
+    fn edited(root: &NBT, name: &str, payload: Payload) -> NBT {
+        let mut root = root.clone();
+        if let Payload::Compound(compound) = &mut root.payload {
+            compound.insert(name, payload);
+        }
+        root
+    }
+
+    #[test]
+    fn round_trip() {
+        let structure = sample();
+        let Ok(read) = read(&write(&structure)) else {
+            panic!("a written structure didn't read back");
+        };
+        assert!(read.data_version == structure.data_version);
+        assert!(read.size == structure.size);
+        /* the void entry read appends for positions a file leaves out */
+        assert!(read.palette[..2] == structure.palette[..]);
+        assert!(read.palette[2] == state(VOID));
+        assert!(read.blocks == structure.blocks);
+        assert!(read.block_entities == structure.block_entities);
+        assert!(read.entities == structure.entities);
+    }
+
+    #[test]
+    fn missing_blocks_are_voids() {
+        let root = edited(&write(&sample()), "blocks", literal("[]"));
+        let Ok(read) = read(&root) else {
+            panic!("a structure without blocks didn't read");
+        };
+        assert!(read.blocks == vec![2; 4]);
+    }
+
+    #[test]
+    fn bad_data_fails() {
+        let root = write(&sample());
+        let oversized = edited(&root, "size", literal("[2147483647, 2147483647, 2147483647]"));
+        assert!(matches!(read(&oversized), Err(Error::Data)));
+        let negative = edited(&root, "size", literal("[2, -2, 1]"));
+        assert!(matches!(read(&negative), Err(Error::Data)));
+        /* without blocks to trip over, only the palette itself can be at fault */
+        let palette = edited(&edited(&root, "blocks", literal("[]")), "palette", literal(r#"["minecraft:stone"]"#));
+        assert!(matches!(read(&palette), Err(Error::Data)));
+    }
+}